@@ -0,0 +1,27 @@
+use thiserror::Error;
+
+/// Errors returned by the client.
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("Environment variable {0} is not set")]
+    MissingEnvVar(&'static str),
+    #[error("Invalid client configuration: {0}")]
+    InvalidConfig(String),
+    #[error("Failed to set up the connection to Gamayun: {0}")]
+    Transport(#[from] tonic::transport::Error),
+    /// Boxed, as `Status` is large enough to bloat every `Result` of the client otherwise.
+    #[error("Gamayun rejected the request: {0}")]
+    Status(Box<tonic::Status>),
+    #[error("The outcome of this run has already been reported")]
+    AlreadyReported,
+    #[error("Failed to start a runtime for reporting: {0}")]
+    Runtime(#[from] std::io::Error),
+}
+
+impl From<tonic::Status> for ClientError {
+    fn from(status: tonic::Status) -> Self {
+        ClientError::Status(Box::new(status))
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ClientError>;
@@ -0,0 +1,90 @@
+use crate::error::{ClientError, Result};
+use crate::run::GamayunRun;
+use std::ops::Deref;
+use tokio::runtime::Handle;
+use tracing::{error, warn};
+
+const UNREPORTED_MESSAGE: &str = "The job finished without reporting its outcome";
+const PANICKED_MESSAGE: &str = "The job panicked before reporting its outcome";
+
+/// Reports an error for the run when it's dropped before the outcome was reported, so Gamayun
+/// learns about jobs that panicked or returned early instead of waiting for their results
+/// until the run is overdue.
+///
+/// Derefs to the `GamayunRun`, reporting works the same way through the guard.
+///
+/// Dropping the guard doesn't block. Inside a Tokio runtime, the error is reported by a task
+/// on that runtime and is lost if the runtime shuts down first, as it does when `main`
+/// returns. Call `finish` at the end of the job to wait for the report.
+pub struct RunGuard {
+    run: GamayunRun,
+}
+
+impl RunGuard {
+    pub(crate) fn new(run: GamayunRun) -> Self {
+        RunGuard { run }
+    }
+
+    /// Reports an error if the run hasn't been reported, and waits until the report is sent.
+    pub async fn finish(self) -> Result<()> {
+        if !self.run.is_reported() {
+            self.run.report_error(UNREPORTED_MESSAGE).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Deref for RunGuard {
+    type Target = GamayunRun;
+
+    fn deref(&self) -> &Self::Target {
+        &self.run
+    }
+}
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        if self.run.is_reported() {
+            return;
+        }
+
+        let message = if std::thread::panicking() {
+            PANICKED_MESSAGE
+        } else {
+            UNREPORTED_MESSAGE
+        };
+        warn!(
+            "Run {} of job {} was not reported: {}",
+            self.run.run_information().run_id,
+            self.run.run_information().job_name,
+            message
+        );
+
+        // Drop can't await, and the runtime of the job might be shutting down, so report with a
+        // new connection
+        let config = self.run.config().clone();
+        let report = async move { GamayunRun::new(config)?.report_error(message).await };
+
+        match Handle::try_current() {
+            // Blocking here would block a thread of the runtime, report in the background
+            Ok(handle) => {
+                handle.spawn(async move {
+                    if let Err(e) = report.await {
+                        error!("Failed to report the error of the unreported run: {}", e);
+                    }
+                });
+            }
+            // Not on a runtime, so there's nothing to block, report on a temporary one
+            Err(_) => {
+                let outcome = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .map_err(ClientError::from)
+                    .and_then(|runtime| runtime.block_on(report));
+                if let Err(e) = outcome {
+                    error!("Failed to report the error of the unreported run: {}", e);
+                }
+            }
+        }
+    }
+}
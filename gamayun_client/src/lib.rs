@@ -0,0 +1,25 @@
+//! Client for jobs reporting their outcome to Gamayun.
+//!
+//! A job started by Gamayun creates its run with `GamayunRun::from_env()` and reports exactly
+//! once, with `report`, `report_typed`, `report_empty`, `report_error` or
+//! `report_detailed_error`. Jobs that collect results in parts submit each part with `submit`
+//! and report with `complete`, `complete_partial` or `complete_failed` at the end. Calling
+//! `guard()` on the run makes sure an error is reported if the job panics or returns without
+//! reporting, `finish()` on the guard waits for that report. Screenshots, raw pages and other
+//! files can be attached to the run with `upload_artifact` before it reports.
+//!
+//! Failed calls are retried with backoff while Gamayun is unreachable, and large reports are
+//! streamed to Gamayun in batches.
+
+mod error;
+mod guard;
+mod retry;
+mod run;
+
+pub use error::{ClientError, Result};
+pub use guard::RunGuard;
+pub use retry::RetryPolicy;
+pub use run::{ErrorReport, GamayunRun, GamayunRunBuilder, DEFAULT_BATCH_SIZE, DEFAULT_SERVER_URL};
+
+/// Generated protocol types, for building typed results and reading report summaries.
+pub use protos::gamayun as proto;
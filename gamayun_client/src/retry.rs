@@ -0,0 +1,107 @@
+use std::future::Future;
+use std::time::Duration;
+use tonic::{Code, Status};
+use tracing::warn;
+
+/// How failed calls to Gamayun are retried.
+///
+/// Only failures that are likely to be transient are retried, such as Gamayun being
+/// unreachable or restarting. Reports of results are only retried if they didn't reach
+/// Gamayun, as it might have stored them before failing. The backoff doubles after every
+/// attempt, up to `max_backoff`.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry.
+    pub initial_backoff: Duration,
+    /// Upper bound of the delay between two attempts.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that makes a single attempt.
+    pub fn no_retry() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        }
+    }
+}
+
+fn is_transient(status: &Status, idempotent: bool) -> bool {
+    match status.code() {
+        // The request didn't reach Gamayun
+        Code::Unavailable => true,
+        // Gamayun might have handled the request before failing
+        Code::DeadlineExceeded | Code::ResourceExhausted | Code::Aborted => idempotent,
+        _ => false,
+    }
+}
+
+/// Runs `operation` until it succeeds, fails with a permanent error or runs out of attempts.
+pub(crate) async fn with_retry<T, F, Fut>(
+    policy: &RetryPolicy,
+    operation_name: &str,
+    operation: F,
+) -> Result<T, Status>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Status>>,
+{
+    retry(policy, operation_name, true, operation).await
+}
+
+/// Runs an operation that mustn't be repeated once Gamayun handled it, such as storing
+/// results, retrying only if the request didn't reach Gamayun.
+pub(crate) async fn with_retry_unless_received<T, F, Fut>(
+    policy: &RetryPolicy,
+    operation_name: &str,
+    operation: F,
+) -> Result<T, Status>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Status>>,
+{
+    retry(policy, operation_name, false, operation).await
+}
+
+async fn retry<T, F, Fut>(
+    policy: &RetryPolicy,
+    operation_name: &str,
+    idempotent: bool,
+    mut operation: F,
+) -> Result<T, Status>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Status>>,
+{
+    let mut backoff = policy.initial_backoff;
+    let mut attempt = 1;
+
+    loop {
+        match operation().await {
+            Ok(value) => return Ok(value),
+            Err(status) if is_transient(&status, idempotent) && attempt < policy.max_attempts => {
+                warn!(
+                    "{} failed on attempt {} of {}, retrying in {:?}: {}",
+                    operation_name, attempt, policy.max_attempts, backoff, status
+                );
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(policy.max_backoff);
+                attempt += 1;
+            }
+            Err(status) => return Err(status),
+        }
+    }
+}
@@ -0,0 +1,571 @@
+use crate::error::{ClientError, Result};
+use crate::guard::RunGuard;
+use crate::retry::{with_retry, with_retry_unless_received, RetryPolicy};
+use protos::gamayun::result_reporting_service_client::ResultReportingServiceClient;
+use protos::gamayun::{
+    ArtifactChunk, ArtifactInfo, ErrorCategory, JobError, JobResult, MapResult,
+    ReportResultResponse, ResultChunk, RunCompletion, RunInformation, RunStatus, TypedMapResult,
+    TypedValue,
+};
+use std::collections::HashMap;
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::service::interceptor::InterceptedService;
+use tonic::service::Interceptor;
+use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
+use tonic::{Request, Status};
+use tracing::info;
+
+/// Address of the gRPC server when Gamayun runs with its defaults.
+pub const DEFAULT_SERVER_URL: &str = "http://[::1]:16656";
+/// Number of results sent per message when reporting.
+pub const DEFAULT_BATCH_SIZE: usize = 1000;
+
+/// Size of the chunks artifacts are uploaded in.
+const ARTIFACT_CHUNK_SIZE: usize = 1024 * 1024;
+
+const RUN_ID_METADATA_KEY: &str = "x-gamayun-run-id";
+const TOKEN_METADATA_KEY: &str = "x-gamayun-token";
+
+/// Everything needed to connect to Gamayun and report for one run.
+#[derive(Debug, Clone)]
+pub(crate) struct RunConfig {
+    run_information: RunInformation,
+    server_url: String,
+    token: Option<String>,
+    tls_config: Option<ClientTlsConfig>,
+    batch_size: usize,
+    retry_policy: RetryPolicy,
+}
+
+/// Builder for a `GamayunRun`, for jobs that aren't started by Gamayun or need to change the
+/// defaults.
+#[derive(Debug, Clone)]
+pub struct GamayunRunBuilder {
+    config: RunConfig,
+}
+
+impl GamayunRunBuilder {
+    /// URL of the Gamayun gRPC server, defaults to `DEFAULT_SERVER_URL`.
+    pub fn server_url(mut self, server_url: impl Into<String>) -> Self {
+        self.config.server_url = server_url.into();
+        self
+    }
+
+    /// Token of the run, or one of Gamayun's static tokens.
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.config.token = Some(token.into());
+        self
+    }
+
+    /// TLS configuration used for `https://` server URLs, defaults to the system roots.
+    pub fn tls_config(mut self, tls_config: ClientTlsConfig) -> Self {
+        self.config.tls_config = Some(tls_config);
+        self
+    }
+
+    /// Number of results sent per message. Reports with more results are streamed in batches.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.config.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.config.retry_policy = retry_policy;
+        self
+    }
+
+    /// Creates the run. The connection is established on the first report.
+    pub fn build(self) -> Result<GamayunRun> {
+        GamayunRun::new(self.config)
+    }
+}
+
+/// An error with its category and details, for `GamayunRun::report_detailed_error`.
+#[derive(Debug, Clone)]
+pub struct ErrorReport {
+    message: String,
+    category: ErrorCategory,
+    retryable: bool,
+    stack_trace: Option<String>,
+    context: HashMap<String, String>,
+}
+
+impl ErrorReport {
+    /// Creates a report of an error of unknown category that isn't retryable.
+    pub fn new(message: impl ToString) -> Self {
+        ErrorReport {
+            message: message.to_string(),
+            category: ErrorCategory::Unknown,
+            retryable: false,
+            stack_trace: None,
+            context: HashMap::new(),
+        }
+    }
+
+    pub fn category(mut self, category: ErrorCategory) -> Self {
+        self.category = category;
+        self
+    }
+
+    /// Whether running the job again might succeed, for example after a timeout.
+    pub fn retryable(mut self, retryable: bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    pub fn stack_trace(mut self, stack_trace: impl Into<String>) -> Self {
+        self.stack_trace = Some(stack_trace.into());
+        self
+    }
+
+    /// Adds a detail to the report, such as the URL that failed.
+    pub fn context(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.context.insert(key.into(), value.into());
+        self
+    }
+}
+
+/// Adds the run ID and the token to every request, so Gamayun can authenticate the run.
+#[derive(Clone)]
+struct RunAuthInterceptor {
+    run_id: MetadataValue<Ascii>,
+    token: Option<MetadataValue<Ascii>>,
+}
+
+impl Interceptor for RunAuthInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> std::result::Result<Request<()>, Status> {
+        if let Some(token) = &self.token {
+            request
+                .metadata_mut()
+                .insert(RUN_ID_METADATA_KEY, self.run_id.clone());
+            request
+                .metadata_mut()
+                .insert(TOKEN_METADATA_KEY, token.clone());
+        }
+        Ok(request)
+    }
+}
+
+type Client = ResultReportingServiceClient<InterceptedService<Channel, RunAuthInterceptor>>;
+
+/// A single run of a job, reporting its outcome to Gamayun.
+///
+/// Each run reports exactly once: with its results, with no results, or with an error. A run
+/// can also submit its results in parts and report once it completes.
+pub struct GamayunRun {
+    config: RunConfig,
+    client: Client,
+    reported: AtomicBool,
+}
+
+impl GamayunRun {
+    /// Creates the run from the environment variables Gamayun and the Gamayun worker set when
+    /// starting a job: `GAMAYUN_JOB_NAME`, `GAMAYUN_JOB_UNIQUE_ID` and, if authentication is
+    /// enabled, `GAMAYUN_JOB_TOKEN`. The server URL is read from `GAMAYUN_SERVER_URL` and
+    /// defaults to `DEFAULT_SERVER_URL`.
+    pub fn from_env() -> Result<Self> {
+        let job_name = env::var("GAMAYUN_JOB_NAME")
+            .map_err(|_| ClientError::MissingEnvVar("GAMAYUN_JOB_NAME"))?;
+        let run_id = env::var("GAMAYUN_JOB_UNIQUE_ID")
+            .map_err(|_| ClientError::MissingEnvVar("GAMAYUN_JOB_UNIQUE_ID"))?;
+
+        let mut builder = Self::builder(job_name, run_id);
+        if let Ok(server_url) = env::var("GAMAYUN_SERVER_URL") {
+            builder = builder.server_url(server_url);
+        }
+        if let Ok(token) = env::var("GAMAYUN_JOB_TOKEN") {
+            builder = builder.token(token);
+        }
+        builder.build()
+    }
+
+    /// Starts building a run with the given job name and run ID.
+    pub fn builder(job_name: impl Into<String>, run_id: impl Into<String>) -> GamayunRunBuilder {
+        GamayunRunBuilder {
+            config: RunConfig {
+                run_information: RunInformation {
+                    run_id: run_id.into(),
+                    job_name: job_name.into(),
+                },
+                server_url: DEFAULT_SERVER_URL.to_string(),
+                token: None,
+                tls_config: None,
+                batch_size: DEFAULT_BATCH_SIZE,
+                retry_policy: RetryPolicy::default(),
+            },
+        }
+    }
+
+    pub(crate) fn new(config: RunConfig) -> Result<Self> {
+        let mut endpoint = Endpoint::from_shared(config.server_url.clone()).map_err(|e| {
+            ClientError::InvalidConfig(format!("Invalid server URL {}: {}", config.server_url, e))
+        })?;
+        if config.server_url.starts_with("https://") {
+            let tls_config = config
+                .tls_config
+                .clone()
+                .unwrap_or_else(|| ClientTlsConfig::new().with_native_roots());
+            endpoint = endpoint.tls_config(tls_config)?;
+        }
+
+        let to_metadata = |value: &str, name: &str| {
+            MetadataValue::try_from(value)
+                .map_err(|_| ClientError::InvalidConfig(format!("Invalid {}", name)))
+        };
+        let interceptor = RunAuthInterceptor {
+            run_id: to_metadata(&config.run_information.run_id, "run ID")?,
+            token: config
+                .token
+                .as_deref()
+                .map(|token| to_metadata(token, "token"))
+                .transpose()?,
+        };
+
+        Ok(GamayunRun {
+            client: ResultReportingServiceClient::with_interceptor(
+                endpoint.connect_lazy(),
+                interceptor,
+            ),
+            config,
+            reported: AtomicBool::new(false),
+        })
+    }
+
+    /// Name of the job and ID of the run.
+    pub fn run_information(&self) -> &RunInformation {
+        &self.config.run_information
+    }
+
+    /// Whether the outcome of the run has been reported, or is being reported.
+    pub fn is_reported(&self) -> bool {
+        self.reported.load(Ordering::SeqCst)
+    }
+
+    /// Wraps the run in a guard that reports an error if the run is dropped without
+    /// reporting, for example because the job panicked or returned early.
+    pub fn guard(self) -> RunGuard {
+        RunGuard::new(self)
+    }
+
+    pub(crate) fn config(&self) -> &RunConfig {
+        &self.config
+    }
+
+    /// Reports string results.
+    ///
+    /// # Returns
+    ///
+    /// What Gamayun did with the results.
+    pub async fn report(
+        &self,
+        results: impl IntoIterator<Item = HashMap<String, String>>,
+    ) -> Result<ReportResultResponse> {
+        let results = results
+            .into_iter()
+            .map(|map_result| MapResult { map_result })
+            .collect();
+        self.reporting(self.send_results(results, Vec::new(), false))
+            .await
+    }
+
+    /// Reports typed results, stored with their native types in MongoDB.
+    ///
+    /// # Returns
+    ///
+    /// What Gamayun did with the results.
+    pub async fn report_typed(
+        &self,
+        results: impl IntoIterator<Item = HashMap<String, TypedValue>>,
+    ) -> Result<ReportResultResponse> {
+        let typed_results = results
+            .into_iter()
+            .map(|typed_map_result| TypedMapResult { typed_map_result })
+            .collect();
+        self.reporting(self.send_results(Vec::new(), typed_results, false))
+            .await
+    }
+
+    /// Submits string results and keeps the run open, so more results can be submitted before
+    /// the run is completed with `complete`, `complete_partial` or `complete_failed`.
+    ///
+    /// # Returns
+    ///
+    /// What Gamayun did with the submitted results.
+    pub async fn submit(
+        &self,
+        results: impl IntoIterator<Item = HashMap<String, String>>,
+    ) -> Result<ReportResultResponse> {
+        let results = results
+            .into_iter()
+            .map(|map_result| MapResult { map_result })
+            .collect();
+        self.submitting(self.send_results(results, Vec::new(), true))
+            .await
+    }
+
+    /// Submits typed results and keeps the run open, like `submit`.
+    ///
+    /// # Returns
+    ///
+    /// What Gamayun did with the submitted results.
+    pub async fn submit_typed(
+        &self,
+        results: impl IntoIterator<Item = HashMap<String, TypedValue>>,
+    ) -> Result<ReportResultResponse> {
+        let typed_results = results
+            .into_iter()
+            .map(|typed_map_result| TypedMapResult { typed_map_result })
+            .collect();
+        self.submitting(self.send_results(Vec::new(), typed_results, true))
+            .await
+    }
+
+    /// Completes a run whose results were submitted, as successful.
+    ///
+    /// # Returns
+    ///
+    /// The totals over all submissions of the run.
+    pub async fn complete(&self) -> Result<ReportResultResponse> {
+        self.complete_with(RunStatus::Success, None).await
+    }
+
+    /// Completes a run that submitted some of its results before it failed.
+    ///
+    /// # Returns
+    ///
+    /// The totals over all submissions of the run.
+    pub async fn complete_partial(&self, error: ErrorReport) -> Result<ReportResultResponse> {
+        self.complete_with(RunStatus::Partial, Some(error)).await
+    }
+
+    /// Completes a run that failed after submitting results.
+    ///
+    /// # Returns
+    ///
+    /// The totals over all submissions of the run.
+    pub async fn complete_failed(&self, error: ErrorReport) -> Result<ReportResultResponse> {
+        self.complete_with(RunStatus::Failed, Some(error)).await
+    }
+
+    /// Attaches a named binary blob, such as a screenshot or the raw page that failed to parse,
+    /// to the run. Artifacts have to be uploaded before the outcome of the run is reported.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the artifact, unique within the run. May only contain ASCII letters,
+    ///   digits, `.`, `_` and `-`.
+    /// * `content_type` - MIME type of the artifact, such as `image/png`.
+    /// * `data` - Contents of the artifact.
+    pub async fn upload_artifact(
+        &self,
+        name: impl Into<String>,
+        content_type: impl Into<String>,
+        data: impl Into<Vec<u8>>,
+    ) -> Result<ArtifactInfo> {
+        let name = name.into();
+        let content_type = content_type.into();
+        let data = data.into();
+
+        let mut chunks: Vec<ArtifactChunk> = data
+            .chunks(ARTIFACT_CHUNK_SIZE)
+            .map(|chunk| ArtifactChunk {
+                run_information: None,
+                name: String::new(),
+                content_type: String::new(),
+                data: chunk.to_vec(),
+            })
+            .collect();
+        if chunks.is_empty() {
+            chunks.push(ArtifactChunk::default());
+        }
+        chunks[0].run_information = Some(self.config.run_information.clone());
+        chunks[0].name = name;
+        chunks[0].content_type = content_type;
+
+        self.submitting(async {
+            let response = with_retry(&self.config.retry_policy, "UploadArtifact", || {
+                let mut client = self.client.clone();
+                let chunks = chunks.clone();
+                async move { client.upload_artifact(tokio_stream::iter(chunks)).await }
+            })
+            .await?;
+            Ok(response.into_inner())
+        })
+        .await
+    }
+
+    async fn complete_with(
+        &self,
+        status: RunStatus,
+        error: Option<ErrorReport>,
+    ) -> Result<ReportResultResponse> {
+        let run_completion = RunCompletion {
+            run_information: Some(self.config.run_information.clone()),
+            status: status.into(),
+            error: error.map(|error| self.job_error(error)),
+        };
+        self.reporting(async {
+            let response = with_retry(&self.config.retry_policy, "CompleteRun", || {
+                let mut client = self.client.clone();
+                let run_completion = run_completion.clone();
+                async move { client.complete_run(run_completion).await }
+            })
+            .await?;
+            Ok(response.into_inner())
+        })
+        .await
+    }
+
+    /// Reports that the run finished without any results.
+    pub async fn report_empty(&self) -> Result<()> {
+        self.reporting(async {
+            let run_information = self.config.run_information.clone();
+            with_retry(&self.config.retry_policy, "ReportNoResult", || {
+                let mut client = self.client.clone();
+                let run_information = run_information.clone();
+                async move { client.report_no_result(run_information).await }
+            })
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Reports that the run failed.
+    pub async fn report_error(&self, error: impl ToString) -> Result<()> {
+        self.report_detailed_error(ErrorReport::new(error)).await
+    }
+
+    /// Reports that the run failed, with the category and details of the error. Gamayun uses
+    /// the category and the retryable flag to pick the severity of the notification and to
+    /// decide whether to retry the run.
+    pub async fn report_detailed_error(&self, error: ErrorReport) -> Result<()> {
+        let job_error = self.job_error(error);
+        self.reporting(async {
+            with_retry(&self.config.retry_policy, "ReportError", || {
+                let mut client = self.client.clone();
+                let job_error = job_error.clone();
+                async move { client.report_error(job_error).await }
+            })
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    fn job_error(&self, error: ErrorReport) -> JobError {
+        JobError {
+            run_information: Some(self.config.run_information.clone()),
+            error: error.message,
+            category: error.category.into(),
+            retryable: error.retryable,
+            stack_trace: error.stack_trace.unwrap_or_default(),
+            context: error.context,
+        }
+    }
+
+    async fn send_results(
+        &self,
+        results: Vec<MapResult>,
+        typed_results: Vec<TypedMapResult>,
+        keep_run_open: bool,
+    ) -> Result<ReportResultResponse> {
+        let batch_size = self.config.batch_size;
+        let result_count = results.len() + typed_results.len();
+
+        let response = if result_count <= batch_size {
+            let job_result = JobResult {
+                run_information: Some(self.config.run_information.clone()),
+                results,
+                typed_results,
+                keep_run_open,
+            };
+            with_retry_unless_received(&self.config.retry_policy, "ReportResult", || {
+                let mut client = self.client.clone();
+                let job_result = job_result.clone();
+                async move { client.report_result(job_result).await }
+            })
+            .await?
+        } else {
+            // Too many results for one message, stream them in batches. A retry sends all
+            // batches again, so it's only done if the stream didn't reach Gamayun.
+            let chunks = self.build_chunks(results, typed_results, keep_run_open);
+            info!(
+                "Streaming {} results in {} batches",
+                result_count,
+                chunks.len()
+            );
+            with_retry_unless_received(&self.config.retry_policy, "StreamResults", || {
+                let mut client = self.client.clone();
+                let chunks = chunks.clone();
+                async move { client.stream_results(tokio_stream::iter(chunks)).await }
+            })
+            .await?
+        };
+        Ok(response.into_inner())
+    }
+
+    fn build_chunks(
+        &self,
+        results: Vec<MapResult>,
+        typed_results: Vec<TypedMapResult>,
+        keep_run_open: bool,
+    ) -> Vec<ResultChunk> {
+        let batch_size = self.config.batch_size;
+        let mut chunks: Vec<ResultChunk> = results
+            .chunks(batch_size)
+            .map(|batch| ResultChunk {
+                run_information: None,
+                results: batch.to_vec(),
+                typed_results: Vec::new(),
+                keep_run_open: false,
+            })
+            .chain(typed_results.chunks(batch_size).map(|batch| ResultChunk {
+                run_information: None,
+                results: Vec::new(),
+                typed_results: batch.to_vec(),
+                keep_run_open: false,
+            }))
+            .collect();
+
+        if let Some(first_chunk) = chunks.first_mut() {
+            first_chunk.run_information = Some(self.config.run_information.clone());
+            first_chunk.keep_run_open = keep_run_open;
+        }
+        chunks
+    }
+
+    /// Makes sure results are only submitted while the run is open.
+    async fn submitting<T>(
+        &self,
+        submission: impl std::future::Future<Output = Result<T>>,
+    ) -> Result<T> {
+        if self.is_reported() {
+            return Err(ClientError::AlreadyReported);
+        }
+        submission.await
+    }
+
+    /// Makes sure the run reports only once. If reporting fails the run counts as not
+    /// reported, so the caller can try again or report an error instead.
+    async fn reporting<T>(
+        &self,
+        report: impl std::future::Future<Output = Result<T>>,
+    ) -> Result<T> {
+        if self
+            .reported
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return Err(ClientError::AlreadyReported);
+        }
+
+        let outcome = report.await;
+        if outcome.is_err() {
+            self.reported.store(false, Ordering::SeqCst);
+        }
+        outcome
+    }
+}
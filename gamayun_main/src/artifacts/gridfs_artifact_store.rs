@@ -0,0 +1,153 @@
+use crate::artifacts::{ArtifactMetadata, ArtifactStore};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::{AsyncReadExt, AsyncWriteExt, TryStreamExt};
+use mongodb::bson::{self, doc, DateTime as BsonDateTime, Document};
+use mongodb::gridfs::{FilesCollectionDocument, GridFsBucket};
+use mongodb::options::GridFsBucketOptions;
+use mongodb::Client;
+use tracing::{info, instrument, warn};
+
+/// Implementation of `ArtifactStore` keeping the artifacts in a MongoDB GridFS bucket, with
+/// the `ArtifactMetadata` of each artifact stored as the metadata of its file.
+pub struct GridFsArtifactStore {
+    bucket: GridFsBucket,
+}
+
+impl GridFsArtifactStore {
+    /// Creates a new `GridFsArtifactStore`.
+    ///
+    /// # Arguments
+    ///
+    /// * `mongo_client` - MongoDB client used to access the bucket.
+    /// * `mongo_db_name` - Name of the database the bucket is in.
+    /// * `bucket_name` - Name of the GridFS bucket.
+    pub fn new(mongo_client: &Client, mongo_db_name: &str, bucket_name: String) -> Self {
+        let bucket = mongo_client.database(mongo_db_name).gridfs_bucket(
+            GridFsBucketOptions::builder()
+                .bucket_name(bucket_name)
+                .build(),
+        );
+        GridFsArtifactStore { bucket }
+    }
+
+    async fn find_files(&self, filter: Document) -> Result<Vec<FilesCollectionDocument>> {
+        self.bucket
+            .find(filter)
+            .await
+            .context("Failed to query artifacts")?
+            .try_collect()
+            .await
+            .context("Failed to read artifacts")
+    }
+
+    fn run_filter(job_name: &str, run_id: &str) -> Document {
+        doc! { "metadata.job_name": job_name, "metadata.run_id": run_id }
+    }
+
+    fn to_metadata(file: &FilesCollectionDocument) -> Option<ArtifactMetadata> {
+        let metadata = file.metadata.clone()?;
+        match bson::from_document(metadata) {
+            Ok(metadata) => Some(metadata),
+            Err(e) => {
+                warn!("Ignoring artifact file {} without metadata: {}", file.id, e);
+                None
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ArtifactStore for GridFsArtifactStore {
+    #[instrument(skip(self, data), fields(name = %metadata.name, run_id = %metadata.run_id))]
+    async fn store(&self, metadata: &ArtifactMetadata, data: Vec<u8>) -> Result<()> {
+        let mut filter = Self::run_filter(&metadata.job_name, &metadata.run_id);
+        filter.insert("metadata.name", &metadata.name);
+        let replaced = self.find_files(filter).await?;
+
+        let mut upload_stream = self
+            .bucket
+            .open_upload_stream(format!(
+                "{}/{}/{}",
+                metadata.job_name, metadata.run_id, metadata.name
+            ))
+            .metadata(bson::to_document(metadata).context("Failed to serialize metadata")?)
+            .await
+            .context("Failed to open GridFS upload stream")?;
+        upload_stream
+            .write_all(&data)
+            .await
+            .context("Failed to upload artifact")?;
+        upload_stream
+            .close()
+            .await
+            .context("Failed to finish artifact upload")?;
+
+        // Only remove the old version once the new one is complete
+        for file in replaced {
+            self.bucket
+                .delete(file.id)
+                .await
+                .context("Failed to delete replaced artifact")?;
+        }
+        Ok(())
+    }
+
+    async fn list(&self, job_name: &str, run_id: &str) -> Result<Vec<ArtifactMetadata>> {
+        let files = self.find_files(Self::run_filter(job_name, run_id)).await?;
+        Ok(files.iter().filter_map(Self::to_metadata).collect())
+    }
+
+    async fn load(
+        &self,
+        job_name: &str,
+        run_id: &str,
+        name: &str,
+    ) -> Result<Option<(ArtifactMetadata, Vec<u8>)>> {
+        let mut filter = Self::run_filter(job_name, run_id);
+        filter.insert("metadata.name", name);
+        let Some(file) = self
+            .bucket
+            .find_one(filter)
+            .await
+            .context("Failed to query artifact")?
+        else {
+            return Ok(None);
+        };
+        let Some(metadata) = Self::to_metadata(&file) else {
+            return Ok(None);
+        };
+
+        let mut data = Vec::with_capacity(file.length as usize);
+        self.bucket
+            .open_download_stream(file.id)
+            .await
+            .context("Failed to open GridFS download stream")?
+            .read_to_end(&mut data)
+            .await
+            .context("Failed to download artifact")?;
+        Ok(Some((metadata, data)))
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_uploaded_before(&self, job_name: &str, before: DateTime<Utc>) -> Result<u64> {
+        let files = self
+            .find_files(doc! {
+                "metadata.job_name": job_name,
+                "uploadDate": { "$lt": BsonDateTime::from_millis(before.timestamp_millis()) },
+            })
+            .await?;
+
+        let mut deleted = 0;
+        for file in files {
+            self.bucket
+                .delete(file.id)
+                .await
+                .context("Failed to delete artifact")?;
+            deleted += 1;
+        }
+        info!("Deleted {} artifacts of job {}", deleted, job_name);
+        Ok(deleted)
+    }
+}
@@ -0,0 +1,198 @@
+use crate::artifacts::{ArtifactMetadata, ArtifactStore};
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::{info, instrument, warn};
+
+const DATA_DIRECTORY: &str = "data";
+const METADATA_DIRECTORY: &str = "metadata";
+
+/// Implementation of `ArtifactStore` keeping the artifacts in a local directory.
+///
+/// Each run gets a directory at `{root}/{job_name}/{run_id}`, holding the contents of its
+/// artifacts in `data/{name}` and their `ArtifactMetadata` in `metadata/{name}.json`.
+pub struct LocalArtifactStore {
+    root: PathBuf,
+}
+
+impl LocalArtifactStore {
+    /// Creates a new `LocalArtifactStore`.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - Directory the artifacts are stored in, created on the first upload.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        LocalArtifactStore { root: root.into() }
+    }
+
+    fn job_directory(&self, job_name: &str) -> Result<PathBuf> {
+        Ok(self.root.join(safe_path_segment(job_name)?))
+    }
+
+    fn run_directory(&self, job_name: &str, run_id: &str) -> Result<PathBuf> {
+        Ok(self
+            .job_directory(job_name)?
+            .join(safe_path_segment(run_id)?))
+    }
+
+    fn metadata_path(run_directory: &Path, name: &str) -> Result<PathBuf> {
+        Ok(run_directory
+            .join(METADATA_DIRECTORY)
+            .join(format!("{}.json", safe_path_segment(name)?)))
+    }
+
+    fn data_path(run_directory: &Path, name: &str) -> Result<PathBuf> {
+        Ok(run_directory
+            .join(DATA_DIRECTORY)
+            .join(safe_path_segment(name)?))
+    }
+
+    async fn read_metadata(path: &Path) -> Result<ArtifactMetadata> {
+        let contents = fs::read(path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_slice(&contents)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Reads the metadata of all artifacts in a run directory, skipping unreadable files.
+    async fn list_run_directory(run_directory: &Path) -> Result<Vec<ArtifactMetadata>> {
+        let mut entries = match fs::read_dir(run_directory.join(METADATA_DIRECTORY)).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).context("Failed to list artifacts"),
+        };
+
+        let mut artifacts = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            match Self::read_metadata(&entry.path()).await {
+                Ok(metadata) => artifacts.push(metadata),
+                Err(e) => warn!("Ignoring artifact metadata: {:?}", e),
+            }
+        }
+        Ok(artifacts)
+    }
+}
+
+/// Makes sure a job name, run ID or artifact name can't escape the directory it's joined to.
+fn safe_path_segment(segment: &str) -> Result<&str> {
+    if segment.is_empty()
+        || segment == "."
+        || segment == ".."
+        || segment.contains(['/', '\\', '\0'])
+    {
+        bail!("{:?} can't be used in an artifact path", segment);
+    }
+    Ok(segment)
+}
+
+async fn remove_file_if_exists(path: &Path) -> Result<()> {
+    match fs::remove_file(path).await {
+        Err(e) if e.kind() != ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to delete {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[async_trait]
+impl ArtifactStore for LocalArtifactStore {
+    #[instrument(skip(self, data), fields(name = %metadata.name, run_id = %metadata.run_id))]
+    async fn store(&self, metadata: &ArtifactMetadata, data: Vec<u8>) -> Result<()> {
+        let run_directory = self.run_directory(&metadata.job_name, &metadata.run_id)?;
+        let data_path = Self::data_path(&run_directory, &metadata.name)?;
+        let metadata_path = Self::metadata_path(&run_directory, &metadata.name)?;
+
+        fs::create_dir_all(run_directory.join(DATA_DIRECTORY))
+            .await
+            .context("Failed to create artifact directory")?;
+        fs::create_dir_all(run_directory.join(METADATA_DIRECTORY))
+            .await
+            .context("Failed to create artifact directory")?;
+
+        // Write to a temporary file first, so a failed upload never leaves half an artifact.
+        // Artifact names can't start with a dot, so the temporary file can't clash with one.
+        let temporary_path = run_directory
+            .join(DATA_DIRECTORY)
+            .join(format!(".{}.partial", metadata.name));
+        fs::write(&temporary_path, data)
+            .await
+            .context("Failed to write artifact")?;
+        fs::rename(&temporary_path, &data_path)
+            .await
+            .context("Failed to move artifact into place")?;
+        fs::write(
+            &metadata_path,
+            serde_json::to_vec(metadata).context("Failed to serialize metadata")?,
+        )
+        .await
+        .context("Failed to write artifact metadata")?;
+        Ok(())
+    }
+
+    async fn list(&self, job_name: &str, run_id: &str) -> Result<Vec<ArtifactMetadata>> {
+        Self::list_run_directory(&self.run_directory(job_name, run_id)?).await
+    }
+
+    async fn load(
+        &self,
+        job_name: &str,
+        run_id: &str,
+        name: &str,
+    ) -> Result<Option<(ArtifactMetadata, Vec<u8>)>> {
+        let run_directory = self.run_directory(job_name, run_id)?;
+        let metadata_path = Self::metadata_path(&run_directory, name)?;
+        if !fs::try_exists(&metadata_path).await.unwrap_or(false) {
+            return Ok(None);
+        }
+
+        let metadata = Self::read_metadata(&metadata_path).await?;
+        let data = fs::read(Self::data_path(&run_directory, name)?)
+            .await
+            .context("Failed to read artifact")?;
+        Ok(Some((metadata, data)))
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_uploaded_before(&self, job_name: &str, before: DateTime<Utc>) -> Result<u64> {
+        let mut run_directories = match fs::read_dir(self.job_directory(job_name)?).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e).context("Failed to list artifact directories"),
+        };
+
+        let mut deleted: u64 = 0;
+        while let Some(entry) = run_directories.next_entry().await? {
+            let run_directory = entry.path();
+            let artifacts = Self::list_run_directory(&run_directory).await?;
+            let artifact_count = artifacts.len();
+
+            let mut deleted_from_run = 0;
+            for artifact in artifacts
+                .into_iter()
+                .filter(|artifact| artifact.uploaded_at < before)
+            {
+                remove_file_if_exists(&Self::data_path(&run_directory, &artifact.name)?).await?;
+                remove_file_if_exists(&Self::metadata_path(&run_directory, &artifact.name)?)
+                    .await?;
+                deleted_from_run += 1;
+            }
+
+            if artifact_count > 0 && deleted_from_run == artifact_count {
+                if let Err(e) = fs::remove_dir_all(&run_directory).await {
+                    warn!(
+                        "Failed to remove artifact directory {}: {}",
+                        run_directory.display(),
+                        e
+                    );
+                }
+            }
+            deleted += deleted_from_run as u64;
+        }
+        info!("Deleted {} artifacts of job {}", deleted, job_name);
+        Ok(deleted)
+    }
+}
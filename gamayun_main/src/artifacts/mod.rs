@@ -0,0 +1,86 @@
+pub(crate) mod gridfs_artifact_store;
+pub(crate) mod local_artifact_store;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Longest artifact name accepted, in bytes.
+const MAX_ARTIFACT_NAME_LENGTH: usize = 255;
+
+/// Describes a binary blob attached to a run, such as a screenshot or the raw page a job failed
+/// to parse.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtifactMetadata {
+    pub job_name: String,
+    pub run_id: String,
+    /// Name of the artifact, unique within the run.
+    pub name: String,
+    pub content_type: String,
+    pub size_bytes: u64,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+/// Defines a trait for storing the artifacts of runs.
+#[async_trait]
+pub trait ArtifactStore: Send + Sync {
+    /// Stores an artifact, replacing an artifact of the run with the same name.
+    ///
+    /// # Arguments
+    ///
+    /// * `metadata` - Description of the artifact, its size must match `data`.
+    /// * `data` - Contents of the artifact.
+    async fn store(&self, metadata: &ArtifactMetadata, data: Vec<u8>) -> Result<()>;
+
+    /// Lists the artifacts of a run.
+    async fn list(&self, job_name: &str, run_id: &str) -> Result<Vec<ArtifactMetadata>>;
+
+    /// Loads an artifact of a run with its contents, `None` if the run has no artifact with
+    /// the given name.
+    async fn load(
+        &self,
+        job_name: &str,
+        run_id: &str,
+        name: &str,
+    ) -> Result<Option<(ArtifactMetadata, Vec<u8>)>>;
+
+    /// Deletes the artifacts of a job uploaded before the given time.
+    ///
+    /// # Returns
+    ///
+    /// The number of deleted artifacts.
+    async fn delete_uploaded_before(&self, job_name: &str, before: DateTime<Utc>) -> Result<u64>;
+}
+
+/// Checks that a name can be used for an artifact, and as a file name by the local directory
+/// backend: it may only contain ASCII letters, digits, `.`, `_` and `-`, and must not start
+/// with a dot.
+///
+/// # Returns
+///
+/// `Result<(), String>` - A description of the problem if the name can't be used.
+pub fn validate_artifact_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("Artifact name must not be empty".to_string());
+    }
+    if name.len() > MAX_ARTIFACT_NAME_LENGTH {
+        return Err(format!(
+            "Artifact name must not be longer than {} bytes",
+            MAX_ARTIFACT_NAME_LENGTH
+        ));
+    }
+    if name.starts_with('.') {
+        return Err("Artifact name must not start with a dot".to_string());
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+    {
+        return Err(format!(
+            "Artifact name {} may only contain ASCII letters, digits, '.', '_' and '-'",
+            name
+        ));
+    }
+    Ok(())
+}
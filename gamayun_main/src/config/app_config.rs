@@ -1,17 +1,338 @@
+use crate::notification::event::{NotificationKind, Severity};
 use anyhow::{Context, Result};
 use config::Config;
 use serde::Deserialize;
+use std::collections::HashMap;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct SendGridConfig {
+    /// Name routing rules refer to the sender by, defaults to `sendgrid`.
+    pub name: Option<String>,
     pub api_key: String,
     pub from_email: String,
     pub to_emails: Vec<String>,
 }
 
+/// How the connection to the SMTP server is secured.
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+pub enum SmtpTlsMode {
+    /// Plain text, meant for local relays and SMTP sinks.
+    None,
+    /// Upgrades a plain connection with STARTTLS, port 587 by default.
+    #[default]
+    StartTls,
+    /// TLS from the start of the connection, port 465 by default.
+    Implicit,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SmtpConfig {
+    /// Name routing rules refer to the sender by, defaults to `smtp`.
+    pub name: Option<String>,
+    pub host: String,
+    /// Port of the server, the default of the TLS mode when not set.
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub tls: SmtpTlsMode,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from_email: String,
+    pub to_emails: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ChatWebhookConfig {
+    /// Name routing rules refer to the sender by, defaults to the chat system:
+    /// `slack`, `discord`, `mattermost` or `teams`.
+    pub name: Option<String>,
+    /// Incoming webhook URL generated by the chat system.
+    pub webhook_url: String,
+    /// Replaces the scheme, host and port of `webhook_url`, e.g. to post to a local stub.
+    pub base_url: Option<String>,
+    /// Name the messages are posted as, not supported by Microsoft Teams.
+    pub username: Option<String>,
+}
+
+const DEFAULT_WEBHOOK_TIMEOUT_SECONDS: u64 = 10;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct WebhookConfig {
+    /// Name routing rules refer to the sender by, defaults to `webhook`.
+    pub name: Option<String>,
+    pub url: String,
+    /// Extra headers sent with every request.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Secret the payloads are signed with using HMAC-SHA256, unsigned when not set.
+    pub secret: Option<String>,
+    /// Header carrying the signature, defaults to `X-Gamayun-Signature`.
+    pub signature_header: Option<String>,
+    /// Timeout of a single request, defaults to 10 seconds. Failed requests are retried as
+    /// configured in `notification_delivery`.
+    pub timeout_seconds: Option<u64>,
+}
+
+impl WebhookConfig {
+    pub fn timeout_seconds(&self) -> u64 {
+        self.timeout_seconds
+            .unwrap_or(DEFAULT_WEBHOOK_TIMEOUT_SECONDS)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TelegramConfig {
+    /// Name routing rules refer to the sender by, defaults to `telegram`.
+    pub name: Option<String>,
+    pub bot_token: String,
+    /// Chats the bot posts to, numeric IDs or `@channelname`, as strings.
+    pub chat_ids: Vec<String>,
+    /// Base URL of the Bot API, defaults to `https://api.telegram.org`.
+    pub api_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct NtfyConfig {
+    /// Name routing rules refer to the sender by, defaults to `ntfy`.
+    pub name: Option<String>,
+    /// Base URL of the ntfy server, defaults to `https://ntfy.sh`.
+    pub server_url: Option<String>,
+    pub topic: String,
+    /// Priority from 1 (min) to 5 (max) of all notifications, picked by the severity of each
+    /// notification when not set.
+    pub priority: Option<u8>,
+    /// Access token for topics that require authentication.
+    pub access_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GrpcAuthConfig {
+    /// Reject reports that don't carry the token Gamayun generated for their run.
+    #[serde(default)]
+    pub require_run_token: bool,
+    /// Tokens accepted for any job, for reporters that are not started by Gamayun.
+    #[serde(default)]
+    pub static_tokens: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TlsConfig {
+    /// PEM file with the server certificate chain.
+    pub cert_path: String,
+    /// PEM file with the private key of the server certificate.
+    pub key_path: String,
+    /// PEM file with the CAs client certificates must chain to. Enables mutual TLS when set.
+    pub client_ca_path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub enum ArtifactBackend {
+    GridFs,
+    LocalDirectory,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ArtifactStorageConfig {
+    pub backend: ArtifactBackend,
+    /// Directory artifacts are stored in, required by the `LocalDirectory` backend.
+    #[serde(default)]
+    pub directory: Option<String>,
+    /// Name of the GridFS bucket, defaults to `artifacts`.
+    #[serde(default)]
+    pub bucket_name: Option<String>,
+}
+
+/// Rule sending matching notifications to some of the senders. A rule matches a notification
+/// when all of its set conditions do.
+#[derive(Debug, Deserialize, Clone)]
+pub struct NotificationRoute {
+    /// Glob patterns of job names, such as `real_estate_*`. Notifications that are not about a
+    /// job only match rules without `jobs` and `tags`.
+    #[serde(default)]
+    pub jobs: Vec<String>,
+    /// Matches jobs with at least one of the tags.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Kinds of notifications, such as `JobError` or `Overdue`.
+    #[serde(default)]
+    pub kinds: Vec<NotificationKind>,
+    /// Lowest severity of matching notifications.
+    pub min_severity: Option<Severity>,
+    /// Names of the senders notified, all senders when empty.
+    #[serde(default)]
+    pub senders: Vec<String>,
+    /// Recipients e-mail senders send to instead of their configured `to_emails`.
+    pub to_emails: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct NotificationRoutingConfig {
+    /// Every matching rule is applied.
+    #[serde(default)]
+    pub routes: Vec<NotificationRoute>,
+    /// Names of the senders notified when no rule matches, all senders when not set.
+    pub default_senders: Option<Vec<String>>,
+}
+
+const DEFAULT_DEDUP_WINDOW_SECONDS: u64 = 3600;
+const DEFAULT_DIGEST_CRON: &str = "0 0 * * * *";
+const DEFAULT_RATE_LIMIT_PERIOD_SECONDS: u64 = 3600;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SenderRateLimitConfig {
+    /// Name of the senders the limit applies to, together.
+    pub sender: String,
+    /// Number of notifications sent in a period, the rest are counted in the digest.
+    pub max_notifications: u32,
+    /// Length of the period, defaults to an hour.
+    pub period_seconds: Option<u64>,
+}
+
+impl SenderRateLimitConfig {
+    pub fn period_seconds(&self) -> u64 {
+        self.period_seconds
+            .unwrap_or(DEFAULT_RATE_LIMIT_PERIOD_SECONDS)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct NotificationThrottlingConfig {
+    /// How long repeats of a notification are suppressed after it was sent, defaults to an
+    /// hour. Repeats are notifications about the same job, of the same kind and with the same
+    /// error.
+    pub dedup_window_seconds: Option<u64>,
+    /// When suppressed notifications are summarized in a digest, defaults to every hour.
+    pub digest_cron: Option<String>,
+    #[serde(default)]
+    pub rate_limits: Vec<SenderRateLimitConfig>,
+    /// Notify when a job succeeds after it failed, defaults to `true`.
+    pub notify_recovery: Option<bool>,
+}
+
+impl NotificationThrottlingConfig {
+    pub fn dedup_window_seconds(&self) -> u64 {
+        self.dedup_window_seconds
+            .unwrap_or(DEFAULT_DEDUP_WINDOW_SECONDS)
+    }
+
+    pub fn digest_cron(&self) -> String {
+        self.digest_cron
+            .clone()
+            .unwrap_or_else(|| DEFAULT_DIGEST_CRON.to_string())
+    }
+
+    pub fn notify_recovery(&self) -> bool {
+        self.notify_recovery.unwrap_or(true)
+    }
+}
+
+const DEFAULT_DELIVERY_MAX_ATTEMPTS: u32 = 8;
+const DEFAULT_DELIVERY_INITIAL_BACKOFF_SECONDS: u64 = 30;
+const DEFAULT_DELIVERY_MAX_BACKOFF_SECONDS: u64 = 3600;
+const DEFAULT_MAX_DEAD_LETTERS: usize = 1000;
+
+/// Retries of notifications that couldn't be delivered. Pending and dead-lettered
+/// notifications are kept in the `notification_outbox` collection of the Gamayun database,
+/// so they survive restarts.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct NotificationDeliveryConfig {
+    /// Attempts per sender before a notification is dead-lettered, defaults to 8.
+    pub max_attempts: Option<u32>,
+    /// Delay before the first retry, doubled after every attempt, defaults to 30 seconds.
+    pub initial_backoff_seconds: Option<u64>,
+    /// Upper bound of the delay between two attempts, defaults to an hour.
+    pub max_backoff_seconds: Option<u64>,
+    /// Number of dead-lettered notifications kept, the oldest are dropped first. Defaults
+    /// to 1000.
+    pub max_dead_letters: Option<usize>,
+}
+
+impl NotificationDeliveryConfig {
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+            .unwrap_or(DEFAULT_DELIVERY_MAX_ATTEMPTS)
+            .max(1)
+    }
+
+    pub fn initial_backoff_seconds(&self) -> u64 {
+        self.initial_backoff_seconds
+            .unwrap_or(DEFAULT_DELIVERY_INITIAL_BACKOFF_SECONDS)
+    }
+
+    pub fn max_backoff_seconds(&self) -> u64 {
+        self.max_backoff_seconds
+            .unwrap_or(DEFAULT_DELIVERY_MAX_BACKOFF_SECONDS)
+    }
+
+    pub fn max_dead_letters(&self) -> usize {
+        self.max_dead_letters.unwrap_or(DEFAULT_MAX_DEAD_LETTERS)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct NotificationTemplatesConfig {
+    /// Directory with the templates, relative to the configuration root. Defaults to
+    /// `notification_templates`.
+    pub directory: Option<String>,
+    /// URL the HTTP API is reachable at, such as `https://gamayun.example.com`. Notifications
+    /// link to the job and run when set.
+    pub api_base_url: Option<String>,
+}
+
+const DEFAULT_RESULT_BATCH_SIZE: usize = 1000;
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct AppConfig {
     pub sendgrid_config: Option<SendGridConfig>,
+    /// E-mail notifications through an SMTP server, such as a company relay.
+    pub smtp_config: Option<SmtpConfig>,
+    /// Slack incoming webhooks notifications are posted to.
+    #[serde(default)]
+    pub slack_webhooks: Vec<ChatWebhookConfig>,
+    /// Discord webhooks notifications are posted to.
+    #[serde(default)]
+    pub discord_webhooks: Vec<ChatWebhookConfig>,
+    /// Mattermost incoming webhooks notifications are posted to.
+    #[serde(default)]
+    pub mattermost_webhooks: Vec<ChatWebhookConfig>,
+    /// Microsoft Teams incoming webhooks or workflows notifications are posted to.
+    #[serde(default)]
+    pub teams_webhooks: Vec<ChatWebhookConfig>,
+    /// Push notifications through a Telegram bot.
+    pub telegram_config: Option<TelegramConfig>,
+    /// Push notifications through an ntfy topic.
+    pub ntfy_config: Option<NtfyConfig>,
+    /// HTTP endpoints notifications are POSTed to as JSON events.
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+    /// Which senders get which notifications, every sender gets every notification when not
+    /// set.
+    pub notification_routing: Option<NotificationRoutingConfig>,
+    /// Deduplication, rate limits and digests of notifications, every notification is sent
+    /// when not set.
+    pub notification_throttling: Option<NotificationThrottlingConfig>,
+    /// Retries of failed notifications and where pending ones are kept, retried in memory
+    /// with the defaults when not set.
+    pub notification_delivery: Option<NotificationDeliveryConfig>,
+    /// Templates of the notification subjects and bodies.
+    pub notification_templates: Option<NotificationTemplatesConfig>,
+    /// Number of results looked up and written to MongoDB together.
+    pub result_batch_size: Option<usize>,
+    /// Maximum size of a single gRPC message accepted from jobs, tonic's default is 4 MB.
+    pub grpc_max_message_size_bytes: Option<usize>,
+    /// Authentication of jobs reporting to the gRPC service.
+    pub grpc_auth: Option<GrpcAuthConfig>,
+    /// TLS for the gRPC and HTTP servers, both serve plain text when not set.
+    pub tls: Option<TlsConfig>,
+    /// Where run artifacts are stored, a GridFS bucket in the Gamayun database when not set.
+    pub artifact_storage: Option<ArtifactStorageConfig>,
+}
+
+impl AppConfig {
+    pub fn result_batch_size(&self) -> usize {
+        self.result_batch_size
+            .unwrap_or(DEFAULT_RESULT_BATCH_SIZE)
+            .max(1)
+    }
 }
 
 pub fn initialize_app_config(config_root: String) -> Result<AppConfig> {
@@ -1,23 +1,161 @@
-use anyhow::Result;
-use serde::Deserialize;
+use crate::config::result_schema::ResultSchema;
+use crate::notification::event::Severity;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::io::Read;
 use std::path::Path;
-use tracing::info;
+use std::time::Duration;
+use tracing::{info, warn};
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub enum OnDuplicateEntry {
     IgnoreNew,
     Overwrite,
     TrackChanges,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct DuplicateEntryPolicy {
     pub unique_ids: Vec<String>,
     pub on_duplicate_entry: OnDuplicateEntry,
 }
 
+#[derive(Debug, Deserialize, Clone)]
+pub struct RetentionPolicy {
+    /// Results older than this are deleted by MongoDB through a TTL index.
+    #[serde(default)]
+    pub max_age_days: Option<u64>,
+
+    /// Maximum number of results kept for the job; the oldest ones are deleted first.
+    #[serde(default)]
+    pub max_documents: Option<u64>,
+
+    /// Number of versions kept per unique key when the job uses the `TrackChanges` policy.
+    /// Older versions are merged into the oldest kept version.
+    #[serde(default)]
+    pub keep_latest_versions: Option<u64>,
+}
+
+const DEFAULT_MAX_ARTIFACT_SIZE_BYTES: u64 = 10 * 1024 * 1024;
+const DEFAULT_MAX_RUN_ARTIFACTS_SIZE_BYTES: u64 = 50 * 1024 * 1024;
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ArtifactLimits {
+    /// Largest artifact a run can upload, defaults to 10 MB.
+    #[serde(default)]
+    pub max_artifact_size_bytes: Option<u64>,
+
+    /// Largest total size of the artifacts of a single run, defaults to 50 MB.
+    #[serde(default)]
+    pub max_run_size_bytes: Option<u64>,
+}
+
+impl ArtifactLimits {
+    pub fn max_artifact_size_bytes(&self) -> u64 {
+        self.max_artifact_size_bytes
+            .unwrap_or(DEFAULT_MAX_ARTIFACT_SIZE_BYTES)
+    }
+
+    pub fn max_run_size_bytes(&self) -> u64 {
+        self.max_run_size_bytes
+            .unwrap_or(DEFAULT_MAX_RUN_ARTIFACTS_SIZE_BYTES)
+    }
+}
+
+/// Where notifications about a job are sent, replacing the routing rules of the application
+/// configuration.
+#[derive(Debug, Deserialize, Clone)]
+pub struct JobNotificationTarget {
+    /// Names of the senders notified, all senders when empty.
+    #[serde(default)]
+    pub senders: Vec<String>,
+    /// Recipients e-mail senders send to instead of their configured `to_emails`.
+    #[serde(default)]
+    pub to_emails: Option<Vec<String>>,
+    /// Notifications below this severity are not sent.
+    #[serde(default)]
+    pub min_severity: Option<Severity>,
+}
+
+const DEFAULT_MAX_NOTIFIED_ITEMS: usize = 50;
+
+/// Notifications about the results of a job, one per run listing all new and changed items.
+/// Templates get the items as `items`, each with its `status`, `fields` and, for changed
+/// items, the `previous` values of the changed fields.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ResultNotificationConfig {
+    /// Notify about results with a unique key that wasn't stored before.
+    #[serde(default)]
+    pub on_new_item: bool,
+    /// Notify about results the `TrackChanges` policy found changed fields in.
+    #[serde(default)]
+    pub on_change: bool,
+    /// Most items listed in a notification, defaults to 50. The rest are only counted.
+    #[serde(default)]
+    pub max_items: Option<usize>,
+}
+
+impl ResultNotificationConfig {
+    pub fn max_items(&self) -> usize {
+        self.max_items.unwrap_or(DEFAULT_MAX_NOTIFIED_ITEMS)
+    }
+}
+
+/// Category of an error reported by a job.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    Network,
+    Parse,
+    Auth,
+    RateLimited,
+    Unknown,
+}
+
+const DEFAULT_RETRY_DELAY_SECONDS: u64 = 60;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct JobRetryPolicy {
+    /// How many times a run is retried after reporting a retryable error.
+    pub max_retries: u32,
+
+    /// Delay before the first retry, doubled for every following retry. Defaults to 60 seconds.
+    #[serde(default)]
+    pub retry_delay_seconds: Option<u64>,
+
+    /// Error categories that are retried, all categories when not set.
+    #[serde(default)]
+    pub retry_on: Option<Vec<ErrorCategory>>,
+}
+
+impl JobRetryPolicy {
+    /// Returns the delay before the given retry, or `None` if an error of the given category
+    /// reported by the given attempt isn't retried.
+    ///
+    /// # Arguments
+    ///
+    /// * `category` - Category of the reported error.
+    /// * `attempt` - The attempt that reported the error, starting at 1.
+    pub fn retry_delay(&self, category: ErrorCategory, attempt: u32) -> Option<Duration> {
+        if attempt > self.max_retries {
+            return None;
+        }
+        if let Some(retry_on) = &self.retry_on {
+            if !retry_on.contains(&category) {
+                return None;
+            }
+        }
+
+        let base_delay = self
+            .retry_delay_seconds
+            .unwrap_or(DEFAULT_RETRY_DELAY_SECONDS);
+        let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+        Some(Duration::from_secs(base_delay.saturating_mul(factor)))
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct JobConfig {
     /// The name of the job, which must be unique.
@@ -30,6 +168,11 @@ pub struct JobConfig {
     #[serde(default)]
     pub arguments: Vec<String>,
 
+    /// Labels a remote worker must have to run the job. The job is started on the Gamayun
+    /// host when not set.
+    #[serde(default)]
+    pub run_on: Option<HashMap<String, String>>,
+
     /// Cron string for the job.
     pub cron_string: String,
 
@@ -48,12 +191,65 @@ pub struct JobConfig {
     /// Duplicate entry policy.
     #[serde(default)]
     pub duplicate_entry_policy: Option<DuplicateEntryPolicy>,
+
+    /// Retries of runs that report a retryable error.
+    #[serde(default)]
+    pub retry: Option<JobRetryPolicy>,
+
+    /// Schema results are validated and coerced against before storage.
+    #[serde(default)]
+    pub result_schema: Option<ResultSchema>,
+
+    /// Retention policy for stored results. `max_age_days` applies to artifacts as well.
+    #[serde(default)]
+    pub retention: Option<RetentionPolicy>,
+
+    /// Size limits of the artifacts runs of the job upload.
+    #[serde(default)]
+    pub artifact_limits: Option<ArtifactLimits>,
+
+    /// Where notifications about the job are sent, the routing rules decide when not set.
+    #[serde(default)]
+    pub notify: Option<JobNotificationTarget>,
+
+    /// Notifications about new and changed results, not sent when not set.
+    #[serde(default)]
+    pub notify_on_results: Option<ResultNotificationConfig>,
+
+    /// Database the results are stored in, defaults to the Gamayun database.
+    /// Supports the same placeholders as `collection`.
+    #[serde(default)]
+    pub database: Option<String>,
+
+    /// Collection the results are stored in, defaults to `{job_name}`.
+    /// Supports the `{job_name}`, `{tag}` (first tag of the job), `{yyyy}`, `{mm}`, `{dd}`,
+    /// `{yyyy_mm}` and `{yyyy_mm_dd}` placeholders.
+    #[serde(default)]
+    pub collection: Option<String>,
+}
+
+/// Database and collection the results of a job are stored in.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StorageTarget {
+    pub database: String,
+    pub collection: String,
 }
 
 impl JobConfig {
-    pub fn load_configs_from_directory(root_path: &str) -> Result<Vec<JobConfig>> {
+    /// Loads all job configurations below the given directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `root_path` - Directory the `*.config.toml` files are searched in.
+    /// * `default_database` - Database the results of jobs without a `database` are stored in.
+    pub fn load_configs_from_directory(
+        root_path: &str,
+        default_database: &str,
+    ) -> Result<Vec<JobConfig>> {
         let configs = Self::recursive_load(Path::new(root_path))?;
 
+        Self::warn_about_shared_collections(&configs, default_database);
+
         info!(
             "Loaded the following job configurations: {}",
             configs
@@ -66,6 +262,108 @@ impl JobConfig {
         Ok(configs)
     }
 
+    /// Returns the duplicate entry policy results of the job are stored with, `TrackChanges`
+    /// without unique IDs if the job doesn't configure one.
+    pub fn effective_duplicate_entry_policy(&self) -> DuplicateEntryPolicy {
+        self.duplicate_entry_policy
+            .clone()
+            .unwrap_or_else(|| DuplicateEntryPolicy {
+                unique_ids: vec![],
+                on_duplicate_entry: OnDuplicateEntry::TrackChanges,
+            })
+    }
+
+    /// Resolves the database and collection the results of the job are stored in at the given
+    /// time, filling in the placeholders of the `database` and `collection` templates.
+    ///
+    /// # Arguments
+    ///
+    /// * `default_database` - Database used when the job doesn't configure one.
+    /// * `now` - Time used for the date-based placeholders.
+    pub fn storage_target(
+        &self,
+        default_database: &str,
+        now: DateTime<Utc>,
+    ) -> Result<StorageTarget> {
+        let database = match &self.database {
+            Some(template) => self.render_storage_template(template, now)?,
+            None => default_database.to_string(),
+        };
+        let collection = match &self.collection {
+            Some(template) => self.render_storage_template(template, now)?,
+            None => self.name.clone(),
+        };
+        Ok(StorageTarget {
+            database,
+            collection,
+        })
+    }
+
+    fn render_storage_template(&self, template: &str, now: DateTime<Utc>) -> Result<String> {
+        let mut rendered = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            rendered.push_str(&rest[..start]);
+            let end = rest[start..]
+                .find('}')
+                .map(|end| start + end)
+                .ok_or_else(|| anyhow::anyhow!("Unclosed placeholder in '{}'", template))?;
+            let value = match &rest[start + 1..end] {
+                "job_name" => self.name.clone(),
+                "tag" => self.tags.first().cloned().ok_or_else(|| {
+                    anyhow::anyhow!("'{}' uses {{tag}}, but the job has no tags", template)
+                })?,
+                "yyyy" => now.format("%Y").to_string(),
+                "mm" => now.format("%m").to_string(),
+                "dd" => now.format("%d").to_string(),
+                "yyyy_mm" => now.format("%Y_%m").to_string(),
+                "yyyy_mm_dd" => now.format("%Y_%m_%d").to_string(),
+                placeholder => {
+                    return Err(anyhow::anyhow!(
+                        "Unknown placeholder {{{}}} in '{}'",
+                        placeholder,
+                        template
+                    ))
+                }
+            };
+            rendered.push_str(&value);
+            rest = &rest[end + 1..];
+        }
+        rendered.push_str(rest);
+
+        if rendered.is_empty() {
+            return Err(anyhow::anyhow!("'{}' renders to an empty name", template));
+        }
+        Ok(rendered)
+    }
+
+    /// Logs a warning for every pair of jobs that store their results into the same collection
+    /// but handle duplicate entries differently.
+    fn warn_about_shared_collections(configs: &[JobConfig], default_database: &str) {
+        let now = Utc::now();
+        let mut jobs_by_target: HashMap<StorageTarget, Vec<&JobConfig>> = HashMap::new();
+        for config in configs {
+            if let Ok(target) = config.storage_target(default_database, now) {
+                jobs_by_target.entry(target).or_default().push(config);
+            }
+        }
+
+        for (target, jobs) in jobs_by_target {
+            for (i, first) in jobs.iter().enumerate() {
+                for second in &jobs[i + 1..] {
+                    if first.effective_duplicate_entry_policy()
+                        != second.effective_duplicate_entry_policy()
+                    {
+                        warn!(
+                            "Jobs {} and {} share the collection {} but have different duplicate entry policies",
+                            first.name, second.name, target.collection
+                        );
+                    }
+                }
+            }
+        }
+    }
+
     fn recursive_load(path: &Path) -> Result<Vec<JobConfig>> {
         let mut configs = Vec::new();
         if path.is_dir() {
@@ -120,7 +418,29 @@ impl JobConfig {
         let contents = contents.replace("${CONFIGURATION_FILE_DIRECTORY}", &parent_dir);
 
         // Parse the TOML configuration
-        let config: JobConfig = toml::from_str(&contents)?;
+        let mut config: JobConfig = toml::from_str(&contents)?;
+
+        if let Some(RetentionPolicy {
+            keep_latest_versions: Some(0),
+            ..
+        }) = &config.retention
+        {
+            return Err(anyhow::anyhow!(
+                "keep_latest_versions for job '{}' must be at least 1",
+                config.name
+            ));
+        }
+
+        config
+            .storage_target("", Utc::now())
+            .with_context(|| format!("Invalid storage target for job '{}'", config.name))?;
+
+        if let Some(result_schema) = &mut config.result_schema {
+            result_schema
+                .compile()
+                .with_context(|| format!("Invalid result schema for job '{}'", config.name))?;
+        }
+
         Ok(config)
     }
 }
@@ -1,2 +1,3 @@
 pub(crate) mod app_config;
 pub(crate) mod job_config;
+pub(crate) mod result_schema;
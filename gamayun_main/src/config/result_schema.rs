@@ -0,0 +1,80 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Deserialize, Clone)]
+pub enum FieldType {
+    String,
+    Number,
+    Boolean,
+    IsoDate,
+    Url,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub enum OnInvalidResult {
+    /// Drop the invalid result.
+    Reject,
+    /// Store the invalid result into the `<job>_rejected` collection.
+    Quarantine,
+    /// Store the result as if it were valid, but log a warning.
+    AcceptWithWarning,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct FieldSchema {
+    /// Whether the field must be present and non-empty.
+    #[serde(default)]
+    pub required: bool,
+
+    /// Type the field value is coerced into before storage.
+    #[serde(default)]
+    pub field_type: Option<FieldType>,
+
+    /// Regular expression string values of the field have to match.
+    #[serde(default)]
+    pub regex: Option<String>,
+
+    /// Value used when the field is missing or empty.
+    #[serde(default)]
+    pub default: Option<String>,
+
+    /// `regex`, compiled when the job configuration is loaded.
+    #[serde(skip)]
+    pub compiled_regex: Option<Regex>,
+}
+
+fn default_rejection_alert_ratio() -> f64 {
+    0.5
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ResultSchema {
+    /// Schema for each of the fields, by field name.
+    #[serde(default)]
+    pub fields: HashMap<String, FieldSchema>,
+
+    /// What to do with results that don't match the schema.
+    pub on_invalid: OnInvalidResult,
+
+    /// Ratio of invalid results in a single run above which a notification is sent.
+    #[serde(default = "default_rejection_alert_ratio")]
+    pub rejection_alert_ratio: f64,
+}
+
+impl ResultSchema {
+    /// Compiles the regular expressions of the schema once, so results aren't validated
+    /// against a schema that is unusable, e.g. because a regular expression doesn't compile.
+    pub fn compile(&mut self) -> Result<()> {
+        for (field_name, field_schema) in &mut self.fields {
+            if let Some(regex) = &field_schema.regex {
+                field_schema.compiled_regex = Some(
+                    Regex::new(regex)
+                        .with_context(|| format!("Invalid regex for field '{}'", field_name))?,
+                );
+            }
+        }
+        Ok(())
+    }
+}
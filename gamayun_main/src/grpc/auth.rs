@@ -0,0 +1,163 @@
+use crate::config::app_config::GrpcAuthConfig;
+use crate::job_scheduling::scheduled_job_tracking_service::ScheduledJobTrackingService;
+use protos::gamayun::RunInformation;
+use subtle::ConstantTimeEq;
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+use tracing::warn;
+
+/// Metadata key holding the ID of the run a report belongs to.
+pub const RUN_ID_METADATA_KEY: &str = "x-gamayun-run-id";
+/// Metadata key holding the run token or one of the static tokens.
+pub const TOKEN_METADATA_KEY: &str = "x-gamayun-token";
+
+/// Compares a token in constant time, so the time a comparison takes doesn't reveal how much
+/// of a guessed token is right.
+pub fn tokens_match(expected: &str, token: &str) -> bool {
+    expected.as_bytes().ct_eq(token.as_bytes()).into()
+}
+
+/// Who sent a report, as established by `ReporterAuthInterceptor`.
+#[derive(Debug, Clone)]
+pub enum AuthenticatedReporter {
+    /// Authentication is disabled.
+    Anyone,
+    /// The reporter knows one of the static tokens and may report for any job.
+    StaticToken,
+    /// The reporter knows the token of the given run and may only report for that run.
+    Run { run_id: String, job_name: String },
+}
+
+impl AuthenticatedReporter {
+    /// Checks that the reporter may report for the given run.
+    #[allow(clippy::result_large_err)]
+    pub fn authorize(&self, run_information: &RunInformation) -> Result<(), Status> {
+        match self {
+            AuthenticatedReporter::Anyone | AuthenticatedReporter::StaticToken => Ok(()),
+            AuthenticatedReporter::Run { run_id, job_name } => {
+                if *run_id == run_information.run_id && *job_name == run_information.job_name {
+                    Ok(())
+                } else {
+                    warn!(
+                        "Token for run {} of job {} used to report for run {} of job {}",
+                        run_id, job_name, run_information.run_id, run_information.job_name
+                    );
+                    Err(Status::permission_denied(
+                        "The token doesn't belong to the reported run",
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Checks that the caller may act as a remote worker. Workers are handed run tokens and
+    /// report for any run, so they always need a static token, even with authentication off.
+    #[allow(clippy::result_large_err)]
+    pub fn authorize_worker(&self) -> Result<(), Status> {
+        match self {
+            AuthenticatedReporter::StaticToken => Ok(()),
+            AuthenticatedReporter::Anyone => {
+                warn!("Rejected a worker request without a static token");
+                Err(Status::unauthenticated(
+                    "Workers have to authenticate with one of the static tokens in grpc_auth",
+                ))
+            }
+            AuthenticatedReporter::Run { run_id, .. } => {
+                warn!("Token for run {} used to call the worker service", run_id);
+                Err(Status::permission_denied(
+                    "Workers have to authenticate with a static token",
+                ))
+            }
+        }
+    }
+
+    /// Returns the reporter stored in the request extensions by the interceptor, or `Anyone`
+    /// if the request didn't go through it.
+    pub fn from_request<T>(request: &Request<T>) -> AuthenticatedReporter {
+        request
+            .extensions()
+            .get::<AuthenticatedReporter>()
+            .cloned()
+            .unwrap_or(AuthenticatedReporter::Anyone)
+    }
+}
+
+/// Interceptor that checks the token in the request metadata against the token Gamayun
+/// generated for the run, or against the configured static tokens.
+#[derive(Clone)]
+pub struct ReporterAuthInterceptor {
+    config: Option<GrpcAuthConfig>,
+    scheduled_job_tracking_service: ScheduledJobTrackingService,
+}
+
+impl ReporterAuthInterceptor {
+    pub fn new(
+        config: Option<GrpcAuthConfig>,
+        scheduled_job_tracking_service: ScheduledJobTrackingService,
+    ) -> Self {
+        Self {
+            config,
+            scheduled_job_tracking_service,
+        }
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn authenticate<T>(&self, request: &Request<T>) -> Result<AuthenticatedReporter, Status> {
+        let Some(config) = &self.config else {
+            return Ok(AuthenticatedReporter::Anyone);
+        };
+
+        let metadata_value = |key: &str| {
+            request
+                .metadata()
+                .get(key)
+                .and_then(|value| value.to_str().ok())
+        };
+
+        let token = metadata_value(TOKEN_METADATA_KEY);
+
+        if let Some(token) = token {
+            if config
+                .static_tokens
+                .iter()
+                .any(|static_token| tokens_match(static_token, token))
+            {
+                return Ok(AuthenticatedReporter::StaticToken);
+            }
+        }
+
+        if !config.require_run_token {
+            return Ok(AuthenticatedReporter::Anyone);
+        }
+
+        let (Some(run_id), Some(token)) = (metadata_value(RUN_ID_METADATA_KEY), token) else {
+            warn!("Rejected a report without a run id or token");
+            return Err(Status::unauthenticated(format!(
+                "The {} and {} metadata are required",
+                RUN_ID_METADATA_KEY, TOKEN_METADATA_KEY
+            )));
+        };
+
+        match self
+            .scheduled_job_tracking_service
+            .verify_run_token(run_id, token)
+        {
+            Some(job_name) => Ok(AuthenticatedReporter::Run {
+                run_id: run_id.to_string(),
+                job_name,
+            }),
+            None => {
+                warn!("Rejected a report with an invalid token for run {}", run_id);
+                Err(Status::unauthenticated("Invalid token for the run"))
+            }
+        }
+    }
+}
+
+impl Interceptor for ReporterAuthInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let reporter = self.authenticate(&request)?;
+        request.extensions_mut().insert(reporter);
+        Ok(request)
+    }
+}
@@ -0,0 +1,141 @@
+use crate::grpc::result_collecting_service::ResultCollectingService;
+use crate::init::AppContext;
+use anyhow::{Context, Result};
+use mongodb::bson::doc;
+use mongodb::Client;
+use protos::gamayun::result_reporting_service_server::ResultReportingServiceServer;
+use std::future::IntoFuture;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tonic::server::NamedService;
+use tonic_health::server::HealthReporter;
+use tonic_health::ServingStatus;
+use tracing::{info, warn};
+
+pub const HEALTH_CHECK_JOB_CATEGORY: &str = "GAMAYUN_HEALTH_CHECK";
+
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+const MONGO_PING_TIMEOUT: Duration = Duration::from_secs(5);
+// The scheduler ticks every 10 seconds, missing a few ticks means it stopped running jobs
+const MAX_SCHEDULER_TICK_AGE: Duration = Duration::from_secs(35);
+
+/// Keeps the serving status of the gRPC health service up to date.
+///
+/// A heartbeat job on the scheduler proves that the scheduler is still running jobs, and
+/// MongoDB is pinged to make sure reported results can be stored. The overall status and the
+/// status of `gamayun.ResultReportingService` are `SERVING` only while both are healthy.
+///
+/// # Arguments
+///
+/// * `app_context` - The application context holding the scheduler and the MongoDB client.
+/// * `health_reporter` - The reporter of the health service that is served.
+/// * `shutdown_token` - Stops the status updates on shutdown.
+pub(crate) fn start_health_reporting(
+    app_context: &AppContext,
+    health_reporter: HealthReporter,
+    shutdown_token: CancellationToken,
+) -> Result<()> {
+    let last_scheduler_tick = Arc::new(Mutex::new(Instant::now()));
+
+    let tick = last_scheduler_tick.clone();
+    app_context
+        .scheduler
+        .schedule_sequential_job(
+            "*/10 * * * * *", // every 10 seconds
+            Some("gRPC Health Check Heartbeat".to_string()),
+            Some(HEALTH_CHECK_JOB_CATEGORY.to_string()),
+            None,
+            move || {
+                let tick = tick.clone();
+                async move {
+                    *tick.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+                }
+            },
+        )
+        .context("Failed to schedule the health check heartbeat")?;
+
+    tokio::spawn(update_serving_status(
+        app_context.mongo_client.clone(),
+        health_reporter,
+        last_scheduler_tick,
+        shutdown_token,
+    ));
+
+    Ok(())
+}
+
+async fn update_serving_status(
+    mongo_client: Client,
+    mut health_reporter: HealthReporter,
+    last_scheduler_tick: Arc<Mutex<Instant>>,
+    shutdown_token: CancellationToken,
+) {
+    // Not serving until the first check passed
+    set_status(&mut health_reporter, ServingStatus::NotServing).await;
+
+    let mut previous_status = None;
+    let mut interval = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = shutdown_token.cancelled() => return,
+        }
+
+        let mongo_healthy = match tokio::time::timeout(
+            MONGO_PING_TIMEOUT,
+            mongo_client
+                .database("admin")
+                .run_command(doc! { "ping": 1 })
+                .into_future(),
+        )
+        .await
+        {
+            Ok(Ok(_)) => true,
+            Ok(Err(e)) => {
+                warn!("Health check: MongoDB ping failed: {:?}", e);
+                false
+            }
+            Err(_) => {
+                warn!("Health check: MongoDB ping timed out");
+                false
+            }
+        };
+
+        let scheduler_tick_age = last_scheduler_tick
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .elapsed();
+        let scheduler_healthy = scheduler_tick_age <= MAX_SCHEDULER_TICK_AGE;
+        if !scheduler_healthy {
+            warn!(
+                "Health check: scheduler hasn't run the heartbeat for {:?}",
+                scheduler_tick_age
+            );
+        }
+
+        let status = if mongo_healthy && scheduler_healthy {
+            ServingStatus::Serving
+        } else {
+            ServingStatus::NotServing
+        };
+
+        if previous_status != Some(status) {
+            info!("gRPC health status changed to {:?}", status);
+            previous_status = Some(status);
+        }
+        set_status(&mut health_reporter, status).await;
+    }
+}
+
+async fn set_status(health_reporter: &mut HealthReporter, status: ServingStatus) {
+    // The empty service name is the overall health of the server
+    health_reporter.set_service_status("", status).await;
+    health_reporter
+        .set_service_status(
+            <ResultReportingServiceServer<ResultCollectingService> as NamedService>::NAME,
+            status,
+        )
+        .await;
+}
@@ -1,33 +1,138 @@
+use crate::grpc::auth::ReporterAuthInterceptor;
+use crate::grpc::health::start_health_reporting;
 use crate::grpc::result_collecting_service::ResultCollectingService;
+use crate::grpc::worker_service::RemoteWorkerService;
 use crate::init::AppContext;
+use crate::tls::accept_tls_connections;
 use anyhow::{Context, Result};
 use protos::gamayun::result_reporting_service_server::ResultReportingServiceServer;
+use protos::gamayun::worker_service_server::WorkerServiceServer;
 use std::env;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
 use tokio_util::sync::CancellationToken;
+use tonic::service::interceptor::InterceptedService;
 use tonic::transport::Server;
 use tracing::info;
 
-mod result_collecting_service;
+pub(crate) mod auth;
+pub(crate) mod health;
+pub(crate) mod result_collecting_service;
+pub(crate) mod worker_service;
+
+/// Reads the gRPC address from the `GAMAYUN_GRPC_ADDR` environment variable or uses the
+/// default.
+fn grpc_address() -> Result<SocketAddr> {
+    env::var("GAMAYUN_GRPC_ADDR")
+        .unwrap_or_else(|_| "[::1]:16656".to_string())
+        .parse()
+        .context("Failed to parse GAMAYUN_GRPC_ADDR")
+}
+
+/// URL jobs started on this machine report their results to. `GAMAYUN_SERVER_URL` overrides
+/// it, for example when the TLS certificate is issued for a host name.
+///
+/// # Arguments
+///
+/// * `tls_enabled` - Whether the gRPC server is served over TLS.
+pub fn local_server_url(tls_enabled: bool) -> Result<String> {
+    if let Ok(server_url) = env::var("GAMAYUN_SERVER_URL") {
+        return Ok(server_url);
+    }
+
+    let mut addr = grpc_address()?;
+    // A server listening on all interfaces is reachable through the loopback interface
+    if addr.ip().is_unspecified() {
+        addr.set_ip(match addr {
+            SocketAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+            SocketAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+        });
+    }
+    let scheme = if tls_enabled { "https" } else { "http" };
+    Ok(format!("{}://{}", scheme, addr))
+}
 
 pub async fn run_grpc_server(
     app_context: AppContext,
     shutdown_token: CancellationToken,
 ) -> Result<()> {
-    // Read gRPC address from environment variable or use default
-    let addr = env::var("GAMAYUN_GRPC_ADDR")
-        .unwrap_or_else(|_| "[::1]:16656".to_string())
-        .parse()
-        .context("Failed to parse GAMAYUN_GRPC_ADDR")?;
+    let addr = grpc_address()?;
+
+    let max_message_size = app_context.app_config.grpc_max_message_size_bytes;
+    let auth_interceptor = ReporterAuthInterceptor::new(
+        app_context.app_config.grpc_auth.clone(),
+        app_context.background_job_completion_scheduler.clone(),
+    );
+    let tls = app_context.tls.clone();
+
+    let (health_reporter, health_service) = tonic_health::server::health_reporter();
+    start_health_reporting(&app_context, health_reporter, shutdown_token.clone())?;
+
+    // Reflection lets tools like grpcurl discover and call the services by hand
+    let reflection_service = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(protos::gamayun::FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+        .build_v1()
+        .context("Failed to build gRPC reflection service")?;
+    let reflection_service_v1alpha = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(protos::gamayun::FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+        .build_v1alpha()
+        .context("Failed to build gRPC reflection service")?;
 
+    let worker_service = RemoteWorkerService::new(
+        app_context.worker_registry.clone(),
+        app_context.background_job_completion_scheduler.clone(),
+    );
     let result_service = ResultCollectingService::new(app_context);
 
-    info!("ResultService listening on {}", addr);
+    let mut result_service_server = ResultReportingServiceServer::new(result_service);
+    if let Some(max_message_size) = max_message_size {
+        result_service_server = result_service_server.max_decoding_message_size(max_message_size);
+    }
+
+    let router = Server::builder()
+        .add_service(InterceptedService::new(
+            result_service_server,
+            auth_interceptor.clone(),
+        ))
+        .add_service(InterceptedService::new(
+            WorkerServiceServer::new(worker_service),
+            auth_interceptor,
+        ))
+        .add_service(health_service)
+        .add_service(reflection_service)
+        .add_service(reflection_service_v1alpha);
+
+    match tls {
+        Some(tls) => {
+            // gRPC requires HTTP/2 to be negotiated
+            let server_config = tls.server_config(vec![b"h2".to_vec()])?;
+            let listener = TcpListener::bind(addr)
+                .await
+                .context("Failed to bind gRPC server")?;
+
+            info!("ResultService listening on {} with TLS", addr);
+
+            router
+                .serve_with_incoming_shutdown(
+                    accept_tls_connections(listener, TlsAcceptor::from(Arc::new(server_config))),
+                    shutdown_token.cancelled(),
+                )
+                .await
+                .context("gRPC server error")?;
+        }
+        None => {
+            info!("ResultService listening on {}", addr);
 
-    Server::builder()
-        .add_service(ResultReportingServiceServer::new(result_service))
-        .serve_with_shutdown(addr, shutdown_token.cancelled())
-        .await
-        .context("gRPC server error")?;
+            router
+                .serve_with_shutdown(addr, shutdown_token.cancelled())
+                .await
+                .context("gRPC server error")?;
+        }
+    }
 
     Ok(())
 }
@@ -1,20 +1,22 @@
 use crate::config::job_config::{DuplicateEntryPolicy, JobConfig};
 use crate::grpc::result_collecting_service::ResultCollectingService;
-use mongodb::bson::Document;
-use std::collections::HashMap;
+use mongodb::bson::{Bson, DateTime as BsonDateTime, Document};
+use protos::gamayun::typed_value::Kind;
+use protos::gamayun::{MapResult, TypedMapResult, TypedValue};
 use tonic::Status;
 use tracing::error;
 
 impl ResultCollectingService {
-    pub fn match_job_config(&self, job_name: &str) -> std::result::Result<&JobConfig, Status> {
+    #[allow(clippy::result_large_err)]
+    pub fn match_job_config(&self, job_name: &str) -> std::result::Result<JobConfig, Status> {
+        let job_configs = self.app_context.job_configs.read().map_err(|e| {
+            error!("Failed to read job configurations: {}", e);
+            Status::internal("Failed to read job configurations")
+        })?;
+
         // Find the job config based on the job name
-        match self
-            .app_context
-            .job_configs
-            .iter()
-            .find(|config| config.name == job_name)
-        {
-            Some(config) => Ok(config),
+        match job_configs.iter().find(|config| config.name == job_name) {
+            Some(config) => Ok(config.clone()),
             None => {
                 error!("No job config found for job name: {}", job_name);
                 Err(Status::not_found(format!(
@@ -25,17 +27,64 @@ impl ResultCollectingService {
         }
     }
 
-    pub fn build_unique_filter(
-        map: &HashMap<String, String>,
-        policy: &DuplicateEntryPolicy,
-    ) -> Document {
+    pub fn build_unique_filter(fields: &Document, policy: &DuplicateEntryPolicy) -> Document {
         // Build a filter document based on unique_ids fields
         let mut filter = Document::new();
         for unique_field in &policy.unique_ids {
-            if let Some(value) = map.get(unique_field) {
+            if let Some(value) = fields.get(unique_field) {
                 filter.insert(unique_field, value.clone());
             }
         }
         filter
     }
+
+    /// Converts a string-only `MapResult` into a document where every value is a BSON string.
+    pub fn map_result_to_document(map_result: MapResult) -> Document {
+        map_result
+            .map_result
+            .into_iter()
+            .fold(Document::new(), |mut acc, (k, v)| {
+                acc.insert(k, v);
+                acc
+            })
+    }
+
+    /// Converts a `TypedMapResult` into a document holding native BSON values.
+    pub fn typed_map_result_to_document(typed_map_result: TypedMapResult) -> Document {
+        typed_map_result
+            .typed_map_result
+            .into_iter()
+            .fold(Document::new(), |mut acc, (k, v)| {
+                acc.insert(k, Self::typed_value_to_bson(v));
+                acc
+            })
+    }
+
+    /// Converts a single `TypedValue` into the matching BSON value. Values without a kind are
+    /// stored as `null`.
+    fn typed_value_to_bson(value: TypedValue) -> Bson {
+        match value.kind {
+            None | Some(Kind::NullValue(_)) => Bson::Null,
+            Some(Kind::StringValue(s)) => Bson::String(s),
+            Some(Kind::IntValue(i)) => Bson::Int64(i),
+            Some(Kind::DoubleValue(d)) => Bson::Double(d),
+            Some(Kind::BoolValue(b)) => Bson::Boolean(b),
+            Some(Kind::TimestampMillis(millis)) => {
+                Bson::DateTime(BsonDateTime::from_millis(millis))
+            }
+            Some(Kind::ListValue(list)) => Bson::Array(
+                list.values
+                    .into_iter()
+                    .map(Self::typed_value_to_bson)
+                    .collect(),
+            ),
+            Some(Kind::MapValue(map)) => Bson::Document(map.fields.into_iter().fold(
+                Document::new(),
+                |mut acc, (k, v)| {
+                    acc.insert(k, Self::typed_value_to_bson(v));
+                    acc
+                },
+            )),
+        }
+    }
 }
@@ -0,0 +1,117 @@
+use crate::artifacts::{validate_artifact_name, ArtifactMetadata};
+use crate::grpc::result_collecting_service::ResultCollectingService;
+use chrono::Utc;
+use protos::gamayun::{ArtifactChunk, ArtifactInfo, RunInformation};
+use tonic::{Response, Status, Streaming};
+use tracing::{error, info, instrument};
+
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+impl ResultCollectingService {
+    /// Receives an artifact in chunks and stores it for the run, enforcing the artifact size
+    /// limits of the job while the chunks arrive.
+    ///
+    /// # Arguments
+    ///
+    /// * `run_information` - Information about the run the artifact belongs to, taken from the
+    ///   first chunk.
+    /// * `first_chunk` - The first chunk of the stream, with the name of the artifact.
+    /// * `stream` - The rest of the chunks.
+    ///
+    /// # Returns
+    ///
+    /// `Result<Response<ArtifactInfo>, Status>` - Returns the name and size of the stored
+    /// artifact, or a `Status` error if the artifact is invalid, too large or can't be stored.
+    #[instrument(skip(self, first_chunk, stream))]
+    pub async fn handle_artifact_upload(
+        &self,
+        run_information: RunInformation,
+        first_chunk: ArtifactChunk,
+        mut stream: Streaming<ArtifactChunk>,
+    ) -> Result<Response<ArtifactInfo>, Status> {
+        let name = first_chunk.name;
+        validate_artifact_name(&name).map_err(Status::invalid_argument)?;
+
+        let limits = self
+            .match_job_config(&run_information.job_name)?
+            .artifact_limits
+            .unwrap_or_default();
+
+        // The artifact being replaced doesn't count towards the limit of the run
+        let other_artifacts_size: u64 = self
+            .app_context
+            .artifact_store
+            .list(&run_information.job_name, &run_information.run_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to list artifacts: {:?}", e);
+                Status::internal("Failed to list the artifacts of the run")
+            })?
+            .iter()
+            .filter(|artifact| artifact.name != name)
+            .map(|artifact| artifact.size_bytes)
+            .sum();
+        let max_size = limits.max_artifact_size_bytes().min(
+            limits
+                .max_run_size_bytes()
+                .saturating_sub(other_artifacts_size),
+        );
+
+        let mut data = first_chunk.data;
+        Self::check_artifact_size(&name, data.len() as u64, max_size)?;
+        while let Some(chunk) = stream.message().await.map_err(|e| {
+            error!("Failed to receive artifact chunk: {}", e);
+            e
+        })? {
+            Self::check_artifact_size(&name, (data.len() + chunk.data.len()) as u64, max_size)?;
+            data.extend_from_slice(&chunk.data);
+        }
+
+        let content_type = if first_chunk.content_type.is_empty() {
+            DEFAULT_CONTENT_TYPE.to_string()
+        } else {
+            first_chunk.content_type
+        };
+        let metadata = ArtifactMetadata {
+            job_name: run_information.job_name.clone(),
+            run_id: run_information.run_id.clone(),
+            name,
+            content_type,
+            size_bytes: data.len() as u64,
+            uploaded_at: Utc::now(),
+        };
+
+        self.app_context
+            .artifact_store
+            .store(&metadata, data)
+            .await
+            .map_err(|e| {
+                error!("Failed to store artifact {}: {:?}", metadata.name, e);
+                Status::internal("Failed to store the artifact")
+            })?;
+
+        info!(
+            "Stored artifact {} of {} bytes for job: {} and run id {}",
+            metadata.name, metadata.size_bytes, run_information.job_name, run_information.run_id
+        );
+        Ok(Response::new(ArtifactInfo {
+            name: metadata.name,
+            size_bytes: metadata.size_bytes,
+        }))
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn check_artifact_size(name: &str, size: u64, max_size: u64) -> Result<(), Status> {
+        if size > max_size {
+            error!(
+                "Artifact {} exceeds the {} bytes left within the limits of the job",
+                name, max_size
+            );
+            return Err(Status::resource_exhausted(format!(
+                "Artifact {} exceeds the {} bytes left within the artifact limits of the job",
+                name, max_size
+            )));
+        }
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use crate::grpc::result_collecting_service::impl_run_history::RunOutcome;
 use crate::grpc::result_collecting_service::ResultCollectingService;
 
 use protos::gamayun::{EmptyResponse, RunInformation};
@@ -20,18 +21,22 @@ impl ResultCollectingService {
         &self,
         run_information: RunInformation,
     ) -> Result<Response<EmptyResponse>, Status> {
-        // Extract job name and results
-        let job_name = run_information.job_name;
-        let run_id = run_information.run_id;
-
-        self.app_context
+        let tracked_run = self
+            .app_context
             .background_job_completion_scheduler
-            .report_result_returned(&run_id)
+            .report_result_returned(&run_information.run_id)
             .await;
 
+        self.record_run_history(
+            &run_information,
+            tracked_run.as_ref(),
+            RunOutcome::NoResults,
+        )
+        .await;
+
         info!(
             "Successfully marked the no-results job as completed: {} and run id {}",
-            job_name, run_id
+            run_information.job_name, run_information.run_id
         );
         Ok(Response::new(EmptyResponse {}))
     }
@@ -1,15 +1,78 @@
+use crate::config::job_config::ErrorCategory;
+use crate::grpc::result_collecting_service::impl_run_history::{ReportedError, RunOutcome};
 use crate::grpc::result_collecting_service::ResultCollectingService;
-use crate::notification::NotificationSender;
-use protos::gamayun::{EmptyResponse, RunInformation};
+use crate::job_scheduling::schedule_retry;
+use crate::job_scheduling::scheduled_job_tracking_service::Job;
+use crate::notification::event::{EventError, NotificationEvent, NotificationKind, Severity};
+use protos::gamayun::{EmptyResponse, JobError, RunInformation};
+use std::time::Duration;
 use tonic::{Response, Status};
-use tracing::{instrument, warn};
+use tracing::{error, instrument, warn};
+
+/// How urgently a reported error needs attention.
+fn error_severity(error: &ReportedError, retry_scheduled: bool) -> Severity {
+    match error.category {
+        ErrorCategory::Auth => Severity::Critical,
+        ErrorCategory::RateLimited => Severity::Warning,
+        _ if retry_scheduled => Severity::Warning,
+        _ => Severity::Error,
+    }
+}
+
+/// Identifies repeats of an error: its category and message, with numbers left out as they
+/// often differ between runs, such as timeouts and IDs.
+fn error_fingerprint(error: &ReportedError) -> String {
+    let mut message = String::new();
+    for character in error.message.chars() {
+        if !character.is_ascii_digit() {
+            message.push(character);
+        } else if !message.ends_with('#') {
+            message.push('#');
+        }
+    }
+    format!("{}: {}", category_label(error.category), message)
+}
+
+impl From<JobError> for ReportedError {
+    fn from(job_error: JobError) -> Self {
+        let category = match job_error.category() {
+            protos::gamayun::ErrorCategory::Unknown => ErrorCategory::Unknown,
+            protos::gamayun::ErrorCategory::Network => ErrorCategory::Network,
+            protos::gamayun::ErrorCategory::Parse => ErrorCategory::Parse,
+            protos::gamayun::ErrorCategory::Auth => ErrorCategory::Auth,
+            protos::gamayun::ErrorCategory::RateLimited => ErrorCategory::RateLimited,
+        };
+
+        ReportedError {
+            message: job_error.error,
+            category,
+            retryable: job_error.retryable,
+            stack_trace: Some(job_error.stack_trace).filter(|trace| !trace.is_empty()),
+            context: job_error.context,
+        }
+    }
+}
+
+fn category_label(category: ErrorCategory) -> &'static str {
+    match category {
+        ErrorCategory::Network => "network",
+        ErrorCategory::Parse => "parse",
+        ErrorCategory::Auth => "auth",
+        ErrorCategory::RateLimited => "rate limit",
+        ErrorCategory::Unknown => "unknown",
+    }
+}
 
 impl ResultCollectingService {
     /// Handles an error that occurred during a job's execution.
     ///
+    /// Retryable errors are retried if the job has a retry policy that covers them. The category
+    /// and whether a retry was scheduled determine the severity of the notification, and the
+    /// error is stored in the run history.
+    ///
     /// # Arguments
     ///
-    /// * `error` - A `String` describing the error that occurred.
+    /// * `error` - The error that occurred, with its category and details.
     /// * `run_information` - Information about the job run, including `run_id` and `job_name`.
     ///
     /// # Returns
@@ -19,31 +82,219 @@ impl ResultCollectingService {
     #[instrument(skip(self))]
     pub async fn handle_error(
         &self,
-        error: String,
+        error: ReportedError,
         run_information: RunInformation,
     ) -> Result<Response<EmptyResponse>, Status> {
         // Log the error
         warn!("Received job error: {:?}", error);
 
-        let run_id = run_information.run_id.clone();
-
-        self.app_context
+        let tracked_run = self
+            .app_context
             .background_job_completion_scheduler
-            .report_result_returned(&run_id)
+            .report_result_returned(&run_information.run_id)
             .await;
 
-        self.app_context
-            .notification_sender
-            .notify(
-                format!("Gamayun Error for job {}", run_information.job_name),
-                format!(
-                    "The following error was reported for job {} with run id {}: \n{}",
-                    run_information.job_name, run_information.run_id, error
-                ),
-            )
+        // Results submitted while the run was kept open are stored despite the error
+        if let Some(run) = &tracked_run {
+            self.finish_run_results(&run_information, Some(run), &run.results, run.items.clone())
+                .await;
+        }
+
+        self.handle_failed_run(error, &run_information, tracked_run, false)
             .await;
 
         // Return an empty response
         Ok(Response::new(EmptyResponse {}))
     }
+
+    /// Notifies about a run that failed, retries it if allowed, and stores it in the run
+    /// history. The run has to be untracked already.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The error the run failed with.
+    /// * `run_information` - Information about the job run.
+    /// * `tracked_run` - The run as it was tracked, with the results it submitted before failing.
+    /// * `partial` - Whether the run reported itself as partially successful.
+    pub(crate) async fn handle_failed_run(
+        &self,
+        error: ReportedError,
+        run_information: &RunInformation,
+        tracked_run: Option<Job>,
+        partial: bool,
+    ) {
+        let retry = self.schedule_retry_if_allowed(&error, run_information, tracked_run.as_ref());
+        let severity = error_severity(&error, retry.is_some());
+
+        let mut event = NotificationEvent::new(
+            NotificationKind::JobError,
+            severity,
+            Self::error_notification_title(&error, severity, &run_information.job_name, partial),
+            Self::error_notification_body(&error, run_information, tracked_run.as_ref(), retry),
+        )
+        .for_run(
+            &run_information.job_name,
+            &run_information.run_id,
+            self.match_job_config(&run_information.job_name)
+                .map(|job_config| job_config.tags)
+                .unwrap_or_default(),
+        )
+        .with_error(EventError {
+            message: error.message.clone(),
+            category: category_label(error.category).to_string(),
+            retryable: error.retryable,
+            stack_trace: error.stack_trace.clone(),
+            context: error.context.clone(),
+        })
+        .with_fingerprint(error_fingerprint(&error));
+        if let Some(run) = &tracked_run {
+            event = event.with_run_timing(run.attempt, run.started_at);
+        }
+        self.app_context.notification_sender.notify(event).await;
+
+        let submitted_results = tracked_run
+            .as_ref()
+            .map(|run| run.results)
+            .filter(|results| results.submissions > 0);
+        let severity = severity.label().to_lowercase();
+        let retry_scheduled = retry.is_some();
+        let outcome = if partial {
+            RunOutcome::Partial {
+                results: submitted_results.unwrap_or_default(),
+                error,
+                severity,
+                retry_scheduled,
+            }
+        } else {
+            RunOutcome::Error {
+                error,
+                severity,
+                retry_scheduled,
+                results: submitted_results,
+            }
+        };
+        self.record_run_history(run_information, tracked_run.as_ref(), outcome)
+            .await;
+    }
+
+    /// Schedules the next attempt of the run if the error is retryable and the job's retry
+    /// policy allows it.
+    ///
+    /// # Returns
+    ///
+    /// `Option<(u32, Duration)>` - The number of the next attempt and the delay before it, if
+    /// a retry was scheduled.
+    fn schedule_retry_if_allowed(
+        &self,
+        error: &ReportedError,
+        run_information: &RunInformation,
+        tracked_run: Option<&Job>,
+    ) -> Option<(u32, Duration)> {
+        if !error.retryable {
+            return None;
+        }
+        // Only runs Gamayun started can be retried, as it needs to know the attempt
+        let attempt = tracked_run?.attempt;
+        let job_config = self.match_job_config(&run_information.job_name).ok()?;
+        let delay = job_config
+            .retry
+            .as_ref()?
+            .retry_delay(error.category, attempt)?;
+
+        if let Err(e) = schedule_retry(
+            self.app_context.scheduler.clone(),
+            job_config,
+            attempt + 1,
+            delay,
+            self.app_context.background_job_completion_scheduler.clone(),
+            self.app_context.worker_registry.clone(),
+            self.app_context.server_url.clone(),
+        ) {
+            error!("Failed to retry job {}: {:?}", run_information.job_name, e);
+            return None;
+        }
+        Some((attempt + 1, delay))
+    }
+
+    fn error_notification_title(
+        error: &ReportedError,
+        severity: Severity,
+        job_name: &str,
+        partial: bool,
+    ) -> String {
+        if partial {
+            format!(
+                "Gamayun {}: partial run of job {} after {} error",
+                severity.label(),
+                job_name,
+                category_label(error.category)
+            )
+        } else if severity == Severity::Error && error.category == ErrorCategory::Unknown {
+            format!("Gamayun Error for job {}", job_name)
+        } else {
+            format!(
+                "Gamayun {}: {} error for job {}",
+                severity.label(),
+                category_label(error.category),
+                job_name
+            )
+        }
+    }
+
+    fn error_notification_body(
+        error: &ReportedError,
+        run_information: &RunInformation,
+        tracked_run: Option<&Job>,
+        retry: Option<(u32, Duration)>,
+    ) -> String {
+        let mut body = format!(
+            "The following error was reported for job {} with run id {}: \n{}",
+            run_information.job_name, run_information.run_id, error.message
+        );
+
+        body.push_str(&format!(
+            "\n\nCategory: {}\nRetryable: {}",
+            category_label(error.category),
+            error.retryable
+        ));
+        if let Some(tracked_run) = tracked_run {
+            body.push_str(&format!("\nAttempt: {}", tracked_run.attempt));
+
+            let results = &tracked_run.results;
+            if results.submissions > 0 {
+                body.push_str(&format!(
+                    "\nStored before the error: {} inserted, {} updated, {} ignored, {} changed, \
+                     {} rejected in {} submissions",
+                    results.inserted,
+                    results.updated,
+                    results.ignored,
+                    results.changed,
+                    results.rejected,
+                    results.submissions
+                ));
+            }
+        }
+        if let Some((next_attempt, delay)) = retry {
+            body.push_str(&format!(
+                "\nAttempt {} is scheduled in {} seconds",
+                next_attempt,
+                delay.as_secs()
+            ));
+        }
+
+        if !error.context.is_empty() {
+            let mut context: Vec<_> = error.context.iter().collect();
+            context.sort();
+            body.push_str("\n\nContext:");
+            for (key, value) in context {
+                body.push_str(&format!("\n{}: {}", key, value));
+            }
+        }
+
+        if let Some(stack_trace) = &error.stack_trace {
+            body.push_str(&format!("\n\nStack trace:\n{}", stack_trace));
+        }
+
+        body
+    }
 }
@@ -0,0 +1,133 @@
+use crate::grpc::result_collecting_service::ResultCollectingService;
+use crate::job_scheduling::scheduled_job_tracking_service::{ItemChanges, Job};
+use crate::notification::event::{NotificationEvent, NotificationKind, Severity};
+use protos::gamayun::RunInformation;
+use serde_json::Value;
+use tracing::info;
+
+/// Formats an item as a single line, such as "new: title: Flat in Zagreb, price: 120000".
+fn item_line(item: &Value) -> String {
+    let status = item["status"].as_str().unwrap_or_default();
+    let fields = item["fields"]
+        .as_object()
+        .map(|fields| {
+            fields
+                .iter()
+                .map(|(name, value)| match item["previous"].get(name) {
+                    Some(previous) => format!(
+                        "{}: {} (was {})",
+                        name,
+                        value_text(value),
+                        value_text(previous)
+                    ),
+                    None => format!("{}: {}", name, value_text(value)),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .unwrap_or_default();
+    format!("{}: {}", status, fields)
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => "nothing".to_string(),
+        value => value.to_string(),
+    }
+}
+
+/// Builds the notification listing the new and changed items of a run, if it found any.
+///
+/// # Arguments
+///
+/// * `job_name` - Name of the job.
+/// * `run_id` - ID of the run.
+/// * `tags` - Tags of the job.
+/// * `items` - New and changed items over all submissions of the run.
+pub(crate) fn item_notification(
+    job_name: &str,
+    run_id: &str,
+    tags: Vec<String>,
+    items: ItemChanges,
+) -> Option<NotificationEvent> {
+    if items.is_empty() {
+        return None;
+    }
+
+    let mut found = Vec::new();
+    if items.new_count > 0 {
+        found.push(format!("{} new", items.new_count));
+    }
+    if items.changed_count > 0 {
+        found.push(format!("{} changed", items.changed_count));
+    }
+    let found = found.join(" and ");
+    info!(
+        "Run id {} of job {} found {} items",
+        run_id, job_name, found
+    );
+
+    let mut details = format!(
+        "Job {} with run ID {} found {} items:\n\n{}",
+        job_name,
+        run_id,
+        found,
+        items
+            .items
+            .iter()
+            .map(|item| format!("- {}", item_line(item)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+    if items.omitted() > 0 {
+        details.push_str(&format!(
+            "\n\n{} more items are not listed.",
+            items.omitted()
+        ));
+    }
+
+    Some(
+        NotificationEvent::new(
+            NotificationKind::NewResults,
+            Severity::Info,
+            format!("Gamayun: {} items for job {}", found, job_name),
+            details,
+        )
+        .for_run(job_name, run_id, tags)
+        .with_items(items.items)
+        // Every run finds different items, they are never repeats
+        .with_fingerprint(run_id.to_string()),
+    )
+}
+
+impl ResultCollectingService {
+    /// Sends one notification listing the new and changed items of a completed run, if the
+    /// job notifies about its results and the run found any.
+    ///
+    /// # Arguments
+    ///
+    /// * `run_information` - Information about the completed run.
+    /// * `tracked_run` - The run as it was tracked, for its attempt and start time.
+    /// * `items` - New and changed items over all submissions of the run.
+    pub(crate) async fn notify_about_items(
+        &self,
+        run_information: &RunInformation,
+        tracked_run: Option<&Job>,
+        items: ItemChanges,
+    ) {
+        let job_name = &run_information.job_name;
+        let tags = self
+            .match_job_config(job_name)
+            .map(|job_config| job_config.tags)
+            .unwrap_or_default();
+        let Some(mut event) = item_notification(job_name, &run_information.run_id, tags, items)
+        else {
+            return;
+        };
+        if let Some(run) = tracked_run {
+            event = event.with_run_timing(run.attempt, run.started_at);
+        }
+        self.app_context.notification_sender.notify(event).await;
+    }
+}
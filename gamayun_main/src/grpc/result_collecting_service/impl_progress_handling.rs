@@ -0,0 +1,56 @@
+use crate::grpc::result_collecting_service::ResultCollectingService;
+use crate::job_scheduling::scheduled_job_tracking_service::JobProgress;
+use chrono::Utc;
+use protos::gamayun::{EmptyResponse, ProgressReport, RunInformation};
+use tonic::{Response, Status};
+use tracing::{info, instrument};
+
+impl ResultCollectingService {
+    /// Handles a heartbeat of a running job by storing its progress and extending the time
+    /// Gamayun waits for the results of the run.
+    ///
+    /// # Arguments
+    ///
+    /// * `progress_report` - The progress reported by the job.
+    /// * `run_information` - Information about the job run, including `run_id` and `job_name`.
+    ///
+    /// # Returns
+    ///
+    /// `Result<Response<EmptyResponse>, Status>` - Returns an empty response on success,
+    /// or a `Status` error if the run is not being tracked.
+    #[instrument(skip(self, progress_report))]
+    pub async fn handle_progress(
+        &self,
+        progress_report: ProgressReport,
+        run_information: RunInformation,
+    ) -> Result<Response<EmptyResponse>, Status> {
+        info!(
+            status = %progress_report.status,
+            items_processed = ?progress_report.items_processed,
+            items_total = ?progress_report.items_total,
+            "Received progress for job {}",
+            run_information.job_name
+        );
+
+        let progress = JobProgress {
+            status: progress_report.status,
+            items_processed: progress_report.items_processed,
+            items_total: progress_report.items_total,
+            reported_at: Utc::now(),
+        };
+
+        if !self
+            .app_context
+            .background_job_completion_scheduler
+            .report_progress(&run_information.run_id, progress)
+            .await
+        {
+            return Err(Status::not_found(format!(
+                "No running job found for run id: {}",
+                run_information.run_id
+            )));
+        }
+
+        Ok(Response::new(EmptyResponse {}))
+    }
+}
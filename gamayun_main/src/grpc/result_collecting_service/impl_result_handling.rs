@@ -1,15 +1,47 @@
 use crate::config::job_config::{DuplicateEntryPolicy, OnDuplicateEntry};
 use crate::grpc::result_collecting_service::ResultCollectingService;
+use crate::job_scheduling::scheduled_job_tracking_service::{ItemChanges, SubmittedResults};
 
+use chrono::Utc;
+use futures::TryStreamExt;
 use mongodb::bson::{doc, Bson, DateTime as BsonDateTime, Document};
 use mongodb::{error::Error as MongoError, Collection};
-use protos::gamayun::{EmptyResponse, MapResult, RunInformation};
+use protos::gamayun::{MapResult, ReportResultResponse, RunInformation, TypedMapResult};
 use std::collections::HashMap;
 use tonic::{Response, Status};
 use tracing::{error, info, instrument};
 
-const CREATED_AT_FIELD: &str = "gamayun_created_at";
+pub(crate) const CREATED_AT_FIELD: &str = "gamayun_created_at";
 const UPTADED_AT_FIELD: &str = "gamayun_updated_at";
+const TAGS_FIELD: &str = "gamayun_tags";
+/// Upper bound for the statements of a single `update` command, safely below MongoDB's 16 MB
+/// limit for the whole command document.
+const MAX_UPDATE_COMMAND_BYTES: usize = 12 * 1024 * 1024;
+
+/// The most recent version stored for a unique key.
+enum LatestVersion {
+    /// The version is already in the collection and has the given `_id`.
+    Stored(Bson),
+    /// The version is part of the current batch and waits to be inserted at the given index.
+    Pending(usize),
+}
+
+/// What is known about the documents stored for a single unique key.
+struct KeyState {
+    latest: LatestVersion,
+    /// All versions merged into a single document, oldest first.
+    merged: Document,
+    /// `gamayun_created_at` of the first version.
+    created_at: Option<Bson>,
+}
+
+/// Writes collected for a batch, executed together once the batch has been processed.
+#[derive(Default)]
+struct BatchWrites {
+    inserts: Vec<Document>,
+    /// Statements of an `update` command, each updating or replacing a document by `_id`.
+    updates: Vec<Document>,
+}
 
 impl ResultCollectingService {
     /// Processes results for a job that contains map-only data and handles storing them
@@ -17,304 +49,521 @@ impl ResultCollectingService {
     ///
     /// # Arguments
     ///
-    /// * `results` - The job result containing a list of map results.
+    /// * `results` - The job result containing a list of string-only map results.
+    /// * `typed_results` - The job result containing a list of typed map results.
+    /// * `keep_run_open` - Whether to keep the run open for more results until `CompleteRun`.
     /// * `run_information` - Information about the job that produced the results.
     ///
     /// # Returns
     ///
-    /// `Result<Response<EmptyResponse>, Status>` - Returns an empty response on success,
-    /// or a `Status` error if processing or storing fails.
-    #[instrument(skip(self))]
+    /// `Result<Response<ReportResultResponse>, Status>` - Returns the number of inserted,
+    /// updated, ignored, changed and rejected results on success, or a `Status` error if
+    /// processing or storing fails.
+    #[instrument(skip(self, results, typed_results))]
     pub async fn handle_result(
         &self,
         results: Vec<MapResult>,
+        typed_results: Vec<TypedMapResult>,
+        keep_run_open: bool,
         run_information: RunInformation,
-    ) -> Result<Response<EmptyResponse>, Status> {
-        // Extract job name and results
-        let job_name = run_information.job_name;
-        let run_id = run_information.run_id;
-
+    ) -> Result<Response<ReportResultResponse>, Status> {
         info!(
             "Started processing results for job: {} and run id {}",
-            job_name, run_id
+            run_information.job_name, run_information.run_id
         );
 
-        self.app_context
-            .background_job_completion_scheduler
-            .report_result_returned(&run_id)
+        let submission = self
+            .store_results(results, typed_results, &run_information)
+            .await?;
+        let response = submission.response;
+
+        self.finish_result_submission(&run_information, submission, keep_run_open)
             .await;
 
+        info!(
+            "Successfully processed all results for job: {} and run id {}",
+            run_information.job_name, run_information.run_id
+        );
+        Ok(Response::new(response))
+    }
+
+    /// Validates results and stores them in MongoDB based on the duplicate entry policy of the
+    /// job, in batches of the configured size.
+    ///
+    /// # Arguments
+    ///
+    /// * `results` - A list of string-only map results.
+    /// * `typed_results` - A list of typed map results.
+    /// * `run_information` - Information about the job that produced the results.
+    ///
+    /// # Returns
+    ///
+    /// `Result<SubmittedResults, Status>` - The number of inserted, updated, ignored, changed,
+    /// rejected and invalid results together with the new and changed items the job notifies
+    /// about, or a `Status` error if processing or storing fails.
+    #[instrument(skip(self, results, typed_results))]
+    pub async fn store_results(
+        &self,
+        results: Vec<MapResult>,
+        typed_results: Vec<TypedMapResult>,
+        run_information: &RunInformation,
+    ) -> Result<SubmittedResults, Status> {
+        let job_name = &run_information.job_name;
+        let run_id = &run_information.run_id;
+
         // Match the job config using the new function
-        let job_config = self.match_job_config(&job_name)?;
+        let job_config = self.match_job_config(job_name)?;
 
-        // Get the MongoDB collection
+        // Get the MongoDB collection configured for the job
+        let storage_target = job_config
+            .storage_target(&self.app_context.mongo_db_name, Utc::now())
+            .map_err(|e| {
+                error!("Failed to resolve the storage target: {:?}", e);
+                Status::internal(format!("Failed to resolve the storage target: {}", e))
+            })?;
         let collection = self
             .app_context
             .mongo_client
-            .database(self.app_context.mongo_db_name.as_str())
-            .collection(&job_name);
+            .database(&storage_target.database)
+            .collection(&storage_target.collection);
 
         // Check for duplicate entry policy, default to TrackChanges
-        let duplicate_policy = job_config
-            .duplicate_entry_policy
-            .clone()
-            .unwrap_or_else(|| DuplicateEntryPolicy {
-                unique_ids: vec![],
-                on_duplicate_entry: OnDuplicateEntry::TrackChanges,
-            });
+        let duplicate_policy = job_config.effective_duplicate_entry_policy();
 
         // Extract tags from job_config
         let tags = job_config.tags.clone();
 
-        // Handle each map result based on the duplicate entry policy
-        for map_result in results {
-            Self::handle_single_result(
-                &job_name,
+        // String-only results are stored as BSON strings, typed results keep their native types
+        let documents: Vec<Document> = results
+            .into_iter()
+            .map(Self::map_result_to_document)
+            .chain(
+                typed_results
+                    .into_iter()
+                    .map(Self::typed_map_result_to_document),
+            )
+            .collect();
+        let reported_count = documents.len();
+
+        // Validate and coerce the results if the job has a result schema
+        let (documents, invalid) = self
+            .apply_result_schema(&job_config, run_id, &collection, documents)
+            .await?;
+
+        let mut response = ReportResultResponse {
+            rejected: (reported_count - documents.len()) as u64,
+            ..Default::default()
+        };
+        let mut items = ItemChanges::for_job(job_config.notify_on_results.as_ref());
+
+        let batch_size = self.app_context.app_config.result_batch_size();
+        let mut documents = documents.into_iter().peekable();
+        while documents.peek().is_some() {
+            let batch: Vec<Document> = documents.by_ref().take(batch_size).collect();
+            Self::store_batch(
+                job_name,
                 &collection,
                 &duplicate_policy,
                 &tags,
-                map_result,
+                batch,
+                &mut response,
+                &mut items,
             )
-            .await?;
+            .await
+            .map_err(|e| {
+                error!("MongoDB operation failed: {}", e);
+                Status::internal(format!("MongoDB error: {}", e))
+            })?;
         }
 
         info!(
-            "Successfully processed all results for job: {} and run id {}",
-            job_name, run_id
+            "Stored results for job {}: {} inserted, {} updated, {} ignored, {} changed, {} rejected",
+            job_name,
+            response.inserted,
+            response.updated,
+            response.ignored,
+            response.changed,
+            response.rejected
         );
-        Ok(Response::new(EmptyResponse {}))
+        Ok(SubmittedResults {
+            response,
+            invalid,
+            items,
+        })
     }
 
-    /// Processes a single map result for a job and stores it in MongoDB based on the
-    /// provided duplicate entry policy.
+    /// Stores a batch of results in MongoDB based on the provided duplicate entry policy.
+    /// Existing documents for all unique keys of the batch are looked up with a single query,
+    /// and all writes are sent as one `insert_many` and one `update` command.
     ///
     /// # Arguments
     ///
-    /// * `job_name` - The name of the job for which the result is being processed.
-    /// * `collection` - The MongoDB collection to store the result.
+    /// * `job_name` - The name of the job for which the results are being processed.
+    /// * `collection` - The MongoDB collection to store the results.
     /// * `duplicate_policy` - The policy that defines how duplicate entries should be handled.
     /// * `tags` - Tags associated with the job.
-    /// * `map_result` - The individual map result to be processed.
+    /// * `batch` - The results, already converted to BSON.
+    /// * `response` - Counters that are updated with the outcome of each result.
+    /// * `items` - Collects the new and changed items the job notifies about.
     ///
     /// # Returns
     ///
-    /// `Result<(), Status>` - Returns `Ok(())` on success or a `Status` error if processing fails.
-    #[instrument(skip(collection, duplicate_policy, tags, map_result))]
-    async fn handle_single_result(
+    /// `Result<(), MongoError>` - Returns `Ok(())` on successful storage or a `MongoError`
+    /// if any MongoDB operation fails.
+    #[instrument(skip(collection, duplicate_policy, tags, batch, response, items))]
+    async fn store_batch(
         job_name: &String,
         collection: &Collection<Document>,
         duplicate_policy: &DuplicateEntryPolicy,
-        tags: &Vec<String>,
-        map_result: MapResult,
-    ) -> Result<(), Status> {
-        let map: HashMap<String, String> = map_result.map_result;
+        tags: &[String],
+        batch: Vec<Document>,
+        response: &mut ReportResultResponse,
+        items: &mut ItemChanges,
+    ) -> Result<(), MongoError> {
+        let mut key_states = Self::load_key_states(collection, duplicate_policy, &batch).await?;
+        let mut writes = BatchWrites::default();
 
         // Current timestamp to add to new documents
         let current_time = BsonDateTime::now();
 
-        let mut doc = map
-            .clone()
-            .into_iter()
-            .fold(Document::new(), |mut acc, (k, v)| {
-                acc.insert(k, v);
-                acc
-            });
+        for fields in batch {
+            // Build the filter for unique ID fields
+            let filter = Self::build_unique_filter(&fields, duplicate_policy);
 
-        // Add created_at, updated_at, and tags fields
-        doc.insert(CREATED_AT_FIELD, current_time);
-        doc.insert(CREATED_AT_FIELD, current_time);
-        doc.insert(
-            "gamayun_tags",
-            Bson::Array(tags.iter().map(|tag| Bson::String(tag.clone())).collect()),
-        );
+            let mut doc = fields;
+
+            // Add created_at, updated_at, and tags fields
+            doc.insert(CREATED_AT_FIELD, current_time);
+            doc.insert(UPTADED_AT_FIELD, current_time);
+            doc.insert(
+                TAGS_FIELD,
+                Bson::Array(tags.iter().map(|tag| Bson::String(tag.clone())).collect()),
+            );
+
+            // Results without any unique ID field can't be duplicates
+            if filter.is_empty() {
+                if items.on_new_item {
+                    items.record_new(Self::item_fields(&doc));
+                }
+                writes.inserts.push(doc);
+                response.inserted += 1;
+                continue;
+            }
+
+            let key = filter.to_string();
+            match key_states.get_mut(&key) {
+                None => {
+                    if items.on_new_item {
+                        items.record_new(Self::item_fields(&doc));
+                    }
+                    key_states.insert(
+                        key,
+                        KeyState {
+                            latest: LatestVersion::Pending(writes.inserts.len()),
+                            merged: doc.clone(),
+                            created_at: Some(Bson::DateTime(current_time)),
+                        },
+                    );
+                    writes.inserts.push(doc);
+                    response.inserted += 1;
+                }
+                Some(state) => match duplicate_policy.on_duplicate_entry {
+                    OnDuplicateEntry::IgnoreNew => {
+                        Self::handle_ignore_new_policy(state, &mut writes, current_time);
+                        response.ignored += 1;
+                    }
+                    OnDuplicateEntry::Overwrite => {
+                        Self::handle_overwrite_policy(state, &mut writes, doc);
+                        response.updated += 1;
+                    }
+                    OnDuplicateEntry::TrackChanges => {
+                        let previous = items.on_change.then(|| state.merged.clone());
+                        if Self::handle_track_changes_policy(
+                            state,
+                            &mut writes,
+                            doc,
+                            filter,
+                            current_time,
+                        ) {
+                            response.changed += 1;
+                            if let Some(previous) = previous {
+                                items.record_change(
+                                    Self::item_fields(&state.merged),
+                                    Self::previous_values(&previous, &state.merged),
+                                );
+                            }
+                        } else {
+                            response.ignored += 1;
+                        }
+                    }
+                },
+            }
+        }
+
+        Self::execute_batch_writes(collection, writes).await?;
 
-        // Build the filter for unique ID fields
-        let filter = Self::build_unique_filter(&map, &duplicate_policy);
-
-        // Store the document based on the duplicate policy
-        Self::store_based_on_duplicate_policy(
-            &job_name,
-            &collection,
-            duplicate_policy.clone(),
-            doc,
-            filter,
-        )
-        .await
-        .map_err(|e| {
-            error!("MongoDB operation failed: {}", e);
-            Status::internal(format!("MongoDB error: {}", e))
-        })?;
+        info!(
+            "Stored batch as per {:?} policy for job: {}",
+            duplicate_policy.on_duplicate_entry, job_name
+        );
         Ok(())
     }
 
-    /// Stores a document in MongoDB based on the duplicate entry policy for the job.
-    ///
-    /// # Arguments
-    ///
-    /// * `job_name` - The name of the job for which the document is being stored.
-    /// * `collection` - The MongoDB collection to store the document.
-    /// * `duplicate_policy` - The policy defining how duplicate entries should be handled.
-    /// * `doc` - The document to be stored.
-    /// * `filter` - A filter to identify existing documents matching the unique IDs.
-    ///
-    /// # Returns
-    ///
-    /// `Result<(), MongoError>` - Returns `Ok(())` on successful storage or a `MongoError`
-    /// if any MongoDB operation fails.
-    #[instrument(skip(collection, duplicate_policy, doc, filter))]
-    async fn store_based_on_duplicate_policy(
-        job_name: &String,
+    /// Loads the documents already stored for the unique keys of the batch with a single query
+    /// and folds them into one `KeyState` per key.
+    async fn load_key_states(
         collection: &Collection<Document>,
-        duplicate_policy: DuplicateEntryPolicy,
-        doc: Document,
-        filter: Document,
-    ) -> Result<(), MongoError> {
-        match duplicate_policy.on_duplicate_entry {
-            OnDuplicateEntry::IgnoreNew => {
-                Self::handle_ignore_new_policy(job_name, collection, doc, filter).await
-            }
-            OnDuplicateEntry::Overwrite => {
-                Self::handle_overwrite_policy(job_name, collection, doc, filter).await
-            }
-            OnDuplicateEntry::TrackChanges => {
-                Self::handle_track_changes_policy(job_name, collection, doc, filter).await
+        duplicate_policy: &DuplicateEntryPolicy,
+        batch: &[Document],
+    ) -> Result<HashMap<String, KeyState>, MongoError> {
+        let filters: Vec<Document> = batch
+            .iter()
+            .map(|fields| Self::build_unique_filter(fields, duplicate_policy))
+            .filter(|filter| !filter.is_empty())
+            .collect();
+
+        let mut key_states: HashMap<String, KeyState> = HashMap::new();
+        if filters.is_empty() {
+            return Ok(key_states);
+        }
+
+        let mut cursor = collection
+            .find(doc! { "$or": filters })
+            .sort(doc! { CREATED_AT_FIELD: 1 })
+            .await?;
+
+        while let Some(existing_doc) = cursor.try_next().await? {
+            let key = Self::build_unique_filter(&existing_doc, duplicate_policy).to_string();
+            let id = existing_doc.get("_id").cloned().unwrap_or(Bson::Null);
+            match key_states.get_mut(&key) {
+                Some(state) => {
+                    state.latest = LatestVersion::Stored(id);
+                    for (field, value) in existing_doc {
+                        state.merged.insert(field, value);
+                    }
+                }
+                None => {
+                    key_states.insert(
+                        key,
+                        KeyState {
+                            latest: LatestVersion::Stored(id),
+                            created_at: existing_doc.get(CREATED_AT_FIELD).cloned(),
+                            merged: existing_doc,
+                        },
+                    );
+                }
             }
         }
+
+        Ok(key_states)
     }
 
-    /// Handles the `IgnoreNew` duplicate entry policy by skipping new documents if
-    /// a duplicate is found, updating only `gamayun_updated_at` in the existing document.
+    /// Handles the `IgnoreNew` duplicate entry policy by skipping the new document and
+    /// updating only `gamayun_updated_at` in the existing document.
     ///
     /// # Arguments
     ///
-    /// * `job_name` - The name of the job.
-    /// * `collection` - The MongoDB collection.
-    /// * `doc` - The new document to potentially store.
-    /// * `filter` - The filter to find existing duplicates.
-    ///
-    /// # Returns
-    ///
-    /// `Result<(), MongoError>` - Returns `Ok(())` on successful storage or a `MongoError`
-    /// if a MongoDB operation fails.
-    #[instrument(skip(collection, doc, filter))]
-    async fn handle_ignore_new_policy(
-        job_name: &String,
-        collection: &Collection<Document>,
-        doc: Document,
-        filter: Document,
-    ) -> Result<(), MongoError> {
-        // Skip inserting if a document with the same unique ID already exists
-        if collection.find_one(filter.clone()).await?.is_none() {
-            collection.insert_one(doc).await?;
-        } else {
-            // Update `gamayun_updated_at` field only
-            let current_time = BsonDateTime::now();
-            let update = doc! {
-                "$set": { UPTADED_AT_FIELD: current_time }
-            };
-            collection.update_one(filter, update).await?;
-            info!(
-                "Duplicate found, ignoring new entry as per policy for job: {}",
-                job_name
-            );
+    /// * `state` - What is known about the documents stored for the key.
+    /// * `writes` - Writes collected for the current batch.
+    /// * `current_time` - The new value of `gamayun_updated_at`.
+    fn handle_ignore_new_policy(
+        state: &KeyState,
+        writes: &mut BatchWrites,
+        current_time: BsonDateTime,
+    ) {
+        // A document inserted in this batch already has the current time
+        if let LatestVersion::Stored(id) = &state.latest {
+            writes.updates.push(doc! {
+                "q": { "_id": id.clone() },
+                "u": { "$set": { UPTADED_AT_FIELD: current_time } },
+            });
         }
-        Ok(())
     }
 
-    /// Handles the `Overwrite` duplicate entry policy by replacing existing documents
+    /// Handles the `Overwrite` duplicate entry policy by replacing the existing document
     /// while preserving the `gamayun_created_at` field.
     ///
     /// # Arguments
     ///
-    /// * `job_name` - The name of the job.
-    /// * `collection` - The MongoDB collection.
+    /// * `state` - What is known about the documents stored for the key.
+    /// * `writes` - Writes collected for the current batch.
     /// * `doc` - The new document to store.
-    /// * `filter` - The filter to find existing duplicates.
-    ///
-    /// # Returns
-    ///
-    /// `Result<(), MongoError>` - Returns `Ok(())` on successful storage or a `MongoError`
-    /// if a MongoDB operation fails.
-    #[instrument(skip(collection, doc, filter))]
-    async fn handle_overwrite_policy(
-        job_name: &String,
-        collection: &Collection<Document>,
-        mut doc: Document,
-        filter: Document,
-    ) -> Result<(), MongoError> {
-        // Find existing document and keep its `gamayun_created_at`
-        if let Some(existing_doc) = collection.find_one(filter.clone()).await? {
-            if let Some(gamayun_created_at) = existing_doc.get(CREATED_AT_FIELD).cloned() {
-                doc.insert(CREATED_AT_FIELD, gamayun_created_at);
-            }
+    fn handle_overwrite_policy(state: &mut KeyState, writes: &mut BatchWrites, mut doc: Document) {
+        if let Some(created_at) = &state.created_at {
+            doc.insert(CREATED_AT_FIELD, created_at.clone());
         }
 
-        // Replace the existing document with the updated fields
-        collection.replace_one(filter, doc).await?;
-        info!(
-            "Duplicate found, overwriting entry as per policy for job: {}",
-            job_name
-        );
-        Ok(())
+        match &state.latest {
+            LatestVersion::Stored(id) => {
+                writes.updates.push(doc! {
+                    "q": { "_id": id.clone() },
+                    "u": doc.clone(),
+                });
+            }
+            LatestVersion::Pending(index) => {
+                writes.inserts[*index] = doc.clone();
+            }
+        }
+        state.merged = doc;
     }
 
-    /// Handles the `TrackChanges` duplicate entry policy by creating a new document
-    /// containing only the fields that have changed since the last stored document.
+    /// Handles the `TrackChanges` duplicate entry policy by inserting a new document
+    /// containing the unique ID fields and the fields that have changed compared to all stored
+    /// versions. If nothing changed, only `gamayun_updated_at` of the latest version is updated.
     ///
     /// # Arguments
     ///
-    /// * `job_name` - The name of the job.
-    /// * `collection` - The MongoDB collection.
+    /// * `state` - What is known about the documents stored for the key.
+    /// * `writes` - Writes collected for the current batch.
     /// * `doc` - The new document to compare and store.
-    /// * `filter` - The filter to find existing duplicates.
+    /// * `filter` - The unique ID fields of the document.
+    /// * `current_time` - Timestamp of the new version.
     ///
     /// # Returns
     ///
-    /// `Result<(), MongoError>` - Returns `Ok(())` on successful storage or a `MongoError`
-    /// if a MongoDB operation fails.
-    #[instrument(skip(collection, doc, filter))]
-    async fn handle_track_changes_policy(
-        job_name: &String,
-        collection: &Collection<Document>,
+    /// `bool` - Whether any of the fields changed.
+    fn handle_track_changes_policy(
+        state: &mut KeyState,
+        writes: &mut BatchWrites,
         doc: Document,
         filter: Document,
-    ) -> Result<(), MongoError> {
-        // Find the latest document matching the filter
-        if let Some(existing_doc) = collection
-            .find_one(filter.clone())
-            .sort(doc! { CREATED_AT_FIELD: -1 })
-            .await?
-        {
-            // Calculate the changed fields
-            let mut changed_fields = Document::new();
-            for (key, new_value) in &doc {
-                if let Some(existing_value) = existing_doc.get(key) {
-                    // Compare existing value with new value
-                    if existing_value != new_value {
-                        changed_fields.insert(key.clone(), new_value.clone());
-                    }
-                } else {
-                    // New field that didn't exist before
-                    changed_fields.insert(key.clone(), new_value.clone());
-                }
+        current_time: BsonDateTime,
+    ) -> bool {
+        // Calculate the changed fields, ignoring the timestamps
+        let mut changed_fields = Document::new();
+        for (key, new_value) in doc {
+            if key == CREATED_AT_FIELD || key == UPTADED_AT_FIELD {
+                continue;
+            }
+            if state.merged.get(&key) != Some(&new_value) {
+                changed_fields.insert(key, new_value);
             }
+        }
 
-            // Add timestamps to changed document
-            let current_time = BsonDateTime::now();
-            changed_fields.insert(CREATED_AT_FIELD, current_time);
-            changed_fields.insert(UPTADED_AT_FIELD, current_time);
+        if changed_fields.is_empty() {
+            Self::handle_ignore_new_policy(state, writes, current_time);
+            return false;
+        }
 
-            // Insert only the changed fields
-            collection.insert_one(changed_fields).await?;
-        } else {
-            // If no existing document, insert the full document
-            collection.insert_one(doc).await?;
+        // Each version keeps the unique ID fields, so all versions of a key can be found
+        let mut version = filter;
+        version.extend(changed_fields);
+
+        // Add timestamps to changed document
+        version.insert(CREATED_AT_FIELD, current_time);
+        version.insert(UPTADED_AT_FIELD, current_time);
+
+        for (key, value) in &version {
+            state.merged.insert(key.clone(), value.clone());
+        }
+        state.latest = LatestVersion::Pending(writes.inserts.len());
+
+        // Insert only the changed fields
+        writes.inserts.push(version);
+        true
+    }
+
+    /// Fields of a result as the job reported them, without the ones Gamayun adds.
+    fn item_fields(doc: &Document) -> serde_json::Value {
+        let fields: Document = doc
+            .iter()
+            .filter(|(key, _)| !Self::is_gamayun_field(key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        Bson::Document(fields).into_relaxed_extjson()
+    }
+
+    /// Values the changed fields of a result had before, `null` for fields that are new.
+    ///
+    /// # Arguments
+    ///
+    /// * `previous` - All versions of the result merged, before the change.
+    /// * `current` - All versions of the result merged, including the change.
+    fn previous_values(previous: &Document, current: &Document) -> serde_json::Value {
+        let values: Document = current
+            .iter()
+            .filter(|(key, value)| {
+                !Self::is_gamayun_field(key) && previous.get(key.as_str()) != Some(value)
+            })
+            .map(|(key, _)| {
+                (
+                    key.clone(),
+                    previous.get(key.as_str()).cloned().unwrap_or(Bson::Null),
+                )
+            })
+            .collect();
+        Bson::Document(values).into_relaxed_extjson()
+    }
+
+    fn is_gamayun_field(key: &str) -> bool {
+        key == "_id" || key == CREATED_AT_FIELD || key == UPTADED_AT_FIELD || key == TAGS_FIELD
+    }
+
+    /// Executes the writes collected for a batch.
+    async fn execute_batch_writes(
+        collection: &Collection<Document>,
+        writes: BatchWrites,
+    ) -> Result<(), MongoError> {
+        let update_chunks = Self::chunk_by_size(writes.updates)?;
+
+        if !writes.inserts.is_empty() {
+            collection.insert_many(writes.inserts).await?;
+        }
+
+        // A raw `update` command sends many updates in one round trip, and unlike
+        // `Client::bulk_write` it doesn't require MongoDB 8.0
+        for updates in update_chunks {
+            let result = collection
+                .client()
+                .database(&collection.namespace().db)
+                .run_command(doc! {
+                    "update": collection.name(),
+                    "updates": updates,
+                    "ordered": true,
+                })
+                .await?;
+
+            if let Ok(write_errors) = result.get_array("writeErrors") {
+                if !write_errors.is_empty() {
+                    error!("Batched update failed: {:?}", write_errors);
+                    return Err(MongoError::custom(format!(
+                        "{} updates failed",
+                        write_errors.len()
+                    )));
+                }
+            }
+            if let Ok(write_concern_error) = result.get_document("writeConcernError") {
+                error!("Batched update failed: {:?}", write_concern_error);
+                return Err(MongoError::custom(format!(
+                    "Write concern error: {}",
+                    write_concern_error.get_str("errmsg").unwrap_or("unknown")
+                )));
+            }
         }
 
-        info!(
-            "Tracking changes, inserted new entry as per policy for job: {}",
-            job_name
-        );
         Ok(())
     }
+
+    /// Splits `update` statements into chunks whose encoded size stays below
+    /// `MAX_UPDATE_COMMAND_BYTES`, so that every chunk fits into a single command.
+    fn chunk_by_size(updates: Vec<Document>) -> Result<Vec<Vec<Document>>, MongoError> {
+        let mut chunks = Vec::new();
+        let mut chunk = Vec::new();
+        let mut chunk_bytes = 0;
+        for update in updates {
+            let update_bytes = mongodb::bson::to_vec(&update)
+                .map_err(|e| MongoError::custom(format!("Failed to encode update: {}", e)))?
+                .len();
+            if !chunk.is_empty() && chunk_bytes + update_bytes > MAX_UPDATE_COMMAND_BYTES {
+                chunks.push(std::mem::take(&mut chunk));
+                chunk_bytes = 0;
+            }
+            chunk_bytes += update_bytes;
+            chunk.push(update);
+        }
+        if !chunk.is_empty() {
+            chunks.push(chunk);
+        }
+        Ok(chunks)
+    }
 }
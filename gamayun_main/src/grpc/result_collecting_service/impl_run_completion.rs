@@ -0,0 +1,157 @@
+use crate::grpc::result_collecting_service::impl_run_history::{ReportedError, RunOutcome};
+use crate::grpc::result_collecting_service::ResultCollectingService;
+use crate::job_scheduling::scheduled_job_tracking_service::{
+    ItemChanges, Job, ResultCounts, SubmittedResults,
+};
+use protos::gamayun::{ReportResultResponse, RunCompletion, RunInformation, RunStatus};
+use tonic::{Response, Status};
+use tracing::{info, instrument, warn};
+
+impl ResultCollectingService {
+    /// Finishes a result submission once its results are stored. A run that is kept open gets
+    /// the submission added to its totals and keeps being tracked, any other run is completed
+    /// with the totals over all of its submissions.
+    ///
+    /// # Arguments
+    ///
+    /// * `run_information` - Information about the job that submitted the results.
+    /// * `submission` - What happened to the submitted results.
+    /// * `keep_run_open` - Whether the job asked to keep the run open until `CompleteRun`.
+    pub(crate) async fn finish_result_submission(
+        &self,
+        run_information: &RunInformation,
+        submission: SubmittedResults,
+        keep_run_open: bool,
+    ) {
+        let tracking_service = &self.app_context.background_job_completion_scheduler;
+
+        if keep_run_open {
+            if !tracking_service
+                .report_results_submitted(&run_information.run_id, submission)
+                .await
+            {
+                warn!(
+                    "Results of run id {} were stored, but the run isn't tracked and can't be kept open",
+                    run_information.run_id
+                );
+            }
+            return;
+        }
+
+        let tracked_run = tracking_service
+            .report_result_returned(&run_information.run_id)
+            .await;
+        let mut results = tracked_run
+            .as_ref()
+            .map(|run| run.results)
+            .unwrap_or_default();
+        results.add(&submission);
+        let mut items = tracked_run
+            .as_ref()
+            .map(|run| run.items.clone())
+            .unwrap_or_default();
+        items.merge(submission.items);
+
+        self.finish_run_results(run_information, tracked_run.as_ref(), &results, items)
+            .await;
+        self.record_run_history(
+            run_information,
+            tracked_run.as_ref(),
+            RunOutcome::Results(results),
+        )
+        .await;
+    }
+
+    /// Notifies about the results of a completed run: too many invalid results, and the new
+    /// and changed items the job notifies about.
+    ///
+    /// # Arguments
+    ///
+    /// * `run_information` - Information about the completed run.
+    /// * `tracked_run` - The run as it was tracked, for its attempt and start time.
+    /// * `results` - What happened to the results, summed over all submissions of the run.
+    /// * `items` - New and changed items over all submissions of the run.
+    pub(crate) async fn finish_run_results(
+        &self,
+        run_information: &RunInformation,
+        tracked_run: Option<&Job>,
+        results: &ResultCounts,
+        items: ItemChanges,
+    ) {
+        self.alert_on_invalid_results(run_information, results)
+            .await;
+        self.notify_about_items(run_information, tracked_run, items)
+            .await;
+    }
+
+    /// Completes a run that was kept open by its result submissions. Partial and failed runs are
+    /// handled like reported errors, including notifications and retries. New and changed items
+    /// are notified about whatever the status, as they are stored already.
+    ///
+    /// # Arguments
+    ///
+    /// * `run_completion` - The final status of the run, and its error if it didn't succeed.
+    /// * `run_information` - Information about the job run, including `run_id` and `job_name`.
+    ///
+    /// # Returns
+    ///
+    /// `Result<Response<ReportResultResponse>, Status>` - Returns the totals over all result
+    /// submissions of the run.
+    #[instrument(skip(self, run_completion))]
+    pub async fn handle_run_completion(
+        &self,
+        run_completion: RunCompletion,
+        run_information: RunInformation,
+    ) -> Result<Response<ReportResultResponse>, Status> {
+        let status = run_completion.status();
+        info!(
+            "Received completion with status {:?} for job: {} and run id {}",
+            status, run_information.job_name, run_information.run_id
+        );
+
+        let tracked_run = self
+            .app_context
+            .background_job_completion_scheduler
+            .report_result_returned(&run_information.run_id)
+            .await;
+        let results = tracked_run
+            .as_ref()
+            .map(|run| run.results)
+            .unwrap_or_default();
+        if let Some(run) = &tracked_run {
+            self.finish_run_results(&run_information, Some(run), &run.results, run.items.clone())
+                .await;
+        }
+
+        match status {
+            RunStatus::Success => {
+                let outcome = if results.submissions == 0 {
+                    RunOutcome::NoResults
+                } else {
+                    RunOutcome::Results(results)
+                };
+                self.record_run_history(&run_information, tracked_run.as_ref(), outcome)
+                    .await;
+            }
+            RunStatus::Partial | RunStatus::Failed => {
+                let error = run_completion
+                    .error
+                    .map(ReportedError::from)
+                    .unwrap_or_else(|| {
+                        ReportedError::unknown("The run completed without reporting an error")
+                    });
+                warn!("Run completed with error: {:?}", error);
+
+                self.handle_failed_run(
+                    error,
+                    &run_information,
+                    tracked_run,
+                    status == RunStatus::Partial,
+                )
+                .await;
+            }
+        }
+
+        Ok(Response::new(results.into()))
+    }
+}
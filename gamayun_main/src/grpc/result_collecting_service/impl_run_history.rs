@@ -0,0 +1,126 @@
+use crate::config::job_config::ErrorCategory;
+use crate::grpc::result_collecting_service::ResultCollectingService;
+use crate::job_scheduling::scheduled_job_tracking_service::{Job, ResultCounts};
+use mongodb::bson::{self, DateTime as BsonDateTime, Document};
+use protos::gamayun::RunInformation;
+use serde::Serialize;
+use std::collections::HashMap;
+use tracing::error;
+
+/// Collection in the Gamayun database holding one entry per reported run.
+pub(crate) const RUN_HISTORY_COLLECTION: &str = "run_history";
+
+/// An error reported by a job, with the details it was reported with.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ReportedError {
+    pub message: String,
+    pub category: ErrorCategory,
+    pub retryable: bool,
+    pub stack_trace: Option<String>,
+    pub context: HashMap<String, String>,
+}
+
+impl ReportedError {
+    /// An error of unknown category that isn't retryable, as reported by older jobs.
+    pub fn unknown(message: impl Into<String>) -> Self {
+        ReportedError {
+            message: message.into(),
+            category: ErrorCategory::Unknown,
+            retryable: false,
+            stack_trace: None,
+            context: HashMap::new(),
+        }
+    }
+}
+
+/// How a run ended, as stored in the run history.
+#[derive(Debug, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub(crate) enum RunOutcome {
+    Results(ResultCounts),
+    NoResults,
+    /// The run stored some of its results before it failed.
+    Partial {
+        results: ResultCounts,
+        error: ReportedError,
+        severity: String,
+        retry_scheduled: bool,
+    },
+    Error {
+        error: ReportedError,
+        severity: String,
+        retry_scheduled: bool,
+        /// Results submitted before the run failed, if any.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        results: Option<ResultCounts>,
+    },
+}
+
+#[derive(Serialize)]
+struct RunHistoryEntry<'a> {
+    job_name: &'a str,
+    run_id: &'a str,
+    /// Missing for runs Gamayun wasn't tracking, such as ones it didn't start.
+    attempt: Option<u32>,
+    started_at: Option<BsonDateTime>,
+    reported_at: BsonDateTime,
+    #[serde(flatten)]
+    outcome: RunOutcome,
+}
+
+impl ResultCollectingService {
+    /// Stores how a run ended in the run history. Failures are only logged, as the report
+    /// itself was handled. Successful runs are reported to the notification sender, which
+    /// notifies when a failing job recovered.
+    ///
+    /// # Arguments
+    ///
+    /// * `run_information` - Information about the run.
+    /// * `tracked_run` - The run as it was tracked while Gamayun waited for its outcome.
+    /// * `outcome` - How the run ended.
+    pub(crate) async fn record_run_history(
+        &self,
+        run_information: &RunInformation,
+        tracked_run: Option<&Job>,
+        outcome: RunOutcome,
+    ) {
+        if matches!(outcome, RunOutcome::Results(_) | RunOutcome::NoResults) {
+            self.app_context
+                .notification_sender
+                .report_success(&run_information.job_name, &run_information.run_id)
+                .await;
+        }
+
+        let entry = RunHistoryEntry {
+            job_name: &run_information.job_name,
+            run_id: &run_information.run_id,
+            attempt: tracked_run.map(|run| run.attempt),
+            started_at: tracked_run
+                .map(|run| BsonDateTime::from_millis(run.started_at.timestamp_millis())),
+            reported_at: BsonDateTime::now(),
+            outcome,
+        };
+
+        let document = match bson::to_document(&entry) {
+            Ok(document) => document,
+            Err(e) => {
+                error!("Failed to serialize run history entry: {:?}", e);
+                return;
+            }
+        };
+
+        if let Err(e) = self
+            .app_context
+            .mongo_client
+            .database(&self.app_context.mongo_db_name)
+            .collection::<Document>(RUN_HISTORY_COLLECTION)
+            .insert_one(document)
+            .await
+        {
+            error!(
+                "Failed to store run history for run {}: {:?}",
+                run_information.run_id, e
+            );
+        }
+    }
+}
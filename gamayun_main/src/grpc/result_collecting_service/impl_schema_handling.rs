@@ -0,0 +1,201 @@
+use crate::config::job_config::JobConfig;
+use crate::config::result_schema::OnInvalidResult;
+use crate::grpc::result_collecting_service::schema_validation::{InvalidResult, ResultValidator};
+use crate::grpc::result_collecting_service::ResultCollectingService;
+use crate::job_scheduling::scheduled_job_tracking_service::ResultCounts;
+use crate::notification::event::{NotificationEvent, NotificationKind, Severity};
+use mongodb::bson::{Bson, DateTime as BsonDateTime, Document};
+use mongodb::Collection;
+use protos::gamayun::RunInformation;
+use tonic::Status;
+use tracing::{error, info, instrument, warn};
+
+const VALIDATION_ERRORS_FIELD: &str = "gamayun_validation_errors";
+pub(crate) const REJECTED_AT_FIELD: &str = "gamayun_rejected_at";
+const RUN_ID_FIELD: &str = "gamayun_run_id";
+
+impl ResultCollectingService {
+    /// Validates and coerces results against the result schema of the job, if it has one.
+    /// Invalid results are dropped, quarantined or kept depending on the schema's
+    /// `on_invalid` setting. Too many invalid results are alerted about when the run completes.
+    ///
+    /// # Arguments
+    ///
+    /// * `job_config` - Configuration of the job that produced the results.
+    /// * `run_id` - ID of the run that produced the results.
+    /// * `collection` - The collection results of the job are stored in.
+    /// * `documents` - The results, already converted to BSON.
+    ///
+    /// # Returns
+    ///
+    /// `Result<(Vec<Document>, u64), Status>` - The results that should be stored and the number
+    /// of invalid results, or a `Status` error if quarantining the invalid results fails.
+    #[instrument(skip(self, job_config, collection, documents))]
+    pub async fn apply_result_schema(
+        &self,
+        job_config: &JobConfig,
+        run_id: &str,
+        collection: &Collection<Document>,
+        documents: Vec<Document>,
+    ) -> Result<(Vec<Document>, u64), Status> {
+        let Some(schema) = &job_config.result_schema else {
+            return Ok((documents, 0));
+        };
+
+        let validator = ResultValidator::new(schema);
+
+        let total = documents.len();
+        let mut valid = Vec::with_capacity(total);
+        let mut invalid = Vec::new();
+        for fields in documents {
+            match validator.validate(fields) {
+                Ok(coerced) => valid.push(coerced),
+                Err(invalid_result) => invalid.push(invalid_result),
+            }
+        }
+
+        if invalid.is_empty() {
+            return Ok((valid, 0));
+        }
+
+        let invalid_count = invalid.len() as u64;
+        warn!(
+            "{} out of {} results for job {} didn't match the result schema. First errors:\n{}",
+            invalid.len(),
+            total,
+            job_config.name,
+            invalid
+                .iter()
+                .take(5)
+                .map(|invalid_result| invalid_result.errors.join("; "))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+
+        match schema.on_invalid {
+            OnInvalidResult::Reject => {
+                info!("Rejected {} invalid results", invalid.len());
+            }
+            OnInvalidResult::Quarantine => {
+                self.quarantine_invalid_results(run_id, collection, &invalid)
+                    .await?;
+            }
+            OnInvalidResult::AcceptWithWarning => {
+                for invalid_result in &invalid {
+                    warn!(
+                        "Storing invalid result anyway: {}",
+                        invalid_result.errors.join("; ")
+                    );
+                }
+                valid.extend(
+                    invalid
+                        .into_iter()
+                        .map(|invalid_result| invalid_result.fields),
+                );
+            }
+        }
+
+        Ok((valid, invalid_count))
+    }
+
+    /// Sends a notification if the ratio of invalid results over all submissions of a
+    /// completed run is above the `rejection_alert_ratio` of the job's result schema.
+    ///
+    /// # Arguments
+    ///
+    /// * `run_information` - Information about the completed run.
+    /// * `results` - What happened to the results of the run, summed over its submissions.
+    pub(crate) async fn alert_on_invalid_results(
+        &self,
+        run_information: &RunInformation,
+        results: &ResultCounts,
+    ) {
+        let reported = results.reported();
+        if results.invalid == 0 || reported == 0 {
+            return;
+        }
+        let Ok(job_config) = self.match_job_config(&run_information.job_name) else {
+            return;
+        };
+        let Some(schema) = &job_config.result_schema else {
+            return;
+        };
+
+        let invalid_ratio = results.invalid as f64 / reported as f64;
+        if invalid_ratio <= schema.rejection_alert_ratio {
+            return;
+        }
+        self.app_context
+            .notification_sender
+            .notify(
+                NotificationEvent::new(
+                    NotificationKind::InvalidResults,
+                    Severity::Warning,
+                    format!("Gamayun High Rejection Rate for job {}", job_config.name),
+                    format!(
+                        "{} out of {} results reported for job {} with run id {} didn't match the result schema, they were handled as {:?}.",
+                        results.invalid,
+                        reported,
+                        job_config.name,
+                        run_information.run_id,
+                        schema.on_invalid
+                    ),
+                )
+                .for_run(
+                    &job_config.name,
+                    &run_information.run_id,
+                    job_config.tags.clone(),
+                ),
+            )
+            .await;
+    }
+
+    /// Stores invalid results into the `<collection>_rejected` collection, next to the
+    /// collection valid results are stored in.
+    #[instrument(skip(self, collection, invalid))]
+    async fn quarantine_invalid_results(
+        &self,
+        run_id: &str,
+        collection: &Collection<Document>,
+        invalid: &[InvalidResult],
+    ) -> Result<(), Status> {
+        let rejected_collection: Collection<Document> = self
+            .app_context
+            .mongo_client
+            .database(&collection.namespace().db)
+            .collection(&format!("{}_rejected", collection.name()));
+
+        let current_time = BsonDateTime::now();
+        let rejected_docs = invalid.iter().map(|invalid_result| {
+            let mut doc = invalid_result.fields.clone();
+            doc.insert(
+                VALIDATION_ERRORS_FIELD,
+                Bson::Array(
+                    invalid_result
+                        .errors
+                        .iter()
+                        .map(|e| Bson::String(e.clone()))
+                        .collect(),
+                ),
+            );
+            doc.insert(RUN_ID_FIELD, run_id);
+            doc.insert(REJECTED_AT_FIELD, current_time);
+            doc
+        });
+
+        rejected_collection
+            .insert_many(rejected_docs)
+            .await
+            .map_err(|e| {
+                error!("Failed to quarantine invalid results: {}", e);
+                Status::internal(format!("MongoDB error: {}", e))
+            })?;
+
+        info!(
+            "Quarantined {} invalid results into {}",
+            invalid.len(),
+            rejected_collection.name()
+        );
+        Ok(())
+    }
+}
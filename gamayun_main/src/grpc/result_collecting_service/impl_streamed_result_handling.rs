@@ -0,0 +1,65 @@
+use crate::grpc::result_collecting_service::ResultCollectingService;
+use protos::gamayun::{ReportResultResponse, ResultChunk, RunInformation};
+use tonic::{Response, Status, Streaming};
+use tracing::{error, info, instrument};
+
+impl ResultCollectingService {
+    /// Processes results that are streamed in chunks. Each chunk is validated and stored as soon
+    /// as it arrives, and the run is marked as completed once the stream ends unless the first
+    /// chunk asks to keep the run open.
+    ///
+    /// # Arguments
+    ///
+    /// * `run_information` - Information about the job that produced the results, taken from
+    ///   the first chunk.
+    /// * `first_chunk` - The first chunk of the stream.
+    /// * `stream` - The rest of the chunks.
+    ///
+    /// # Returns
+    ///
+    /// `Result<Response<ReportResultResponse>, Status>` - Returns the totals over all chunks on
+    /// success, or a `Status` error if receiving, processing or storing a chunk fails.
+    #[instrument(skip(self, first_chunk, stream))]
+    pub async fn handle_streamed_result(
+        &self,
+        run_information: RunInformation,
+        first_chunk: ResultChunk,
+        mut stream: Streaming<ResultChunk>,
+    ) -> Result<Response<ReportResultResponse>, Status> {
+        info!(
+            "Started receiving streamed results for job: {} and run id {}",
+            run_information.job_name, run_information.run_id
+        );
+
+        let keep_run_open = first_chunk.keep_run_open;
+        let mut totals = self
+            .store_results(
+                first_chunk.results,
+                first_chunk.typed_results,
+                &run_information,
+            )
+            .await?;
+        let mut chunk_count = 1;
+
+        while let Some(chunk) = stream.message().await.map_err(|e| {
+            error!("Failed to receive result chunk: {}", e);
+            e
+        })? {
+            let chunk_results = self
+                .store_results(chunk.results, chunk.typed_results, &run_information)
+                .await?;
+            totals.merge(chunk_results);
+            chunk_count += 1;
+        }
+
+        let response = totals.response;
+        self.finish_result_submission(&run_information, totals, keep_run_open)
+            .await;
+
+        info!(
+            "Successfully processed {} result chunks for job: {} and run id {}",
+            chunk_count, run_information.job_name, run_information.run_id
+        );
+        Ok(Response::new(response))
+    }
+}
@@ -1,12 +1,29 @@
 mod common_utils;
+mod impl_artifact_upload;
 mod impl_empty_result_handling;
 mod impl_error_handling;
+mod impl_item_notifications;
+mod impl_progress_handling;
 mod impl_result_handling;
+mod impl_run_completion;
+mod impl_run_history;
+mod impl_schema_handling;
+mod impl_streamed_result_handling;
+mod schema_validation;
 
+pub(crate) use impl_item_notifications::item_notification;
+pub(crate) use impl_result_handling::CREATED_AT_FIELD;
+pub(crate) use impl_run_history::RUN_HISTORY_COLLECTION;
+pub(crate) use impl_schema_handling::REJECTED_AT_FIELD;
+
+use crate::grpc::auth::AuthenticatedReporter;
 use crate::init::AppContext;
 use protos::gamayun::result_reporting_service_server::ResultReportingService;
-use protos::gamayun::{EmptyResponse, JobError, JobResult, RunInformation};
-use tonic::{Request, Response, Status};
+use protos::gamayun::{
+    ArtifactChunk, ArtifactInfo, EmptyResponse, JobError, JobResult, ProgressReport,
+    ReportResultResponse, ResultChunk, RunCompletion, RunInformation,
+};
+use tonic::{Request, Response, Status, Streaming};
 use tracing::error;
 use tracing_futures::Instrument;
 
@@ -25,10 +42,13 @@ impl ResultReportingService for ResultCollectingService {
     async fn report_result(
         &self,
         request: Request<JobResult>,
-    ) -> Result<Response<EmptyResponse>, Status> {
+    ) -> Result<Response<ReportResultResponse>, Status> {
+        let reporter = AuthenticatedReporter::from_request(&request);
         let job_result = request.into_inner();
         match job_result.run_information {
             Some(run_information) => {
+                reporter.authorize(&run_information)?;
+
                 // Create a span with `name` and `runId` added to the tracing context
 
                 let span = tracing::info_span!(
@@ -38,9 +58,14 @@ impl ResultReportingService for ResultCollectingService {
                 );
 
                 // Instrument the future to use the span for subsequent logs
-                self.handle_result(job_result.results, run_information)
-                    .instrument(span)
-                    .await
+                self.handle_result(
+                    job_result.results,
+                    job_result.typed_results,
+                    job_result.keep_run_open,
+                    run_information,
+                )
+                .instrument(span)
+                .await
             }
             None => {
                 error!("Received result for job with no runId");
@@ -49,11 +74,53 @@ impl ResultReportingService for ResultCollectingService {
         }
     }
 
+    async fn stream_results(
+        &self,
+        request: Request<Streaming<ResultChunk>>,
+    ) -> Result<Response<ReportResultResponse>, Status> {
+        let reporter = AuthenticatedReporter::from_request(&request);
+        let mut stream = request.into_inner();
+        let first_chunk = match stream.message().await? {
+            Some(chunk) => chunk,
+            None => {
+                error!("Received an empty result stream");
+                return Err(Status::invalid_argument("Result stream is empty"));
+            }
+        };
+
+        match first_chunk.run_information.clone() {
+            Some(run_information) => {
+                reporter.authorize(&run_information)?;
+
+                // Create a span with `name` and `runId` added to the tracing context
+                let span = tracing::info_span!(
+                    "stream_results",
+                    name = %run_information.job_name,
+                    run_id = %run_information.run_id
+                );
+
+                // Instrument the future to use the span for subsequent logs
+                self.handle_streamed_result(run_information, first_chunk, stream)
+                    .instrument(span)
+                    .await
+            }
+            None => {
+                error!("Received result stream with no runId in the first chunk");
+                Err(Status::invalid_argument(
+                    "RunInformation is required in the first chunk",
+                ))
+            }
+        }
+    }
+
     async fn report_no_result(
         &self,
         request: Request<RunInformation>,
     ) -> Result<Response<EmptyResponse>, Status> {
+        let reporter = AuthenticatedReporter::from_request(&request);
         let run_information = request.into_inner();
+        reporter.authorize(&run_information)?;
+
         let span = tracing::info_span!(
             "report_no_result",
             name = %run_information.job_name,
@@ -70,9 +137,12 @@ impl ResultReportingService for ResultCollectingService {
         &self,
         request: Request<JobError>,
     ) -> Result<Response<EmptyResponse>, Status> {
-        let job_error = request.into_inner();
-        match job_error.run_information {
+        let reporter = AuthenticatedReporter::from_request(&request);
+        let mut job_error = request.into_inner();
+        match job_error.run_information.take() {
             Some(run_information) => {
+                reporter.authorize(&run_information)?;
+
                 // Create a span with `name` and `runId` added to the tracing context
                 let span = tracing::info_span!(
                     "report_error",
@@ -81,7 +151,7 @@ impl ResultReportingService for ResultCollectingService {
                 );
 
                 // Instrument the future to use the span for subsequent logs
-                self.handle_error(job_error.error, run_information)
+                self.handle_error(job_error.into(), run_information)
                     .instrument(span)
                     .await
             }
@@ -91,4 +161,101 @@ impl ResultReportingService for ResultCollectingService {
             }
         }
     }
+
+    async fn report_progress(
+        &self,
+        request: Request<ProgressReport>,
+    ) -> Result<Response<EmptyResponse>, Status> {
+        let reporter = AuthenticatedReporter::from_request(&request);
+        let progress_report = request.into_inner();
+        match progress_report.run_information.clone() {
+            Some(run_information) => {
+                reporter.authorize(&run_information)?;
+
+                // Create a span with `name` and `runId` added to the tracing context
+                let span = tracing::info_span!(
+                    "report_progress",
+                    name = %run_information.job_name,
+                    run_id = %run_information.run_id
+                );
+
+                // Instrument the future to use the span for subsequent logs
+                self.handle_progress(progress_report, run_information)
+                    .instrument(span)
+                    .await
+            }
+            None => {
+                error!("Received progress for job with no runId");
+                Err(Status::invalid_argument("RunInformation is required"))
+            }
+        }
+    }
+
+    async fn complete_run(
+        &self,
+        request: Request<RunCompletion>,
+    ) -> Result<Response<ReportResultResponse>, Status> {
+        let reporter = AuthenticatedReporter::from_request(&request);
+        let mut run_completion = request.into_inner();
+        match run_completion.run_information.take() {
+            Some(run_information) => {
+                reporter.authorize(&run_information)?;
+
+                // Create a span with `name` and `runId` added to the tracing context
+                let span = tracing::info_span!(
+                    "complete_run",
+                    name = %run_information.job_name,
+                    run_id = %run_information.run_id
+                );
+
+                // Instrument the future to use the span for subsequent logs
+                self.handle_run_completion(run_completion, run_information)
+                    .instrument(span)
+                    .await
+            }
+            None => {
+                error!("Received completion for job with no runId");
+                Err(Status::invalid_argument("RunInformation is required"))
+            }
+        }
+    }
+
+    async fn upload_artifact(
+        &self,
+        request: Request<Streaming<ArtifactChunk>>,
+    ) -> Result<Response<ArtifactInfo>, Status> {
+        let reporter = AuthenticatedReporter::from_request(&request);
+        let mut stream = request.into_inner();
+        let mut first_chunk = match stream.message().await? {
+            Some(chunk) => chunk,
+            None => {
+                error!("Received an empty artifact stream");
+                return Err(Status::invalid_argument("Artifact stream is empty"));
+            }
+        };
+
+        match first_chunk.run_information.take() {
+            Some(run_information) => {
+                reporter.authorize(&run_information)?;
+
+                // Create a span with `name` and `runId` added to the tracing context
+                let span = tracing::info_span!(
+                    "upload_artifact",
+                    name = %run_information.job_name,
+                    run_id = %run_information.run_id
+                );
+
+                // Instrument the future to use the span for subsequent logs
+                self.handle_artifact_upload(run_information, first_chunk, stream)
+                    .instrument(span)
+                    .await
+            }
+            None => {
+                error!("Received artifact stream with no runId in the first chunk");
+                Err(Status::invalid_argument(
+                    "RunInformation is required in the first chunk",
+                ))
+            }
+        }
+    }
 }
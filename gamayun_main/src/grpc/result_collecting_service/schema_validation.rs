@@ -0,0 +1,148 @@
+use crate::config::result_schema::{FieldType, ResultSchema};
+use chrono::{DateTime, NaiveDate};
+use mongodb::bson::{Bson, DateTime as BsonDateTime, Document};
+use url::Url;
+
+/// A result that didn't match the job's result schema.
+pub struct InvalidResult {
+    /// The fields of the result, as they were reported.
+    pub fields: Document,
+    /// Human readable descriptions of everything that is wrong with the result.
+    pub errors: Vec<String>,
+}
+
+/// Validates and coerces results against a `ResultSchema`.
+///
+/// Uses the regular expressions compiled when the job configuration was loaded.
+pub struct ResultValidator<'a> {
+    schema: &'a ResultSchema,
+}
+
+impl<'a> ResultValidator<'a> {
+    /// Creates a new validator for the given schema.
+    ///
+    /// # Arguments
+    ///
+    /// * `schema` - The schema the results are validated against.
+    pub fn new(schema: &'a ResultSchema) -> Self {
+        Self { schema }
+    }
+
+    /// Validates a single result, applying default values and coercing field values into the
+    /// configured types. Fields that are not mentioned in the schema are kept as they are.
+    ///
+    /// # Arguments
+    ///
+    /// * `fields` - The fields of the result.
+    ///
+    /// # Returns
+    ///
+    /// `Result<Document, InvalidResult>` - The coerced document, or the original fields together
+    /// with the validation errors.
+    pub fn validate(&self, fields: Document) -> Result<Document, InvalidResult> {
+        let mut coerced = fields.clone();
+        let mut errors = Vec::new();
+
+        for (field_name, field_schema) in &self.schema.fields {
+            let value = match fields.get(field_name).filter(|value| !is_empty(value)) {
+                Some(value) => value.clone(),
+                None => match &field_schema.default {
+                    Some(default) => Bson::String(default.clone()),
+                    None => {
+                        if field_schema.required {
+                            errors.push(format!("Required field '{}' is missing", field_name));
+                        }
+                        continue;
+                    }
+                },
+            };
+
+            if let (Some(regex), Bson::String(s)) = (&field_schema.compiled_regex, &value) {
+                if !regex.is_match(s) {
+                    errors.push(format!(
+                        "Field '{}' with value '{}' doesn't match regex '{}'",
+                        field_name,
+                        s,
+                        regex.as_str()
+                    ));
+                    continue;
+                }
+            }
+
+            let value = match &field_schema.field_type {
+                Some(field_type) => match coerce(&value, field_type) {
+                    Ok(value) => value,
+                    Err(e) => {
+                        errors.push(format!("Field '{}': {}", field_name, e));
+                        continue;
+                    }
+                },
+                None => value,
+            };
+
+            coerced.insert(field_name, value);
+        }
+
+        if errors.is_empty() {
+            Ok(coerced)
+        } else {
+            Err(InvalidResult { fields, errors })
+        }
+    }
+}
+
+fn is_empty(value: &Bson) -> bool {
+    match value {
+        Bson::Null => true,
+        Bson::String(s) => s.trim().is_empty(),
+        _ => false,
+    }
+}
+
+/// Coerces a value into the given field type.
+fn coerce(value: &Bson, field_type: &FieldType) -> Result<Bson, String> {
+    match (field_type, value) {
+        (FieldType::String, Bson::String(_)) => Ok(value.clone()),
+        (
+            FieldType::String,
+            Bson::Int32(_) | Bson::Int64(_) | Bson::Double(_) | Bson::Boolean(_),
+        ) => Ok(Bson::String(value.to_string())),
+        (FieldType::Number, Bson::Int32(_) | Bson::Int64(_) | Bson::Double(_)) => Ok(value.clone()),
+        (FieldType::Number, Bson::String(s)) => {
+            let s = s.trim();
+            s.parse::<i64>()
+                .map(Bson::Int64)
+                .or_else(|_| s.parse::<f64>().map(Bson::Double))
+                .map_err(|_| format!("'{}' is not a number", s))
+        }
+        (FieldType::Boolean, Bson::Boolean(_)) => Ok(value.clone()),
+        (FieldType::Boolean, Bson::String(s)) => match s.trim().to_lowercase().as_str() {
+            "true" => Ok(Bson::Boolean(true)),
+            "false" => Ok(Bson::Boolean(false)),
+            _ => Err(format!("'{}' is not a boolean", s)),
+        },
+        (FieldType::IsoDate, Bson::DateTime(_)) => Ok(value.clone()),
+        (FieldType::IsoDate, Bson::String(s)) => parse_iso_date(s.trim())
+            .map(Bson::DateTime)
+            .ok_or_else(|| format!("'{}' is not an ISO 8601 date", s)),
+        // Stored as reported, as the normalized URL wouldn't match values stored earlier
+        (FieldType::Url, Bson::String(s)) => Url::parse(s.trim())
+            .map(|_| Bson::String(s.trim().to_string()))
+            .map_err(|e| format!("'{}' is not a valid URL: {}", s, e)),
+        (field_type, value) => Err(format!(
+            "value {} can't be converted to {:?}",
+            value, field_type
+        )),
+    }
+}
+
+/// Parses either a full RFC 3339 timestamp or a plain `YYYY-MM-DD` date (midnight UTC).
+fn parse_iso_date(s: &str) -> Option<BsonDateTime> {
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(s) {
+        return Some(BsonDateTime::from_millis(date_time.timestamp_millis()));
+    }
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|date_time| BsonDateTime::from_millis(date_time.and_utc().timestamp_millis()))
+}
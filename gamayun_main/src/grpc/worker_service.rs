@@ -0,0 +1,152 @@
+use crate::grpc::auth::AuthenticatedReporter;
+use crate::job_scheduling::scheduled_job_tracking_service::ScheduledJobTrackingService;
+use crate::job_scheduling::worker_registry::{RunExit, WorkerRegistry, WORKER_HEARTBEAT_INTERVAL};
+use protos::gamayun::worker_service_server::WorkerService;
+use protos::gamayun::{
+    EmptyResponse, JobAssignment, JobExit, WorkerIdentity, WorkerRegistration,
+    WorkerRegistrationResponse,
+};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+use tracing::{error, info, warn};
+
+/// gRPC service remote workers register with and receive their runs from.
+pub struct RemoteWorkerService {
+    worker_registry: WorkerRegistry,
+    scheduled_job_tracking_service: ScheduledJobTrackingService,
+}
+
+impl RemoteWorkerService {
+    pub fn new(
+        worker_registry: WorkerRegistry,
+        scheduled_job_tracking_service: ScheduledJobTrackingService,
+    ) -> Self {
+        Self {
+            worker_registry,
+            scheduled_job_tracking_service,
+        }
+    }
+}
+
+fn unknown_worker(worker_id: &str) -> Status {
+    warn!("Request from unknown worker {}", worker_id);
+    Status::not_found(format!(
+        "Worker {} is not registered, register again",
+        worker_id
+    ))
+}
+
+#[tonic::async_trait]
+impl WorkerService for RemoteWorkerService {
+    async fn register_worker(
+        &self,
+        request: Request<WorkerRegistration>,
+    ) -> Result<Response<WorkerRegistrationResponse>, Status> {
+        AuthenticatedReporter::from_request(&request).authorize_worker()?;
+        let registration = request.into_inner();
+        if registration.name.is_empty() {
+            return Err(Status::invalid_argument("Worker name is required"));
+        }
+
+        let worker_id = self
+            .worker_registry
+            .register(registration.name, registration.labels)
+            .await;
+
+        Ok(Response::new(WorkerRegistrationResponse {
+            worker_id,
+            heartbeat_interval_millis: WORKER_HEARTBEAT_INTERVAL.as_millis() as u64,
+        }))
+    }
+
+    type PollJobsStream = ReceiverStream<Result<JobAssignment, Status>>;
+
+    async fn poll_jobs(
+        &self,
+        request: Request<WorkerIdentity>,
+    ) -> Result<Response<Self::PollJobsStream>, Status> {
+        AuthenticatedReporter::from_request(&request).authorize_worker()?;
+        let worker_id = request.into_inner().worker_id;
+
+        match self
+            .worker_registry
+            .open_assignment_stream(&worker_id)
+            .await
+        {
+            Some(receiver) => Ok(Response::new(ReceiverStream::new(receiver))),
+            None => Err(unknown_worker(&worker_id)),
+        }
+    }
+
+    async fn heartbeat(
+        &self,
+        request: Request<WorkerIdentity>,
+    ) -> Result<Response<EmptyResponse>, Status> {
+        AuthenticatedReporter::from_request(&request).authorize_worker()?;
+        let worker_id = request.into_inner().worker_id;
+
+        if self.worker_registry.heartbeat(&worker_id).await {
+            Ok(Response::new(EmptyResponse {}))
+        } else {
+            Err(unknown_worker(&worker_id))
+        }
+    }
+
+    async fn report_job_exit(
+        &self,
+        request: Request<JobExit>,
+    ) -> Result<Response<EmptyResponse>, Status> {
+        AuthenticatedReporter::from_request(&request).authorize_worker()?;
+        let job_exit = request.into_inner();
+        let Some(run_information) = job_exit.run_information else {
+            error!("Received a job exit with no runId");
+            return Err(Status::invalid_argument("RunInformation is required"));
+        };
+
+        let worker_name = match self
+            .worker_registry
+            .complete_run(&job_exit.worker_id, &run_information.run_id)
+            .await
+        {
+            RunExit::Completed(worker_name) => worker_name,
+            RunExit::UnknownWorker => return Err(unknown_worker(&job_exit.worker_id)),
+            RunExit::NotAssigned(worker_name) => {
+                warn!(
+                    "Worker {} reported the exit of run {}, which wasn't assigned to it",
+                    worker_name, run_information.run_id
+                );
+                return Err(Status::permission_denied(format!(
+                    "Run {} is not assigned to the worker",
+                    run_information.run_id
+                )));
+            }
+        };
+
+        match job_exit.exit_code {
+            Some(0) => info!(
+                "Job {} with run ID {} exited successfully on worker {}",
+                run_information.job_name, run_information.run_id, worker_name
+            ),
+            Some(exit_code) => error!(
+                "Job {} with run ID {} exited with code {} on worker {}: {}",
+                run_information.job_name,
+                run_information.run_id,
+                exit_code,
+                worker_name,
+                job_exit.error
+            ),
+            None => {
+                error!(
+                    "Job {} with run ID {} didn't run to completion on worker {}: {}",
+                    run_information.job_name, run_information.run_id, worker_name, job_exit.error
+                );
+                // The run won't report anymore, same as a local job that failed to start
+                self.scheduled_job_tracking_service
+                    .cancel_run(&run_information.run_id)
+                    .await;
+            }
+        }
+
+        Ok(Response::new(EmptyResponse {}))
+    }
+}
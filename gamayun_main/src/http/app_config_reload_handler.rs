@@ -1,6 +1,6 @@
 use crate::init::AppContext;
 use crate::job_scheduling::config_reload::handle_config_reload_request;
-use crate::notification::NotificationSender;
+use crate::notification::event::{NotificationEvent, NotificationKind, Severity};
 use actix_web::{post, web, HttpResponse, Responder};
 use tracing::{error, info};
 
@@ -24,10 +24,12 @@ pub(crate) async fn handle_jobs_config_reload_request(
             error!("Failed to reload job configuration: {:?}", e);
             app_context
                 .notification_sender
-                .notify(
+                .notify(NotificationEvent::new(
+                    NotificationKind::ReloadFailure,
+                    Severity::Error,
                     "Gamayun Job Configuration Reload Failure".to_string(),
                     format!("Failed to reload job configuration: {:?}", e),
-                )
+                ))
                 .await;
             HttpResponse::InternalServerError()
                 .body("Failed to reload job configuration".to_string())
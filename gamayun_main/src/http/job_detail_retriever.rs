@@ -0,0 +1,50 @@
+use crate::init::AppContext;
+use crate::job_scheduling::scheduled_job_tracking_service::Job;
+use actix_web::{get, web, HttpResponse, Responder};
+use serde::Serialize;
+use tracing::{error, info};
+
+#[derive(Serialize)]
+struct JobDetailResponse {
+    name: String,
+    cron_string: String,
+    tags: Vec<String>,
+    /// Runs that have been started but haven't reported their results yet.
+    active_runs: Vec<Job>,
+}
+
+#[get("/jobs/{job_name}")]
+pub(super) async fn retrieve_job_detail(
+    app_context: web::Data<AppContext>,
+    job_name: web::Path<String>,
+) -> impl Responder {
+    let job_name = job_name.into_inner();
+    info!("Received request for details of job {}", job_name);
+
+    let job_config = match app_context.job_configs.read() {
+        Ok(job_configs) => job_configs
+            .iter()
+            .find(|config| config.name == job_name)
+            .cloned(),
+        Err(e) => {
+            error!("Failed to read job configurations: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let Some(job_config) = job_config else {
+        return HttpResponse::NotFound().body(format!("No job config found for job {}", job_name));
+    };
+
+    let active_runs = app_context
+        .background_job_completion_scheduler
+        .runs_for_job(&job_name)
+        .await;
+
+    HttpResponse::Ok().json(JobDetailResponse {
+        name: job_config.name,
+        cron_string: job_config.cron_string,
+        tags: job_config.tags,
+        active_runs,
+    })
+}
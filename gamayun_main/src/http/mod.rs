@@ -9,8 +9,12 @@ use tracing::info;
 use tracing_actix_web::TracingLogger;
 
 mod app_config_reload_handler;
+mod job_detail_retriever;
+mod notification_retriever;
 mod routes;
+mod run_detail_retriever;
 mod version_retriever;
+mod worker_retriever;
 
 pub async fn run_actix_server(
     app_context: AppContext,
@@ -28,8 +32,7 @@ pub async fn run_actix_server(
         .next()
         .context("No socket addresses yielded")?;
 
-    info!("Starting Actix Web server on {}:{}", host, port);
-
+    let tls = app_context.tls.clone();
     let state = web::Data::new(app_context);
 
     let server = HttpServer::new(move || {
@@ -37,8 +40,19 @@ pub async fn run_actix_server(
             .wrap(TracingLogger::default())
             .app_data(state.clone())
             .service(assemble_routes())
-    })
-    .bind(addr)
+    });
+
+    let server = match tls {
+        Some(tls) => {
+            info!("Starting Actix Web server on {}:{} with TLS", host, port);
+            let server_config = tls.server_config(vec![b"h2".to_vec(), b"http/1.1".to_vec()])?;
+            server.bind_rustls_0_23(addr, server_config)
+        }
+        None => {
+            info!("Starting Actix Web server on {}:{}", host, port);
+            server.bind(addr)
+        }
+    }
     .context("Failed to bind Actix Web server")?;
 
     let server_handle = server.run();
@@ -0,0 +1,9 @@
+use crate::init::AppContext;
+use actix_web::{get, web, HttpResponse, Responder};
+use tracing::info;
+
+#[get("/notifications/dead-letters")]
+pub(super) async fn retrieve_dead_letters(app_context: web::Data<AppContext>) -> impl Responder {
+    info!("Received request for the dead-lettered notifications");
+    HttpResponse::Ok().json(app_context.notification_sender.dead_letters())
+}
@@ -1,9 +1,18 @@
 use crate::http::app_config_reload_handler::reload_job_config;
+use crate::http::job_detail_retriever::retrieve_job_detail;
+use crate::http::notification_retriever::retrieve_dead_letters;
+use crate::http::run_detail_retriever::{download_artifact, retrieve_run_detail};
 use crate::http::version_retriever::retrieve_version;
+use crate::http::worker_retriever::retrieve_workers;
 use actix_web::{web, Scope};
 
 pub(crate) fn assemble_routes() -> Scope {
     web::scope("/api/v1")
         .service(reload_job_config)
         .service(retrieve_version)
+        .service(retrieve_job_detail)
+        .service(retrieve_run_detail)
+        .service(download_artifact)
+        .service(retrieve_workers)
+        .service(retrieve_dead_letters)
 }
@@ -0,0 +1,148 @@
+use crate::artifacts::{validate_artifact_name, ArtifactMetadata};
+use crate::grpc::result_collecting_service::RUN_HISTORY_COLLECTION;
+use crate::init::AppContext;
+use crate::job_scheduling::scheduled_job_tracking_service::Job;
+use actix_web::http::header::{ContentDisposition, ContentType, DispositionParam, DispositionType};
+use actix_web::{get, web, HttpResponse, Responder};
+use futures::TryStreamExt;
+use mongodb::bson::{doc, Bson, Document};
+use serde::Serialize;
+use tracing::{error, info};
+
+#[derive(Serialize)]
+struct ArtifactSummary {
+    #[serde(flatten)]
+    metadata: ArtifactMetadata,
+    download_path: String,
+}
+
+#[derive(Serialize)]
+struct RunDetailResponse {
+    job_name: String,
+    run_id: String,
+    /// The run, if it's still waiting for its outcome.
+    active_run: Option<Job>,
+    /// How the run ended, once it reported its outcome.
+    history: Vec<serde_json::Value>,
+    artifacts: Vec<ArtifactSummary>,
+}
+
+#[get("/jobs/{job_name}/runs/{run_id}")]
+pub(super) async fn retrieve_run_detail(
+    app_context: web::Data<AppContext>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (job_name, run_id) = path.into_inner();
+    info!(
+        "Received request for details of run {} of job {}",
+        run_id, job_name
+    );
+
+    let active_run = app_context
+        .background_job_completion_scheduler
+        .runs_for_job(&job_name)
+        .await
+        .into_iter()
+        .find(|run| run.run_id == run_id);
+
+    let history: Vec<Document> = match app_context
+        .mongo_client
+        .database(&app_context.mongo_db_name)
+        .collection::<Document>(RUN_HISTORY_COLLECTION)
+        .find(doc! { "job_name": &job_name, "run_id": &run_id })
+        .projection(doc! { "_id": 0 })
+        .await
+    {
+        Ok(cursor) => match cursor.try_collect().await {
+            Ok(history) => history,
+            Err(e) => {
+                error!("Failed to read run history: {:?}", e);
+                return HttpResponse::InternalServerError().finish();
+            }
+        },
+        Err(e) => {
+            error!("Failed to query run history: {:?}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let artifacts = match app_context.artifact_store.list(&job_name, &run_id).await {
+        Ok(artifacts) => artifacts,
+        Err(e) => {
+            error!("Failed to list artifacts: {:?}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    if active_run.is_none() && history.is_empty() && artifacts.is_empty() {
+        return HttpResponse::NotFound().body(format!(
+            "No run found with id {} for job {}",
+            run_id, job_name
+        ));
+    }
+
+    HttpResponse::Ok().json(RunDetailResponse {
+        active_run,
+        history: history
+            .into_iter()
+            .map(|entry| Bson::Document(entry).into_relaxed_extjson())
+            .collect(),
+        artifacts: artifacts
+            .into_iter()
+            .map(|metadata| ArtifactSummary {
+                download_path: format!(
+                    "/api/v1/jobs/{}/runs/{}/artifacts/{}",
+                    job_name, run_id, metadata.name
+                ),
+                metadata,
+            })
+            .collect(),
+        job_name,
+        run_id,
+    })
+}
+
+#[get("/jobs/{job_name}/runs/{run_id}/artifacts/{artifact_name}")]
+pub(super) async fn download_artifact(
+    app_context: web::Data<AppContext>,
+    path: web::Path<(String, String, String)>,
+) -> impl Responder {
+    let (job_name, run_id, artifact_name) = path.into_inner();
+    info!(
+        "Received request for artifact {} of run {} of job {}",
+        artifact_name, run_id, job_name
+    );
+
+    if let Err(e) = validate_artifact_name(&artifact_name) {
+        return HttpResponse::BadRequest().body(e);
+    }
+
+    match app_context
+        .artifact_store
+        .load(&job_name, &run_id, &artifact_name)
+        .await
+    {
+        Ok(Some((metadata, data))) => {
+            let content_type = metadata
+                .content_type
+                .parse()
+                .map(ContentType)
+                .unwrap_or_else(|_| ContentType::octet_stream());
+            HttpResponse::Ok()
+                .insert_header(content_type)
+                .insert_header(ContentDisposition {
+                    disposition: DispositionType::Attachment,
+                    parameters: vec![DispositionParam::Filename(metadata.name)],
+                })
+                .body(data)
+        }
+        Ok(None) => HttpResponse::NotFound().body(format!(
+            "No artifact {} found for run {} of job {}",
+            artifact_name, run_id, job_name
+        )),
+        Err(e) => {
+            error!("Failed to load artifact: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
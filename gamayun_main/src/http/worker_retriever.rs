@@ -0,0 +1,9 @@
+use crate::init::AppContext;
+use actix_web::{get, web, HttpResponse, Responder};
+use tracing::info;
+
+#[get("/workers")]
+pub(super) async fn retrieve_workers(app_context: web::Data<AppContext>) -> impl Responder {
+    info!("Received request for the registered workers");
+    HttpResponse::Ok().json(app_context.worker_registry.workers().await)
+}
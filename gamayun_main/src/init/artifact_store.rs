@@ -0,0 +1,55 @@
+use crate::artifacts::gridfs_artifact_store::GridFsArtifactStore;
+use crate::artifacts::local_artifact_store::LocalArtifactStore;
+use crate::artifacts::ArtifactStore;
+use crate::config::app_config::{AppConfig, ArtifactBackend};
+use anyhow::{Context, Result};
+use mongodb::Client;
+use std::sync::Arc;
+use tracing::info;
+
+const DEFAULT_ARTIFACT_BUCKET_NAME: &str = "artifacts";
+
+/// Initializes the `ArtifactStore` of the configured backend, defaulting to a GridFS bucket
+/// in the Gamayun database.
+///
+/// # Arguments
+///
+/// * `app_config` - The application configuration with the artifact storage settings.
+/// * `mongo_client` - MongoDB client used by the GridFS backend.
+/// * `mongo_db_name` - Name of the Gamayun database.
+///
+/// # Returns
+///
+/// The artifact store, or an error if the `LocalDirectory` backend has no directory set.
+pub fn initialize_artifact_store(
+    app_config: &AppConfig,
+    mongo_client: &Client,
+    mongo_db_name: &str,
+) -> Result<Arc<dyn ArtifactStore>> {
+    let storage_config = app_config.artifact_storage.clone();
+    let backend = storage_config
+        .as_ref()
+        .map(|config| config.backend.clone())
+        .unwrap_or(ArtifactBackend::GridFs);
+
+    match backend {
+        ArtifactBackend::GridFs => {
+            let bucket_name = storage_config
+                .and_then(|config| config.bucket_name)
+                .unwrap_or_else(|| DEFAULT_ARTIFACT_BUCKET_NAME.to_string());
+            info!("Storing artifacts in GridFS bucket {}", bucket_name);
+            Ok(Arc::new(GridFsArtifactStore::new(
+                mongo_client,
+                mongo_db_name,
+                bucket_name,
+            )))
+        }
+        ArtifactBackend::LocalDirectory => {
+            let directory = storage_config
+                .and_then(|config| config.directory)
+                .context("The LocalDirectory artifact backend requires a directory")?;
+            info!("Storing artifacts in directory {}", directory);
+            Ok(Arc::new(LocalArtifactStore::new(directory)))
+        }
+    }
+}
@@ -1,17 +1,24 @@
 use anyhow::Context;
 use chrono::Utc;
 use std::env;
-use tracing::{error, info};
+use std::sync::{Arc, RwLock};
+use tracing::{error, info, warn};
 
-use crate::config::app_config::initialize_app_config;
+use crate::artifacts::ArtifactStore;
+use crate::config::app_config::{initialize_app_config, AppConfig};
 use crate::config::job_config::JobConfig;
+use crate::grpc::local_server_url;
+use crate::job_scheduling::retention::schedule_retention_cleanup;
 use crate::job_scheduling::scheduled_job_tracking_service::ScheduledJobTrackingService;
+use crate::job_scheduling::worker_registry::WorkerRegistry;
 use crate::job_scheduling::{schedule_jobs_from_config, start_background_job_reporting_check};
 use crate::notification::composite_notification_sender::CompositeNotificationSender;
-use crate::notification::NotificationSender;
+use crate::notification::event::{NotificationEvent, NotificationKind, Severity};
+use crate::tls::ReloadableTls;
 use grizzly_scheduler::scheduler::Scheduler;
 use mongodb::Client;
 
+mod artifact_store;
 mod mongo;
 mod notification_sender;
 mod observability;
@@ -26,18 +33,28 @@ pub struct AppContext {
     pub app_version: String,
     /// Configuration root directory
     pub config_root: String,
+    /// Application configuration.
+    pub app_config: AppConfig,
     /// MongoDB client instance.
     pub mongo_client: Client,
     /// Background job completion scheduler.
     pub background_job_completion_scheduler: ScheduledJobTrackingService,
+    /// Registry of the remote workers jobs with a `run_on` selector are dispatched to.
+    pub worker_registry: WorkerRegistry,
     /// Scheduler for job scheduling.
     pub scheduler: Scheduler<Utc>,
-    /// Job configurations loaded from the config.
-    pub job_configs: Vec<JobConfig>,
+    /// Job configurations loaded from the config, replaced on config reload.
+    pub job_configs: Arc<RwLock<Vec<JobConfig>>>,
     /// Name of the MongoDB database.
     pub mongo_db_name: String,
     /// Composite notification sender used to send notifications.
     pub notification_sender: CompositeNotificationSender,
+    /// TLS certificates of the gRPC and HTTP servers, if TLS is configured.
+    pub tls: Option<ReloadableTls>,
+    /// URL of the gRPC server, passed to local jobs as `GAMAYUN_SERVER_URL`.
+    pub server_url: String,
+    /// Storage of the artifacts uploaded by runs.
+    pub artifact_store: Arc<dyn ArtifactStore>,
 }
 
 /// Initializes the first stage of the application.
@@ -46,20 +63,22 @@ pub struct AppContext {
 ///
 /// # Returns
 ///
-/// A `Result` containing the `CompositeNotificationSender` or an error if initialization fails.
+/// A `Result` containing the `AppConfig` and the `CompositeNotificationSender` or an error if
+/// initialization fails.
 async fn init_notification_and_logging(
     app_version: String,
     config_root: String,
-) -> Result<CompositeNotificationSender, Box<dyn std::error::Error>> {
+) -> Result<(AppConfig, CompositeNotificationSender), Box<dyn std::error::Error>> {
     observability::initialize_tracing_subscriber(app_version);
     info!("Initializing app configuration");
     let app_config = initialize_app_config(config_root.clone())?;
     info!("App configuration initialized");
 
-    let notification_sender = notification_sender::initialize_notification_sender(app_config);
+    let notification_sender =
+        notification_sender::initialize_notification_sender(app_config.clone(), &config_root);
     info!("Notification sender initialized");
 
-    Ok(notification_sender)
+    Ok((app_config, notification_sender))
 }
 
 /// Initializes the second stage of the application.
@@ -68,30 +87,78 @@ async fn init_notification_and_logging(
 ///
 /// # Arguments
 ///
+/// * `app_config` - The application configuration.
 /// * `notification_sender` - A `CompositeNotificationSender` instance used for notifications.
 ///
 /// # Returns
 ///
 /// A `Result` containing the `AppContext` or an error if initialization fails.
 async fn init_other_services(
+    app_config: AppConfig,
     notification_sender: CompositeNotificationSender,
     app_version: String,
     config_root: String,
 ) -> Result<AppContext, Box<dyn std::error::Error>> {
+    // Load the TLS certificates before anything is scheduled, so bad certificates fail fast
+    let tls = app_config
+        .tls
+        .clone()
+        .map(ReloadableTls::new)
+        .transpose()
+        .context("Failed to load TLS certificates")?;
+
+    let server_url = local_server_url(tls.is_some())?;
+
     // Initialize MongoDB client
     let (mongo_client, mongo_db_name) = mongo::initialize_mongo_client().await?;
 
+    notification_sender
+        .persist_outbox(&mongo_client.database(&mongo_db_name))
+        .await;
+
+    let artifact_store =
+        artifact_store::initialize_artifact_store(&app_config, &mongo_client, &mongo_db_name)?;
+
     // Initialize the scheduler
     let scheduler = grizzly_scheduler::scheduler::Scheduler::new_in_utc();
 
     let background_job_completion_scheduler =
         start_background_job_reporting_check(scheduler.clone(), notification_sender.clone());
 
+    let worker_registry = WorkerRegistry::new(scheduler.clone(), notification_sender.clone())?;
+
+    notification_sender.schedule_redelivery(scheduler.clone())?;
+    if let Some(throttling) = &app_config.notification_throttling {
+        notification_sender.schedule_digests(scheduler.clone(), throttling)?;
+    }
+
     // Schedule jobs from config
     let job_configs = schedule_jobs_from_config(
         scheduler.clone(),
         background_job_completion_scheduler.clone(),
+        worker_registry.clone(),
+        server_url.clone(),
         config_root.clone(),
+        &mongo_db_name,
+    )?;
+
+    notification_sender.set_job_configs(&job_configs).await;
+
+    let has_static_tokens = app_config
+        .grpc_auth
+        .as_ref()
+        .is_some_and(|grpc_auth| !grpc_auth.static_tokens.is_empty());
+    if !has_static_tokens && job_configs.iter().any(|job| job.run_on.is_some()) {
+        warn!("Jobs run on remote workers, but workers can't connect without a static token in grpc_auth");
+    }
+
+    // Schedule cleanup of results based on job retention policies
+    schedule_retention_cleanup(
+        scheduler.clone(),
+        mongo_client.clone(),
+        mongo_db_name.clone(),
+        artifact_store.clone(),
+        &job_configs,
     )?;
 
     scheduler.start()?;
@@ -102,12 +169,17 @@ async fn init_other_services(
     Ok(AppContext {
         app_version,
         config_root,
+        app_config,
         mongo_client,
         background_job_completion_scheduler,
+        worker_registry,
         scheduler,
-        job_configs,
+        job_configs: Arc::new(RwLock::new(job_configs)),
         mongo_db_name,
         notification_sender,
+        tls,
+        server_url,
+        artifact_store,
     })
 }
 
@@ -129,13 +201,25 @@ pub async fn initialize() -> AppContext {
         .unwrap(); //we want to kill the app if this is not set
 
     match init_notification_and_logging(app_version.clone(), config_root.clone()).await {
-        Ok(notification_sender) => {
-            match init_other_services(notification_sender.clone(), app_version, config_root).await {
+        Ok((app_config, notification_sender)) => {
+            match init_other_services(
+                app_config,
+                notification_sender.clone(),
+                app_version,
+                config_root,
+            )
+            .await
+            {
                 Ok(context) => context,
                 Err(e) => {
                     error!("Initialization failed: {}", e);
                     notification_sender
-                        .notify("Initialization failed".to_string(), e.to_string())
+                        .notify_and_wait(NotificationEvent::new(
+                            NotificationKind::StartupFailure,
+                            Severity::Critical,
+                            "Initialization failed".to_string(),
+                            e.to_string(),
+                        ))
                         .await;
                     panic!("Initialization failed: {}", e);
                 }
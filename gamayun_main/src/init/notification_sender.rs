@@ -1,37 +1,137 @@
-use crate::config::app_config::AppConfig;
-use crate::notification::composite_notification_sender::CompositeNotificationSender;
+use crate::config::app_config::{AppConfig, ChatWebhookConfig, WebhookConfig};
+use crate::notification::chat::discord_notification_sender::DiscordNotificationSender;
+use crate::notification::chat::mattermost_notification_sender::MattermostNotificationSender;
+use crate::notification::chat::slack_notification_sender::SlackNotificationSender;
+use crate::notification::chat::teams_notification_sender::TeamsNotificationSender;
+use crate::notification::chat::ChatWebhookConfiguration;
+use crate::notification::composite_notification_sender::{
+    CompositeNotificationSender, NamedNotificationSender,
+};
 use crate::notification::mail::sendgrid_notification_sender::{
     SendGridConfiguration, SendGridNotificationSender,
 };
+use crate::notification::mail::smtp_notification_sender::{
+    SmtpConfiguration, SmtpNotificationSender,
+};
+use crate::notification::push::ntfy_notification_sender::{
+    NtfyConfiguration, NtfyNotificationSender,
+};
+use crate::notification::push::telegram_notification_sender::{
+    TelegramConfiguration, TelegramNotificationSender,
+};
+use crate::notification::template::NotificationTemplates;
+use crate::notification::webhook::webhook_notification_sender::{
+    WebhookConfiguration, WebhookNotificationSender,
+};
 use crate::notification::NotificationSender;
-use std::sync::Arc;
+use std::time::Duration;
+use tracing::error;
 
 /// Initializes a `CompositeNotificationSender` with available notification senders.
 ///
 /// This function takes the application's configuration and initializes all configured
-/// notification senders, such as SendGrid. It aggregates these senders into a
+/// notification senders, such as SendGrid, SMTP, chat webhooks, push services and outbound
+/// webhooks. It aggregates these senders into a
 /// `CompositeNotificationSender`, which can then be used to send notifications through
-/// multiple channels.
+/// multiple channels, routed by the notification routing rules.
 ///
 /// # Parameters
 ///
 /// - `app_config`: The application's configuration containing settings for various
 ///   notification senders.
+/// - `config_root`: The configuration root directory, holding the notification templates.
 ///
 /// # Returns
 ///
 /// A `CompositeNotificationSender` instance containing all successfully initialized
 /// notification senders.
-pub fn initialize_notification_sender(app_config: AppConfig) -> CompositeNotificationSender {
-    let senders_opt = vec![initialize_send_grid_notifier(app_config)];
+pub fn initialize_notification_sender(
+    app_config: AppConfig,
+    config_root: &str,
+) -> CompositeNotificationSender {
+    let senders_opt: Vec<Option<NamedNotificationSender>> = vec![
+        initialize_smtp_notifier(&app_config).map(|s| {
+            NamedNotificationSender::new(
+                sender_name(&app_config.smtp_config, |c| &c.name, "smtp"),
+                s,
+            )
+            .with_destination(sender_destination(&app_config.smtp_config, |c| {
+                format!("{}/{}", c.host, c.to_emails.join(","))
+            }))
+        }),
+        initialize_send_grid_notifier(app_config.clone()).map(|s| {
+            NamedNotificationSender::new(
+                sender_name(&app_config.sendgrid_config, |c| &c.name, "sendgrid"),
+                s,
+            )
+            .with_destination(sender_destination(&app_config.sendgrid_config, |c| {
+                c.to_emails.join(",")
+            }))
+        }),
+        initialize_ntfy_notifier(&app_config).map(|s| {
+            NamedNotificationSender::new(
+                sender_name(&app_config.ntfy_config, |c| &c.name, "ntfy"),
+                s,
+            )
+            .with_destination(sender_destination(&app_config.ntfy_config, |c| {
+                format!(
+                    "{}/{}",
+                    c.server_url.as_deref().unwrap_or_default(),
+                    c.topic
+                )
+            }))
+        }),
+    ];
+
+    let mut senders: Vec<NamedNotificationSender> = senders_opt.into_iter().flatten().collect();
+    senders.extend(initialize_telegram_notifiers(&app_config));
+    senders.extend(initialize_chat_notifiers(
+        "Slack",
+        "slack",
+        &app_config.slack_webhooks,
+        SlackNotificationSender::new,
+    ));
+    senders.extend(initialize_chat_notifiers(
+        "Discord",
+        "discord",
+        &app_config.discord_webhooks,
+        DiscordNotificationSender::new,
+    ));
+    senders.extend(initialize_chat_notifiers(
+        "Mattermost",
+        "mattermost",
+        &app_config.mattermost_webhooks,
+        MattermostNotificationSender::new,
+    ));
+    senders.extend(initialize_chat_notifiers(
+        "Microsoft Teams",
+        "teams",
+        &app_config.teams_webhooks,
+        TeamsNotificationSender::new,
+    ));
+    senders.extend(initialize_webhook_notifiers(&app_config.webhooks));
 
-    let senders: Vec<Arc<dyn NotificationSender>> = senders_opt
-        .into_iter()
-        .filter_map(|sender| sender.map(|s| Arc::new(s) as Arc<dyn NotificationSender>))
-        .collect();
+    CompositeNotificationSender::new(
+        Some(senders),
+        app_config.notification_routing.clone(),
+        app_config.notification_throttling.as_ref(),
+        &app_config.notification_delivery.clone().unwrap_or_default(),
+        NotificationTemplates::load(config_root, app_config.notification_templates.as_ref()),
+    )
+}
 
-    let composite_sender = CompositeNotificationSender::new(Some(senders));
-    composite_sender
+/// Returns the name routing rules refer to a sender by, the configured one or the default of
+/// the sender type.
+fn sender_name<C>(config: &Option<C>, name: fn(&C) -> &Option<String>, default: &str) -> String {
+    config
+        .as_ref()
+        .and_then(|config| name(config).clone())
+        .unwrap_or_else(|| default.to_string())
+}
+
+/// Returns where a sender delivers to, which identifies its outbox entries across restarts.
+fn sender_destination<C>(config: &Option<C>, destination: fn(&C) -> String) -> String {
+    config.as_ref().map(destination).unwrap_or_default()
 }
 
 /// Initializes a `SendGridNotificationSender` if SendGrid is configured.
@@ -46,14 +146,227 @@ pub fn initialize_notification_sender(app_config: AppConfig) -> CompositeNotific
 ///
 /// # Returns
 ///
-/// An `Option<SendGridNotificationSender>`. Returns `Some` if SendGrid is configured;
-/// otherwise, returns `None`.
+/// An `Option<SendGridNotificationSender>`. Returns `Some` if SendGrid is configured and
+/// the sender could be created; otherwise, returns `None`.
 fn initialize_send_grid_notifier(app_config: AppConfig) -> Option<SendGridNotificationSender> {
-    app_config.sendgrid_config.map(|sendgrid_config| {
-        SendGridNotificationSender::new(SendGridConfiguration {
-            from_email: sendgrid_config.from_email,
-            to_emails: sendgrid_config.to_emails,
-            api_key: sendgrid_config.api_key,
+    let sendgrid_config = app_config.sendgrid_config?;
+    match SendGridNotificationSender::new(SendGridConfiguration {
+        from_email: sendgrid_config.from_email,
+        to_emails: sendgrid_config.to_emails,
+        api_key: sendgrid_config.api_key,
+    }) {
+        Ok(sender) => Some(sender),
+        Err(e) => {
+            error!("Failed to initialize SendGrid notifications: {:?}", e);
+            None
+        }
+    }
+}
+
+/// Initializes a `SmtpNotificationSender` if SMTP is configured.
+///
+/// An invalid SMTP configuration is logged and the sender is left out, so the other
+/// notification senders keep working.
+///
+/// # Parameters
+///
+/// - `app_config`: The application's configuration containing SMTP settings.
+///
+/// # Returns
+///
+/// An `Option<SmtpNotificationSender>`. Returns `Some` if SMTP is configured and valid;
+/// otherwise, returns `None`.
+fn initialize_smtp_notifier(app_config: &AppConfig) -> Option<SmtpNotificationSender> {
+    let smtp_config = app_config.smtp_config.clone()?;
+    let host = smtp_config.host.clone();
+    match SmtpNotificationSender::new(SmtpConfiguration {
+        host: smtp_config.host,
+        port: smtp_config.port,
+        tls: smtp_config.tls,
+        username: smtp_config.username,
+        password: smtp_config.password,
+        from_email: smtp_config.from_email,
+        to_emails: smtp_config.to_emails,
+    }) {
+        Ok(sender) => Some(sender),
+        Err(e) => {
+            error!(
+                "Failed to initialize SMTP notifications via {}: {:?}",
+                host, e
+            );
+            None
+        }
+    }
+}
+
+/// Initializes a `TelegramNotificationSender` for every chat if Telegram is configured.
+///
+/// Every chat gets a sender of its own, all with the Telegram sender name, so the outbox
+/// retries a notification only for the chats that didn't accept it.
+///
+/// # Parameters
+///
+/// - `app_config`: The application's configuration containing Telegram settings.
+///
+/// # Returns
+///
+/// The senders of all chats, empty if Telegram is not configured or invalid.
+fn initialize_telegram_notifiers(app_config: &AppConfig) -> Vec<NamedNotificationSender> {
+    let Some(telegram_config) = app_config.telegram_config.clone() else {
+        return Vec::new();
+    };
+    let name = sender_name(&app_config.telegram_config, |c| &c.name, "telegram");
+
+    telegram_config
+        .chat_ids
+        .iter()
+        .filter_map(|chat_id| {
+            match TelegramNotificationSender::new(TelegramConfiguration {
+                bot_token: telegram_config.bot_token.clone(),
+                chat_id: chat_id.clone(),
+                api_url: telegram_config.api_url.clone(),
+            }) {
+                Ok(sender) => Some(
+                    NamedNotificationSender::new(name.clone(), sender).with_destination(chat_id),
+                ),
+                Err(e) => {
+                    error!("Failed to initialize Telegram notifications: {:?}", e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Initializes a `NtfyNotificationSender` if ntfy is configured.
+///
+/// # Parameters
+///
+/// - `app_config`: The application's configuration containing ntfy settings.
+///
+/// # Returns
+///
+/// An `Option<NtfyNotificationSender>`. Returns `Some` if ntfy is configured and valid;
+/// otherwise, returns `None`.
+fn initialize_ntfy_notifier(app_config: &AppConfig) -> Option<NtfyNotificationSender> {
+    let ntfy_config = app_config.ntfy_config.clone()?;
+    let topic = ntfy_config.topic.clone();
+    match NtfyNotificationSender::new(NtfyConfiguration {
+        server_url: ntfy_config.server_url,
+        topic: ntfy_config.topic,
+        priority: ntfy_config.priority,
+        access_token: ntfy_config.access_token,
+    }) {
+        Ok(sender) => Some(sender),
+        Err(e) => {
+            error!(
+                "Failed to initialize ntfy notifications for topic {}: {:?}",
+                topic, e
+            );
+            None
+        }
+    }
+}
+
+/// Initializes a notification sender for every configured webhook of a chat system.
+///
+/// Webhooks with an invalid configuration are logged and left out, so the other
+/// notification senders keep working.
+///
+/// # Parameters
+///
+/// - `service`: Name of the chat system, used in logs.
+/// - `default_name`: Name routing rules refer to webhooks without a configured name by.
+/// - `webhook_configs`: The configured webhooks of the chat system.
+/// - `new_sender`: Creates the sender of the chat system for a webhook.
+///
+/// # Returns
+///
+/// The senders of all webhooks with a valid configuration.
+fn initialize_chat_notifiers<S, F>(
+    service: &str,
+    default_name: &str,
+    webhook_configs: &[ChatWebhookConfig],
+    new_sender: F,
+) -> Vec<NamedNotificationSender>
+where
+    S: NotificationSender + 'static,
+    F: Fn(ChatWebhookConfiguration) -> anyhow::Result<S>,
+{
+    webhook_configs
+        .iter()
+        .enumerate()
+        .filter_map(|(index, webhook_config)| {
+            match new_sender(ChatWebhookConfiguration {
+                webhook_url: webhook_config.webhook_url.clone(),
+                base_url: webhook_config.base_url.clone(),
+                username: webhook_config.username.clone(),
+            }) {
+                Ok(sender) => Some(
+                    NamedNotificationSender::new(
+                        webhook_config
+                            .name
+                            .clone()
+                            .unwrap_or_else(|| default_name.to_string()),
+                        sender,
+                    )
+                    .with_destination(webhook_config.webhook_url.clone()),
+                ),
+                Err(e) => {
+                    error!(
+                        "Failed to initialize {} webhook number {}: {:?}",
+                        service,
+                        index + 1,
+                        e
+                    );
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Initializes a `WebhookNotificationSender` for every configured outbound webhook.
+///
+/// Webhooks with an invalid configuration are logged and left out, so the other
+/// notification senders keep working.
+///
+/// # Parameters
+///
+/// - `webhook_configs`: The configured outbound webhooks.
+///
+/// # Returns
+///
+/// The senders of all webhooks with a valid configuration.
+fn initialize_webhook_notifiers(webhook_configs: &[WebhookConfig]) -> Vec<NamedNotificationSender> {
+    webhook_configs
+        .iter()
+        .filter_map(|webhook_config| {
+            match WebhookNotificationSender::new(WebhookConfiguration {
+                url: webhook_config.url.clone(),
+                headers: webhook_config.headers.clone(),
+                secret: webhook_config.secret.clone(),
+                signature_header: webhook_config.signature_header.clone(),
+                timeout: Duration::from_secs(webhook_config.timeout_seconds()),
+            }) {
+                Ok(sender) => Some(
+                    NamedNotificationSender::new(
+                        webhook_config
+                            .name
+                            .clone()
+                            .unwrap_or_else(|| "webhook".to_string()),
+                        sender,
+                    )
+                    .with_destination(webhook_config.url.clone()),
+                ),
+                Err(e) => {
+                    error!(
+                        "Failed to initialize webhook {}: {:?}",
+                        webhook_config.url, e
+                    );
+                    None
+                }
+            }
         })
-    })
+        .collect()
 }
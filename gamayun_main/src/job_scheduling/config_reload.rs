@@ -1,5 +1,10 @@
 use crate::init::AppContext;
+use crate::job_scheduling::retention::{
+    schedule_retention_cleanup, RETENTION_CLEANUP_JOB_CATEGORY,
+};
 use crate::job_scheduling::{schedule_jobs_from_config, SCHEDULED_GAMAYUN_JOB_CATEGORY};
+use crate::notification::template::NotificationTemplates;
+use std::collections::HashSet;
 use tracing::{info, instrument};
 
 #[instrument(skip(app_context))]
@@ -9,20 +14,62 @@ pub(crate) async fn handle_config_reload_request(app_context: AppContext) -> Res
         .scheduler
         .stop_jobs_by_category(SCHEDULED_GAMAYUN_JOB_CATEGORY)
         .map_err(|e| format!("Failed to stop jobs by category: {:?}", e))?;
-
-    info!("Removing all background job completion jobs");
     app_context
-        .background_job_completion_scheduler
-        .remove_all_jobs()
-        .await;
+        .scheduler
+        .stop_jobs_by_category(RETENTION_CLEANUP_JOB_CATEGORY)
+        .map_err(|e| format!("Failed to stop retention cleanup: {:?}", e))?;
 
     info!("Scheduling jobs from config");
-    schedule_jobs_from_config(
+    let job_configs = schedule_jobs_from_config(
         app_context.scheduler.clone(),
         app_context.background_job_completion_scheduler.clone(),
+        app_context.worker_registry.clone(),
+        app_context.server_url.clone(),
         app_context.config_root.clone(),
+        &app_context.mongo_db_name,
     )
     .map_err(|e| format!("Failed to schedule jobs from config: {:?}", e))?;
 
+    info!("Removing the runs of jobs that are no longer configured");
+    let job_names: HashSet<&str> = job_configs.iter().map(|c| c.name.as_str()).collect();
+    app_context
+        .background_job_completion_scheduler
+        .remove_jobs_not_in(&job_names)
+        .await;
+
+    app_context
+        .notification_sender
+        .set_job_configs(&job_configs)
+        .await;
+
+    info!("Reloading notification templates");
+    app_context
+        .notification_sender
+        .set_templates(NotificationTemplates::load(
+            &app_context.config_root,
+            app_context.app_config.notification_templates.as_ref(),
+        ));
+
+    info!("Scheduling retention cleanup");
+    schedule_retention_cleanup(
+        app_context.scheduler.clone(),
+        app_context.mongo_client.clone(),
+        app_context.mongo_db_name.clone(),
+        app_context.artifact_store.clone(),
+        &job_configs,
+    )
+    .map_err(|e| format!("Failed to schedule retention cleanup: {:?}", e))?;
+
+    *app_context
+        .job_configs
+        .write()
+        .map_err(|e| format!("Failed to update job configurations: {:?}", e))? = job_configs;
+
+    if let Some(tls) = &app_context.tls {
+        info!("Reloading TLS certificates");
+        tls.reload()
+            .map_err(|e| format!("Failed to reload TLS certificates: {:?}", e))?;
+    }
+
     Ok(())
 }
@@ -1,15 +1,21 @@
 pub mod config_reload;
+pub mod retention;
 pub mod scheduled_job_tracking_service;
+pub mod worker_registry;
 
 use crate::config::job_config::JobConfig;
 use crate::job_scheduling::scheduled_job_tracking_service::ScheduledJobTrackingService;
+use crate::job_scheduling::worker_registry::WorkerRegistry;
 use crate::notification::composite_notification_sender::CompositeNotificationSender;
 use anyhow::{Context, Result};
 use chrono::Utc;
+use grizzly_scheduler::job_id::JobId;
 use grizzly_scheduler::scheduler::Scheduler;
 use mongodb::bson::uuid;
+use protos::gamayun::{JobAssignment, RunInformation};
 use std::process::Command;
-use tracing::{error, info};
+use std::sync::{Arc, Mutex};
+use tracing::{error, info, warn};
 use tracing_futures::Instrument;
 
 pub const SCHEDULED_GAMAYUN_JOB_CATEGORY: &str = "SCHEDULED_GAMAYUN_JOB";
@@ -24,9 +30,12 @@ pub fn start_background_job_reporting_check(
 pub fn schedule_jobs_from_config(
     scheduler: Scheduler<Utc>,
     scheduled_job_tracking_service: ScheduledJobTrackingService,
+    worker_registry: WorkerRegistry,
+    server_url: String,
     config_root: String,
+    mongo_db_name: &str,
 ) -> Result<Vec<JobConfig>> {
-    let job_configs = JobConfig::load_configs_from_directory(&config_root)
+    let job_configs = JobConfig::load_configs_from_directory(&config_root, mongo_db_name)
         .context("Failed to load job configurations")?;
 
     for job_config in &job_configs {
@@ -34,6 +43,8 @@ pub fn schedule_jobs_from_config(
             scheduler.clone(),
             job_config.clone(),
             scheduled_job_tracking_service.clone(),
+            worker_registry.clone(),
+            server_url.clone(),
         );
     }
 
@@ -44,76 +55,205 @@ fn schedule_single_job(
     scheduler: Scheduler<Utc>,
     job_config: JobConfig,
     scheduled_job_tracking_service: ScheduledJobTrackingService,
+    worker_registry: WorkerRegistry,
+    server_url: String,
 ) {
     info!("Scheduling job: {}", job_config.name);
 
-    let path_to_executable = job_config.path_to_executable.clone();
-    let job_name = job_config.name.clone();
-    let arguments = job_config.arguments.clone();
-    let result_wait_timeout_millis = job_config.result_wait_timeout_millis.unwrap_or(10_000); // default to 10 seconds
+    let cron_string = job_config.cron_string.clone();
+    let name = job_config.name.clone();
+    let random_trigger_offset = job_config
+        .random_trigger_offset_seconds
+        .map(chrono::Duration::seconds);
 
     // Schedule the job to run based on the cron schedule
     scheduler
         .schedule_sequential_job(
-            &job_config.cron_string,
-            Some(job_config.name),
+            &cron_string,
+            Some(name),
             Some(SCHEDULED_GAMAYUN_JOB_CATEGORY.to_string()),
-            job_config
-                .random_trigger_offset_seconds
-                .map(|offset| chrono::Duration::seconds(offset)),
+            random_trigger_offset,
             move || {
-                let path_to_executable = path_to_executable.clone();
-                let job_name = job_name.clone();
-                let arguments = arguments.clone();
-                let scheduled_job_tracking_service = scheduled_job_tracking_service.clone();
                 run_single_job(
-                    path_to_executable,
-                    job_name,
-                    arguments,
-                    result_wait_timeout_millis,
-                    scheduled_job_tracking_service,
+                    job_config.clone(),
+                    1,
+                    scheduled_job_tracking_service.clone(),
+                    worker_registry.clone(),
+                    server_url.clone(),
                 )
             },
         )
         .expect("Failed to schedule job");
 }
 
+/// Runs the job again after a delay, as another attempt of a run that reported a retryable
+/// error. The retry is a one-off job of the scheduler, so it's cancelled along with the
+/// scheduled jobs when the configuration is reloaded.
+///
+/// # Arguments
+///
+/// * `scheduler` - The scheduler to run the retry on.
+/// * `job_config` - Configuration of the job to retry.
+/// * `attempt` - The attempt number of the retry.
+/// * `delay` - How long to wait before the retry.
+/// * `server_url` - URL of the gRPC server, passed to local jobs.
+pub fn schedule_retry(
+    scheduler: Scheduler<Utc>,
+    job_config: JobConfig,
+    attempt: u32,
+    delay: std::time::Duration,
+    scheduled_job_tracking_service: ScheduledJobTrackingService,
+    worker_registry: WorkerRegistry,
+    server_url: String,
+) -> Result<()> {
+    info!(
+        "Retrying job {} as attempt {} in {:?}",
+        job_config.name, attempt, delay
+    );
+
+    // Cron has a resolution of a second, round up so the retry doesn't fall in the past
+    let retry_at = Utc::now()
+        + chrono::Duration::from_std(delay).context("Retry delay is too long")?
+        + chrono::Duration::seconds(1);
+    let cron_string = retry_at.format("%S %M %H %d %m * %Y").to_string();
+
+    // The job has to remove itself from the scheduler once it ran, so it needs its own ID
+    let retry_job_id: Arc<Mutex<Option<JobId>>> = Arc::new(Mutex::new(None));
+    let retry_job_id_clone = retry_job_id.clone();
+    let scheduler_clone = scheduler.clone();
+    let job_id = scheduler
+        .schedule_sequential_job(
+            &cron_string,
+            Some(format!("{} (attempt {})", job_config.name, attempt)),
+            Some(SCHEDULED_GAMAYUN_JOB_CATEGORY.to_string()),
+            None,
+            move || {
+                let job_config = job_config.clone();
+                let scheduled_job_tracking_service = scheduled_job_tracking_service.clone();
+                let worker_registry = worker_registry.clone();
+                let server_url = server_url.clone();
+                let retry_job_id = retry_job_id_clone.clone();
+                let scheduler = scheduler_clone.clone();
+                async move {
+                    run_single_job(
+                        job_config,
+                        attempt,
+                        scheduled_job_tracking_service,
+                        worker_registry,
+                        server_url,
+                    )
+                    .await;
+                    let job_id = retry_job_id
+                        .lock()
+                        .ok()
+                        .and_then(|mut job_id| job_id.take());
+                    if let Some(job_id) = job_id {
+                        if let Err(e) = scheduler.cancel_job(job_id) {
+                            warn!("Failed to remove the retry from the scheduler: {:?}", e);
+                        }
+                    }
+                }
+            },
+        )
+        .context("Failed to schedule the retry")?;
+
+    if let Ok(mut retry_job_id) = retry_job_id.lock() {
+        *retry_job_id = Some(job_id);
+    }
+    Ok(())
+}
+
 async fn run_single_job(
-    path_to_executable: String,
-    job_name: String,
-    arguments: Vec<String>,
-    result_wait_timeout_millis: i64,
+    job_config: JobConfig,
+    attempt: u32,
     scheduled_job_tracking_service: ScheduledJobTrackingService,
+    worker_registry: WorkerRegistry,
+    server_url: String,
 ) {
+    let job_name = job_config.name;
+    let result_wait_timeout_millis = job_config.result_wait_timeout_millis.unwrap_or(10_000); // default to 10 seconds
     let unique_id = uuid::Uuid::new().to_string();
+    let token = uuid::Uuid::new().to_string();
     let span = tracing::info_span!(
         "run_single_job",
         job_name = %job_name,
-        unique_id = %unique_id
+        unique_id = %unique_id,
+        attempt = attempt
     );
 
     async move {
+        if let Some(run_on) = job_config.run_on {
+            info!("Dispatching job to a worker matching {:?}", run_on);
+
+            // Track the run first, so the job can report as soon as the worker starts it
+            scheduled_job_tracking_service
+                .add_job(
+                    job_name.clone(),
+                    unique_id.clone(),
+                    job_config.tags.clone(),
+                    attempt,
+                    token.clone(),
+                    chrono::Duration::milliseconds(result_wait_timeout_millis),
+                )
+                .await;
+
+            let assignment = JobAssignment {
+                run_information: Some(RunInformation {
+                    run_id: unique_id.clone(),
+                    job_name: job_name.clone(),
+                }),
+                path_to_executable: job_config.path_to_executable,
+                arguments: job_config.arguments,
+                token,
+                attempt,
+            };
+
+            match worker_registry.dispatch(&run_on, assignment).await {
+                Some(worker_name) => info!("Job {} assigned to worker {}", job_name, worker_name),
+                None => {
+                    error!(
+                        "No connected worker matches {:?} for job {}",
+                        run_on, job_name
+                    );
+                    scheduled_job_tracking_service.cancel_run(&unique_id).await;
+                    worker_registry
+                        .notify_unassigned_run(&job_name, &unique_id, job_config.tags, &run_on)
+                        .await;
+                }
+            }
+            return;
+        }
+
         info!("Executing job");
 
+        // Track the run first, so the job can report as soon as it starts
+        scheduled_job_tracking_service
+            .add_job(
+                job_name.clone(),
+                unique_id.clone(),
+                job_config.tags,
+                attempt,
+                token.clone(),
+                chrono::Duration::milliseconds(result_wait_timeout_millis),
+            )
+            .await;
+
         // Start the OS task
-        match Command::new(&path_to_executable)
+        match Command::new(&job_config.path_to_executable)
             .env("GAMAYUN_JOB_NAME", &job_name)
             .env("GAMAYUN_JOB_UNIQUE_ID", &unique_id)
-            .args(arguments)
+            .env("GAMAYUN_JOB_TOKEN", &token)
+            .env("GAMAYUN_JOB_ATTEMPT", attempt.to_string())
+            .env("GAMAYUN_SERVER_URL", &server_url)
+            .args(job_config.arguments)
             .spawn()
         {
             Ok(child) => {
                 info!("Job {} started with PID {}", &job_name, child.id());
-                scheduled_job_tracking_service
-                    .add_job(
-                        job_name.clone(),
-                        unique_id,
-                        chrono::Duration::milliseconds(result_wait_timeout_millis),
-                    )
-                    .await;
             }
             Err(e) => {
                 error!("Failed to start job {}: {:?}", job_name, e);
+                scheduled_job_tracking_service.cancel_run(&unique_id).await;
             }
         }
     }
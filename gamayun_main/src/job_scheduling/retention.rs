@@ -0,0 +1,410 @@
+use crate::artifacts::ArtifactStore;
+use crate::config::job_config::{JobConfig, OnDuplicateEntry, RetentionPolicy, StorageTarget};
+use crate::grpc::result_collecting_service::{CREATED_AT_FIELD, REJECTED_AT_FIELD};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use futures::TryStreamExt;
+use grizzly_scheduler::scheduler::Scheduler;
+use mongodb::bson::{doc, Bson, Document};
+use mongodb::error::{CommandError, ErrorKind};
+use mongodb::options::IndexOptions;
+use mongodb::{Client, Collection, IndexModel};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, instrument, warn};
+use tracing_futures::Instrument;
+
+pub const RETENTION_CLEANUP_JOB_CATEGORY: &str = "GAMAYUN_RETENTION_CLEANUP";
+
+const TTL_INDEX_NAME: &str = "gamayun_retention_ttl";
+
+/// Most documents deleted by a single `delete_many` command.
+const DELETE_BATCH_SIZE: usize = 1000;
+
+/// Schedules the periodic retention cleanup for all jobs that have a retention policy and
+/// triggers one cleanup right away, so TTL indexes are in place without waiting for the first
+/// scheduled run.
+///
+/// # Arguments
+///
+/// * `scheduler` - The scheduler the cleanup job is registered on.
+/// * `mongo_client` - MongoDB client used for the cleanup.
+/// * `mongo_db_name` - Name of the database the results are stored in.
+/// * `artifact_store` - Storage of the artifacts of runs, cleaned up by `max_age_days`.
+/// * `job_configs` - Configurations of all the jobs.
+pub fn schedule_retention_cleanup(
+    scheduler: Scheduler<Utc>,
+    mongo_client: Client,
+    mongo_db_name: String,
+    artifact_store: Arc<dyn ArtifactStore>,
+    job_configs: &[JobConfig],
+) -> Result<()> {
+    if job_configs
+        .iter()
+        .all(|job_config| job_config.retention.is_none())
+    {
+        info!("No job has a retention policy, not scheduling retention cleanup");
+        return Ok(());
+    }
+    // All jobs are kept, so collections shared with jobs without a retention policy are found
+    let job_configs = job_configs.to_vec();
+
+    tokio::spawn(
+        run_retention_cleanup(
+            mongo_client.clone(),
+            mongo_db_name.clone(),
+            artifact_store.clone(),
+            job_configs.clone(),
+        )
+        .instrument(tracing::info_span!("initial_retention_cleanup")),
+    );
+
+    scheduler
+        .schedule_sequential_job(
+            "0 30 * * * *", // run the cleanup every hour
+            Some("Retention Cleanup".to_string()),
+            Some(RETENTION_CLEANUP_JOB_CATEGORY.to_string()),
+            None,
+            move || {
+                run_retention_cleanup(
+                    mongo_client.clone(),
+                    mongo_db_name.clone(),
+                    artifact_store.clone(),
+                    job_configs.clone(),
+                )
+            },
+        )
+        .context("Failed to schedule retention cleanup")?;
+
+    Ok(())
+}
+
+async fn run_retention_cleanup(
+    mongo_client: Client,
+    mongo_db_name: String,
+    artifact_store: Arc<dyn ArtifactStore>,
+    job_configs: Vec<JobConfig>,
+) {
+    info!("Running retention cleanup");
+    let shared = jobs_sharing_collections(&mongo_db_name, &job_configs);
+    for job_config in job_configs
+        .iter()
+        .filter(|job_config| job_config.retention.is_some())
+    {
+        if shared.contains(&job_config.name) {
+            // Results don't record the job that stored them, so the cleanup would delete the
+            // results of the other jobs and replace their TTL index
+            error!(
+                "Not applying the retention policy of job {}, as it shares its collection with other jobs",
+                job_config.name
+            );
+        } else if let Err(e) =
+            apply_retention_policy(&mongo_client, &mongo_db_name, job_config).await
+        {
+            error!(
+                "Retention cleanup failed for job {}: {:?}",
+                job_config.name, e
+            );
+        }
+        if let Err(e) = delete_expired_artifacts(artifact_store.as_ref(), job_config).await {
+            error!(
+                "Artifact cleanup failed for job {}: {:?}",
+                job_config.name, e
+            );
+        }
+    }
+}
+
+/// Names of the jobs that currently store their results in the same collection as another job.
+fn jobs_sharing_collections(mongo_db_name: &str, job_configs: &[JobConfig]) -> HashSet<String> {
+    let now = Utc::now();
+    let mut jobs_by_target: HashMap<StorageTarget, Vec<&str>> = HashMap::new();
+    for job_config in job_configs {
+        if let Ok(target) = job_config.storage_target(mongo_db_name, now) {
+            jobs_by_target
+                .entry(target)
+                .or_default()
+                .push(&job_config.name);
+        }
+    }
+
+    jobs_by_target
+        .into_values()
+        .filter(|jobs| jobs.len() > 1)
+        .flatten()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Deletes the artifacts of the job that are older than `max_age_days` of its retention
+/// policy.
+async fn delete_expired_artifacts(
+    artifact_store: &dyn ArtifactStore,
+    job_config: &JobConfig,
+) -> Result<()> {
+    let Some(max_age_days) = job_config
+        .retention
+        .as_ref()
+        .and_then(|retention| retention.max_age_days)
+    else {
+        return Ok(());
+    };
+
+    let cutoff = Utc::now() - chrono::Duration::days(max_age_days as i64);
+    let deleted = artifact_store
+        .delete_uploaded_before(&job_config.name, cutoff)
+        .await?;
+    info!(
+        "Retention cleanup for job {}: deleted {} artifacts older than {} days",
+        job_config.name, deleted, max_age_days
+    );
+    Ok(())
+}
+
+#[instrument(skip(mongo_client, job_config), fields(job_name = %job_config.name))]
+async fn apply_retention_policy(
+    mongo_client: &Client,
+    mongo_db_name: &str,
+    job_config: &JobConfig,
+) -> Result<()> {
+    let Some(retention) = &job_config.retention else {
+        return Ok(());
+    };
+
+    // Date-sharded collections are cleaned up while they are the current shard, which also
+    // puts the TTL index in place before they stop receiving results
+    let storage_target = job_config.storage_target(mongo_db_name, Utc::now())?;
+    let database = mongo_client.database(&storage_target.database);
+    let collection: Collection<Document> = database.collection(&storage_target.collection);
+    let rejected_collection: Collection<Document> =
+        database.collection(&format!("{}_rejected", storage_target.collection));
+
+    ensure_ttl_index(&collection, CREATED_AT_FIELD, retention).await?;
+    ensure_ttl_index(&rejected_collection, REJECTED_AT_FIELD, retention).await?;
+
+    if let Some(max_documents) = retention.max_documents {
+        let deleted = delete_exceeding_documents(&collection, max_documents).await?;
+        info!(
+            "Retention cleanup for job {}: deleted {} documents over the limit of {}",
+            job_config.name, deleted, max_documents
+        );
+    }
+
+    if let Some(keep_latest_versions) = retention.keep_latest_versions {
+        match &job_config.duplicate_entry_policy {
+            Some(policy)
+                if matches!(policy.on_duplicate_entry, OnDuplicateEntry::TrackChanges)
+                    && !policy.unique_ids.is_empty() =>
+            {
+                let deleted =
+                    prune_old_versions(&collection, &policy.unique_ids, keep_latest_versions)
+                        .await?;
+                info!(
+                    "Retention cleanup for job {}: deleted {} old versions, keeping the latest {} per key",
+                    job_config.name, deleted, keep_latest_versions
+                );
+            }
+            _ => warn!(
+                "keep_latest_versions for job {} is ignored as the job doesn't use TrackChanges with unique ids",
+                job_config.name
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Creates, updates or drops the TTL index on `field` so it matches `max_age_days` of the
+/// retention policy.
+async fn ensure_ttl_index(
+    collection: &Collection<Document>,
+    field: &str,
+    retention: &RetentionPolicy,
+) -> Result<()> {
+    let Some(max_age_days) = retention.max_age_days else {
+        // The index might be left over from an earlier configuration
+        if let Err(e) = collection.drop_index(TTL_INDEX_NAME).await {
+            if !is_missing_namespace_or_index(&e) {
+                return Err(e).context("Failed to drop TTL index");
+            }
+        }
+        return Ok(());
+    };
+
+    let expire_after = Duration::from_secs(max_age_days * 24 * 60 * 60);
+    let index = IndexModel::builder()
+        .keys(doc! { field: 1 })
+        .options(
+            IndexOptions::builder()
+                .name(TTL_INDEX_NAME.to_string())
+                .expire_after(expire_after)
+                .build(),
+        )
+        .build();
+
+    if let Err(e) = collection.create_index(index).await {
+        // 85: IndexOptionsConflict, the index already exists with a different expiry
+        if !matches!(
+            e.kind.as_ref(),
+            ErrorKind::Command(CommandError { code: 85, .. })
+        ) {
+            return Err(e).context("Failed to create TTL index");
+        }
+        // Update the expiry of the existing index in place
+        collection
+            .client()
+            .database(&collection.namespace().db)
+            .run_command(doc! {
+                "collMod": collection.name(),
+                "index": {
+                    "name": TTL_INDEX_NAME,
+                    "expireAfterSeconds": expire_after.as_secs() as i64,
+                },
+            })
+            .await
+            .context("Failed to update TTL index")?;
+    }
+    Ok(())
+}
+
+fn is_missing_namespace_or_index(e: &mongodb::error::Error) -> bool {
+    // 26: NamespaceNotFound, 27: IndexNotFound
+    matches!(
+        e.kind.as_ref(),
+        ErrorKind::Command(CommandError { code: 26 | 27, .. })
+    )
+}
+
+/// Deletes the oldest documents of the collection so at most `max_documents` remain.
+async fn delete_exceeding_documents(
+    collection: &Collection<Document>,
+    max_documents: u64,
+) -> Result<u64> {
+    let count = collection.count_documents(doc! {}).await?;
+    if count <= max_documents {
+        return Ok(0);
+    }
+
+    let mut cursor = collection
+        .find(doc! {})
+        .sort(doc! { CREATED_AT_FIELD: 1 })
+        .limit((count - max_documents) as i64)
+        .projection(doc! { "_id": 1 })
+        .await?;
+
+    let mut deleted = 0;
+    let mut ids = Vec::with_capacity(DELETE_BATCH_SIZE);
+    while let Some(doc) = cursor.try_next().await? {
+        ids.extend(doc.get("_id").cloned());
+        if ids.len() == DELETE_BATCH_SIZE {
+            deleted += delete_by_ids(collection, &ids).await?;
+            ids.clear();
+        }
+    }
+    deleted += delete_by_ids(collection, &ids).await?;
+    Ok(deleted)
+}
+
+/// Deletes the documents with the given IDs, in batches of `DELETE_BATCH_SIZE` so a single
+/// command doesn't grow past the BSON size limit.
+async fn delete_by_ids(collection: &Collection<Document>, ids: &[Bson]) -> Result<u64> {
+    let mut deleted = 0;
+    for batch in ids.chunks(DELETE_BATCH_SIZE) {
+        deleted += collection
+            .delete_many(doc! { "_id": { "$in": batch } })
+            .await?
+            .deleted_count;
+    }
+    Ok(deleted)
+}
+
+/// Keeps only the latest `keep_latest_versions` versions for each unique key of a
+/// `TrackChanges` collection. As each version only holds the changed fields, the removed
+/// versions are merged into the oldest version that is kept.
+async fn prune_old_versions(
+    collection: &Collection<Document>,
+    unique_ids: &[String],
+    keep_latest_versions: u64,
+) -> Result<u64> {
+    let group_key = unique_ids
+        .iter()
+        .fold(Document::new(), |mut acc, unique_id| {
+            acc.insert(unique_id, format!("${}", unique_id));
+            acc
+        });
+
+    // Versions written without all unique id fields can't be told apart by key, grouping them
+    // would merge unrelated results into one
+    let has_unique_ids = unique_ids
+        .iter()
+        .fold(Document::new(), |mut acc, unique_id| {
+            acc.insert(unique_id, doc! { "$exists": true });
+            acc
+        });
+
+    let keys: Vec<Document> = collection
+        .aggregate(vec![
+            doc! { "$match": has_unique_ids.clone() },
+            doc! { "$group": { "_id": group_key, "count": { "$sum": 1 } } },
+            doc! { "$match": { "count": { "$gt": keep_latest_versions as i64 } } },
+        ])
+        .await?
+        .try_collect()
+        .await?;
+
+    let mut deleted = 0;
+    for key in keys {
+        let Ok(key) = key.get_document("_id") else {
+            continue;
+        };
+        if unique_ids
+            .iter()
+            .any(|unique_id| !key.contains_key(unique_id))
+        {
+            warn!("Skipping versions with incomplete unique key {}", key);
+            continue;
+        }
+        // A `null` key value would match versions without the field as well
+        let filter = doc! { "$and": [key.clone(), has_unique_ids.clone()] };
+
+        let versions: Vec<Document> = collection
+            .find(filter)
+            .sort(doc! { CREATED_AT_FIELD: 1 })
+            .await?
+            .try_collect()
+            .await?;
+
+        let removed_count = versions.len().saturating_sub(keep_latest_versions as usize);
+        if removed_count == 0 {
+            continue;
+        }
+
+        // Fold the removed versions and the oldest kept one into a single document
+        let oldest_kept = &versions[removed_count];
+        let mut merged = Document::new();
+        for version in &versions[..=removed_count] {
+            for (field, value) in version {
+                merged.insert(field.clone(), value.clone());
+            }
+        }
+        for field in ["_id", CREATED_AT_FIELD] {
+            if let Some(value) = oldest_kept.get(field) {
+                merged.insert(field, value.clone());
+            }
+        }
+
+        let oldest_kept_id = oldest_kept.get("_id").cloned().unwrap_or(Bson::Null);
+        collection
+            .replace_one(doc! { "_id": oldest_kept_id }, merged)
+            .await?;
+
+        let removed_ids: Vec<Bson> = versions[..removed_count]
+            .iter()
+            .filter_map(|version| version.get("_id").cloned())
+            .collect();
+        deleted += delete_by_ids(collection, &removed_ids).await?;
+    }
+
+    Ok(deleted)
+}
@@ -1,22 +1,193 @@
+use crate::config::job_config::ResultNotificationConfig;
+use crate::grpc::auth::tokens_match;
+use crate::grpc::result_collecting_service::item_notification;
 use crate::notification::composite_notification_sender::CompositeNotificationSender;
-use crate::notification::NotificationSender;
+use crate::notification::event::{NotificationEvent, NotificationKind, Severity};
 use chrono::{DateTime, Duration, Utc};
 use grizzly_scheduler::scheduler::Scheduler;
-use std::collections::HashMap;
-use std::sync::Arc;
+use protos::gamayun::ReportResultResponse;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
 use tokio::sync::Mutex;
 use tracing::{error, info};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+pub struct JobProgress {
+    pub status: String,
+    pub items_processed: Option<u64>,
+    pub items_total: Option<u64>,
+    pub reported_at: DateTime<Utc>,
+}
+
+/// What happened to the results a run reported, summed over its submissions.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ResultCounts {
+    pub submissions: u32,
+    pub inserted: u64,
+    pub updated: u64,
+    pub ignored: u64,
+    pub changed: u64,
+    pub rejected: u64,
+    /// Results that didn't match the result schema of the job, whether they were stored or not.
+    pub invalid: u64,
+}
+
+impl ResultCounts {
+    pub fn add(&mut self, submission: &SubmittedResults) {
+        let response = &submission.response;
+        self.submissions += 1;
+        self.inserted += response.inserted;
+        self.updated += response.updated;
+        self.ignored += response.ignored;
+        self.changed += response.changed;
+        self.rejected += response.rejected;
+        self.invalid += submission.invalid;
+    }
+
+    /// Number of results the run reported, stored or not.
+    pub fn reported(&self) -> u64 {
+        self.inserted + self.updated + self.ignored + self.changed + self.rejected
+    }
+}
+
+/// What happened to the results of a single submission of a run.
+#[derive(Debug, Clone, Default)]
+pub struct SubmittedResults {
+    pub response: ReportResultResponse,
+    /// Results that didn't match the result schema of the job, whether they were stored or not.
+    pub invalid: u64,
+    /// New and changed items the job notifies about.
+    pub items: ItemChanges,
+}
+
+impl SubmittedResults {
+    /// Adds the results of another part of the same submission, such as a streamed chunk.
+    pub fn merge(&mut self, other: SubmittedResults) {
+        self.response.inserted += other.response.inserted;
+        self.response.updated += other.response.updated;
+        self.response.ignored += other.response.ignored;
+        self.response.changed += other.response.changed;
+        self.response.rejected += other.response.rejected;
+        self.invalid += other.invalid;
+        self.items.merge(other.items);
+    }
+}
+
+impl From<ResultCounts> for ReportResultResponse {
+    fn from(counts: ResultCounts) -> Self {
+        ReportResultResponse {
+            inserted: counts.inserted,
+            updated: counts.updated,
+            ignored: counts.ignored,
+            changed: counts.changed,
+            rejected: counts.rejected,
+        }
+    }
+}
+
+/// New and changed results of a run a job notifies about, listed in the notification sent
+/// when the run completes.
+#[derive(Debug, Clone, Default)]
+pub struct ItemChanges {
+    pub on_new_item: bool,
+    pub on_change: bool,
+    pub max_items: usize,
+    /// The listed items, each with its `status`, `fields` and `previous` values.
+    pub items: Vec<serde_json::Value>,
+    pub new_count: u64,
+    pub changed_count: u64,
+}
+
+impl ItemChanges {
+    /// Creates `ItemChanges` collecting the items the job notifies about, none when the job
+    /// doesn't notify about its results.
+    pub fn for_job(config: Option<&ResultNotificationConfig>) -> Self {
+        match config {
+            Some(config) => ItemChanges {
+                on_new_item: config.on_new_item,
+                on_change: config.on_change,
+                max_items: config.max_items(),
+                ..Default::default()
+            },
+            None => ItemChanges::default(),
+        }
+    }
+
+    pub fn record_new(&mut self, fields: serde_json::Value) {
+        self.new_count += 1;
+        self.push(serde_json::json!({ "status": "new", "fields": fields }));
+    }
+
+    pub fn record_change(&mut self, fields: serde_json::Value, previous: serde_json::Value) {
+        self.changed_count += 1;
+        self.push(serde_json::json!({
+            "status": "changed",
+            "fields": fields,
+            "previous": previous,
+        }));
+    }
+
+    /// Adds the items of a later submission of the same run.
+    pub fn merge(&mut self, other: ItemChanges) {
+        self.on_new_item |= other.on_new_item;
+        self.on_change |= other.on_change;
+        self.max_items = self.max_items.max(other.max_items);
+        self.new_count += other.new_count;
+        self.changed_count += other.changed_count;
+        for item in other.items {
+            self.push(item);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.new_count == 0 && self.changed_count == 0
+    }
+
+    /// Number of items counted, but not listed.
+    pub fn omitted(&self) -> u64 {
+        self.new_count + self.changed_count - self.items.len() as u64
+    }
+
+    fn push(&mut self, item: serde_json::Value) {
+        if self.items.len() < self.max_items {
+            self.items.push(item);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct Job {
     pub name: String,
     pub run_id: String,
+    pub tags: Vec<String>,
+    /// 1 for the scheduled run, higher for retries.
+    pub attempt: u32,
+    pub started_at: DateTime<Utc>,
     pub valid_until: DateTime<Utc>,
+    #[serde(skip)]
+    pub result_wait_timeout: Duration,
+    pub progress: Option<JobProgress>,
+    /// Results submitted while the run is kept open.
+    pub results: ResultCounts,
+    /// New and changed items submitted while the run is kept open.
+    #[serde(skip)]
+    pub items: ItemChanges,
+}
+
+/// Secret token generated for a run, together with the job the run belongs to.
+#[derive(Debug, Clone)]
+struct RunToken {
+    job_name: String,
+    token: String,
 }
 
 #[derive(Clone)]
 pub struct ScheduledJobTrackingService {
     jobs: Arc<Mutex<HashMap<String, Job>>>,
+    /// Secret token of each run, by run ID. Kept behind a synchronous lock so it can be checked
+    /// from gRPC interceptors.
+    run_tokens: Arc<RwLock<HashMap<String, RunToken>>>,
     notification_sender: CompositeNotificationSender,
 }
 
@@ -27,12 +198,14 @@ impl ScheduledJobTrackingService {
     ) -> Self {
         let service = ScheduledJobTrackingService {
             jobs: Arc::new(Mutex::new(HashMap::new())),
+            run_tokens: Arc::new(RwLock::new(HashMap::new())),
             notification_sender,
         };
 
         let notification_sender = service.notification_sender.clone();
 
         let jobs_clone = service.jobs.clone();
+        let service_clone = service.clone();
         scheduler
             .schedule_sequential_job(
                 "1 */10 * * * *", // run the job every 10 minutes
@@ -41,32 +214,52 @@ impl ScheduledJobTrackingService {
                 Some(Duration::seconds(2)),
                 move || {
                     let jobs = jobs_clone.clone();
+                    let service = service_clone.clone();
                     let notification_sender = notification_sender.clone();
                     async move {
                         info!("Checking for overdue jobs.");
                         let mut jobs = jobs.lock().await; // Use `await` with the async mutex
                         let now = Utc::now();
-                        let overdue_jobs: Vec<(String, String)> = jobs
-                            .iter()
-                            .filter(|(_, job)| job.valid_until < now)
-                            .map(|(run_id, job)| (run_id.clone(), job.name.clone()))
+                        let overdue_jobs: Vec<Job> = jobs
+                            .values()
+                            .filter(|job| job.valid_until < now)
+                            .cloned()
                             .collect();
 
-                        for (run_id, job_name) in overdue_jobs {
+                        for mut job in overdue_jobs {
+                            // A run kept open for more results can expire with items it found
+                            let items = item_notification(
+                                &job.name,
+                                &job.run_id,
+                                job.tags.clone(),
+                                std::mem::take(&mut job.items),
+                            );
+                            if let Some(event) = items {
+                                notification_sender
+                                    .notify(event.with_run_timing(job.attempt, job.started_at))
+                                    .await;
+                            }
                             notification_sender
                                 .notify(
-                                    format!("Gamayun Overdue Job for {}", job_name),
-                                    format!(
-                                        "Job with name {} and  run ID {} is overdue.",
-                                        job_name, run_id
-                                    ),
+                                    NotificationEvent::new(
+                                        NotificationKind::Overdue,
+                                        Severity::Error,
+                                        format!("Gamayun Overdue Job for {}", job.name),
+                                        format!(
+                                            "Job with name {} and run ID {} is overdue.",
+                                            job.name, job.run_id
+                                        ),
+                                    )
+                                    .for_run(&job.name, &job.run_id, job.tags)
+                                    .with_run_timing(job.attempt, job.started_at),
                                 )
                                 .await;
                             error!(
-                                "Error: Job with name {} and  run ID {} is overdue.",
-                                job_name, run_id
+                                "Error: Job with name {} and run ID {} is overdue.",
+                                job.name, job.run_id
                             );
-                            jobs.remove(&run_id);
+                            jobs.remove(&job.run_id);
+                            service.remove_run_token(&job.run_id);
                         }
                     }
                 },
@@ -76,20 +269,151 @@ impl ScheduledJobTrackingService {
         service
     }
 
-    pub async fn add_job(&self, name: String, run_id: String, duration: Duration) {
-        let valid_until = Utc::now() + duration;
+    pub async fn add_job(
+        &self,
+        name: String,
+        run_id: String,
+        tags: Vec<String>,
+        attempt: u32,
+        token: String,
+        duration: Duration,
+    ) {
+        match self.run_tokens.write() {
+            Ok(mut run_tokens) => {
+                run_tokens.insert(
+                    run_id.clone(),
+                    RunToken {
+                        job_name: name.clone(),
+                        token,
+                    },
+                );
+            }
+            Err(e) => error!("Failed to store the token for run ID {}: {}", run_id, e),
+        }
+
+        let started_at = Utc::now();
         let job = Job {
             name,
             run_id: run_id.clone(),
-            valid_until,
+            tags,
+            attempt,
+            started_at,
+            valid_until: started_at + duration,
+            result_wait_timeout: duration,
+            progress: None,
+            results: ResultCounts::default(),
+            items: ItemChanges::default(),
         };
         let mut jobs = self.jobs.lock().await;
         jobs.insert(run_id, job);
     }
 
-    pub async fn report_result_returned(&self, run_id: &String) {
+    /// Stores the latest progress of a run and extends its deadline by the job's result wait
+    /// timeout, counted from now.
+    ///
+    /// # Returns
+    ///
+    /// `bool` - Whether a run with the given ID is being tracked.
+    pub async fn report_progress(&self, run_id: &String, progress: JobProgress) -> bool {
+        let mut jobs = self.jobs.lock().await;
+        match jobs.get_mut(run_id) {
+            Some(job) => {
+                job.valid_until = progress.reported_at + job.result_wait_timeout;
+                job.progress = Some(progress);
+                info!(
+                    "Extended the deadline of job {} with run ID {} to {}.",
+                    job.name, run_id, job.valid_until
+                );
+                true
+            }
+            None => {
+                error!("Error: Job with run ID {} not found.", run_id);
+                false
+            }
+        }
+    }
+
+    /// Adds results submitted by a run that is kept open to its totals and extends its deadline
+    /// by the job's result wait timeout, counted from now.
+    ///
+    /// # Arguments
+    ///
+    /// * `run_id` - ID of the run.
+    /// * `submission` - What happened to the submitted results.
+    ///
+    /// # Returns
+    ///
+    /// `bool` - Whether a run with the given ID is being tracked.
+    pub async fn report_results_submitted(
+        &self,
+        run_id: &String,
+        submission: SubmittedResults,
+    ) -> bool {
         let mut jobs = self.jobs.lock().await;
-        if jobs.remove(run_id).is_none() {
+        match jobs.get_mut(run_id) {
+            Some(job) => {
+                job.results.add(&submission);
+                job.items.merge(submission.items);
+                job.valid_until = Utc::now() + job.result_wait_timeout;
+                info!(
+                    "Run ID {} of job {} submitted {} results, waiting for completion until {}.",
+                    run_id, job.name, job.results.submissions, job.valid_until
+                );
+                true
+            }
+            None => {
+                error!("Error: Job with run ID {} not found.", run_id);
+                false
+            }
+        }
+    }
+
+    /// Checks whether `token` is the token generated for the run with the given ID.
+    ///
+    /// # Returns
+    ///
+    /// `Option<String>` - The name of the job the run belongs to if the token matches.
+    pub fn verify_run_token(&self, run_id: &str, token: &str) -> Option<String> {
+        match self.run_tokens.read() {
+            Ok(run_tokens) => run_tokens
+                .get(run_id)
+                .filter(|run_token| tokens_match(&run_token.token, token))
+                .map(|run_token| run_token.job_name.clone()),
+            Err(e) => {
+                error!("Failed to read run tokens: {}", e);
+                None
+            }
+        }
+    }
+
+    fn remove_run_token(&self, run_id: &str) {
+        match self.run_tokens.write() {
+            Ok(mut run_tokens) => {
+                run_tokens.remove(run_id);
+            }
+            Err(e) => error!("Failed to remove the token for run ID {}: {}", run_id, e),
+        }
+    }
+
+    /// Returns all tracked runs of the job with the given name.
+    pub async fn runs_for_job(&self, name: &str) -> Vec<Job> {
+        let jobs = self.jobs.lock().await;
+        jobs.values()
+            .filter(|job| job.name == name)
+            .cloned()
+            .collect()
+    }
+
+    /// Stops tracking a run that reported its outcome.
+    ///
+    /// # Returns
+    ///
+    /// `Option<Job>` - The run, if it was being tracked.
+    pub async fn report_result_returned(&self, run_id: &String) -> Option<Job> {
+        self.remove_run_token(run_id);
+        let mut jobs = self.jobs.lock().await;
+        let job = jobs.remove(run_id);
+        if job.is_none() {
             error!("Error: Job with run ID {} not found.", run_id);
         } else {
             info!(
@@ -97,11 +421,34 @@ impl ScheduledJobTrackingService {
                 run_id
             );
         }
+        job
+    }
+
+    /// Stops tracking a run that won't report, because it was never started.
+    pub async fn cancel_run(&self, run_id: &str) {
+        self.remove_run_token(run_id);
+        let mut jobs = self.jobs.lock().await;
+        if jobs.remove(run_id).is_some() {
+            info!("Stopped tracking run ID {}.", run_id);
+        }
     }
 
-    pub async fn remove_all_jobs(&self) {
+    /// Stops tracking the runs of jobs that are no longer configured and revokes their run
+    /// tokens. Runs of jobs that are still configured keep their tokens and stay tracked, so
+    /// they can report their results after the configuration is reloaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `job_names` - The names of the jobs in the new configuration.
+    pub async fn remove_jobs_not_in(&self, job_names: &HashSet<&str>) {
         let mut jobs = self.jobs.lock().await;
-        jobs.clear();
-        info!("All jobs have been removed.");
+        jobs.retain(|_, job| job_names.contains(job.name.as_str()));
+        match self.run_tokens.write() {
+            Ok(mut run_tokens) => {
+                run_tokens.retain(|_, run_token| job_names.contains(run_token.job_name.as_str()))
+            }
+            Err(e) => error!("Failed to remove run tokens: {}", e),
+        }
+        info!("Runs of removed jobs have been removed.");
     }
 }
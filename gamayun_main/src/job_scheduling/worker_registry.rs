@@ -0,0 +1,328 @@
+use crate::notification::composite_notification_sender::CompositeNotificationSender;
+use crate::notification::event::{NotificationEvent, NotificationKind, Severity};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use grizzly_scheduler::scheduler::Scheduler;
+use mongodb::bson::uuid;
+use protos::gamayun::JobAssignment;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+use tonic::Status;
+use tracing::{error, info, warn};
+
+pub const WORKER_LIVENESS_JOB_CATEGORY: &str = "GAMAYUN_WORKER_LIVENESS";
+
+/// How often workers are asked to send a heartbeat.
+pub const WORKER_HEARTBEAT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// Workers that haven't sent a heartbeat for this long are considered lost.
+const WORKER_TIMEOUT_SECONDS: i64 = 60;
+
+const ASSIGNMENT_BUFFER_SIZE: usize = 16;
+
+pub type AssignmentSender = mpsc::Sender<Result<JobAssignment, Status>>;
+pub type AssignmentReceiver = mpsc::Receiver<Result<JobAssignment, Status>>;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkerInfo {
+    pub worker_id: String,
+    pub name: String,
+    pub labels: HashMap<String, String>,
+    pub registered_at: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// IDs of the runs assigned to the worker that haven't exited yet.
+    pub active_runs: Vec<String>,
+}
+
+/// Outcome of a worker reporting that a run exited.
+pub enum RunExit {
+    /// The run was assigned to the worker, with the name of the worker.
+    Completed(String),
+    /// No worker with the given ID is registered.
+    UnknownWorker,
+    /// The run wasn't assigned to the worker, with the name of the worker.
+    NotAssigned(String),
+}
+
+struct Worker {
+    info: WorkerInfo,
+    /// Sender of the assignment stream the worker is polling, if it's connected.
+    assignments: Option<AssignmentSender>,
+}
+
+impl Worker {
+    fn is_connected(&self) -> bool {
+        self.assignments
+            .as_ref()
+            .is_some_and(|sender| !sender.is_closed())
+    }
+
+    fn matches(&self, selector: &HashMap<String, String>) -> bool {
+        selector
+            .iter()
+            .all(|(label, value)| self.info.labels.get(label) == Some(value))
+    }
+}
+
+/// Keeps track of the registered remote workers, hands runs out to them and notices when they
+/// are lost.
+#[derive(Clone)]
+pub struct WorkerRegistry {
+    workers: Arc<Mutex<HashMap<String, Worker>>>,
+    notification_sender: CompositeNotificationSender,
+}
+
+impl WorkerRegistry {
+    pub fn new(
+        scheduler: Scheduler<Utc>,
+        notification_sender: CompositeNotificationSender,
+    ) -> Result<Self> {
+        let registry = WorkerRegistry {
+            workers: Arc::new(Mutex::new(HashMap::new())),
+            notification_sender,
+        };
+
+        let registry_clone = registry.clone();
+        scheduler
+            .schedule_sequential_job(
+                "*/30 * * * * *", // check the workers every 30 seconds
+                Some("Worker Liveness Checker".to_string()),
+                Some(WORKER_LIVENESS_JOB_CATEGORY.to_string()),
+                None,
+                move || {
+                    let registry = registry_clone.clone();
+                    async move { registry.remove_lost_workers().await }
+                },
+            )
+            .context("Failed to schedule the worker liveness check")?;
+
+        Ok(registry)
+    }
+
+    /// Registers a worker, replacing an earlier registration with the same name. The active runs
+    /// of the earlier registration move to the new one, so the worker can still report their
+    /// exit after reconnecting.
+    ///
+    /// # Returns
+    ///
+    /// `String` - The ID of the new registration.
+    pub async fn register(&self, name: String, labels: HashMap<String, String>) -> String {
+        let mut workers = self.workers.lock().await;
+
+        let replaced: Vec<String> = workers
+            .iter()
+            .filter(|(_, worker)| worker.info.name == name)
+            .map(|(worker_id, _)| worker_id.clone())
+            .collect();
+        let mut active_runs = Vec::new();
+        for worker_id in replaced {
+            if let Some(worker) = workers.remove(&worker_id) {
+                active_runs.extend(worker.info.active_runs);
+            }
+        }
+        if !active_runs.is_empty() {
+            info!(
+                "Worker {} registered again, runs {} of the previous registration are kept",
+                name,
+                active_runs.join(", ")
+            );
+        }
+
+        let worker_id = uuid::Uuid::new().to_string();
+        let now = Utc::now();
+        info!(
+            "Worker {} registered with ID {} and labels {:?}",
+            name, worker_id, labels
+        );
+        workers.insert(
+            worker_id.clone(),
+            Worker {
+                info: WorkerInfo {
+                    worker_id: worker_id.clone(),
+                    name,
+                    labels,
+                    registered_at: now,
+                    last_seen: now,
+                    active_runs,
+                },
+                assignments: None,
+            },
+        );
+
+        worker_id
+    }
+
+    /// Records that the worker is still alive.
+    ///
+    /// # Returns
+    ///
+    /// `bool` - Whether a worker with the given ID is registered.
+    pub async fn heartbeat(&self, worker_id: &str) -> bool {
+        let mut workers = self.workers.lock().await;
+        match workers.get_mut(worker_id) {
+            Some(worker) => {
+                worker.info.last_seen = Utc::now();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Opens the stream runs are assigned to the worker through, replacing an earlier one.
+    ///
+    /// # Returns
+    ///
+    /// `Option<AssignmentReceiver>` - The receiving end of the stream, or `None` if no worker
+    /// with the given ID is registered.
+    pub async fn open_assignment_stream(&self, worker_id: &str) -> Option<AssignmentReceiver> {
+        let mut workers = self.workers.lock().await;
+        let worker = workers.get_mut(worker_id)?;
+
+        let (sender, receiver) = mpsc::channel(ASSIGNMENT_BUFFER_SIZE);
+        worker.assignments = Some(sender);
+        worker.info.last_seen = Utc::now();
+        info!("Worker {} is polling for jobs", worker.info.name);
+
+        Some(receiver)
+    }
+
+    /// Assigns a run to a connected worker whose labels match the selector, preferring the
+    /// worker with the fewest active runs.
+    ///
+    /// # Returns
+    ///
+    /// `Option<String>` - The name of the worker the run was assigned to, or `None` if no
+    /// matching worker could take it.
+    pub async fn dispatch(
+        &self,
+        selector: &HashMap<String, String>,
+        assignment: JobAssignment,
+    ) -> Option<String> {
+        let run_id = assignment
+            .run_information
+            .as_ref()
+            .map(|run_information| run_information.run_id.clone())
+            .unwrap_or_default();
+
+        let mut workers = self.workers.lock().await;
+        let mut candidates: Vec<&mut Worker> = workers
+            .values_mut()
+            .filter(|worker| worker.is_connected() && worker.matches(selector))
+            .collect();
+        candidates.sort_by_key(|worker| worker.info.active_runs.len());
+
+        for worker in candidates {
+            let Some(sender) = &worker.assignments else {
+                continue;
+            };
+            match sender.try_send(Ok(assignment.clone())) {
+                Ok(()) => {
+                    worker.info.active_runs.push(run_id);
+                    return Some(worker.info.name.clone());
+                }
+                Err(e) => warn!(
+                    "Failed to assign run {} to worker {}: {}",
+                    run_id, worker.info.name, e
+                ),
+            }
+        }
+
+        None
+    }
+
+    /// Records that an assigned run exited on the worker.
+    ///
+    /// # Returns
+    ///
+    /// `RunExit` - Whether the run was assigned to the worker.
+    pub async fn complete_run(&self, worker_id: &str, run_id: &str) -> RunExit {
+        let mut workers = self.workers.lock().await;
+        let Some(worker) = workers.get_mut(worker_id) else {
+            return RunExit::UnknownWorker;
+        };
+        worker.info.last_seen = Utc::now();
+
+        let active_runs = worker.info.active_runs.len();
+        worker
+            .info
+            .active_runs
+            .retain(|active_run| active_run != run_id);
+        if worker.info.active_runs.len() == active_runs {
+            return RunExit::NotAssigned(worker.info.name.clone());
+        }
+        RunExit::Completed(worker.info.name.clone())
+    }
+
+    /// Notifies that a run couldn't be assigned as no connected worker matches its selector.
+    pub async fn notify_unassigned_run(
+        &self,
+        job_name: &str,
+        run_id: &str,
+        tags: Vec<String>,
+        selector: &HashMap<String, String>,
+    ) {
+        self.notification_sender
+            .notify(
+                NotificationEvent::new(
+                    NotificationKind::NoWorker,
+                    Severity::Error,
+                    format!("Gamayun No Worker for job {}", job_name),
+                    format!(
+                        "Run {} of job {} was not started as no connected worker matches {:?}.",
+                        run_id, job_name, selector
+                    ),
+                )
+                .for_run(job_name, run_id, tags),
+            )
+            .await;
+    }
+
+    /// Returns all registered workers.
+    pub async fn workers(&self) -> Vec<WorkerInfo> {
+        let workers = self.workers.lock().await;
+        workers.values().map(|worker| worker.info.clone()).collect()
+    }
+
+    async fn remove_lost_workers(&self) {
+        let deadline = Utc::now() - Duration::seconds(WORKER_TIMEOUT_SECONDS);
+
+        let lost_workers: Vec<WorkerInfo> = {
+            let mut workers = self.workers.lock().await;
+            let lost_ids: Vec<String> = workers
+                .iter()
+                .filter(|(_, worker)| worker.info.last_seen < deadline)
+                .map(|(worker_id, _)| worker_id.clone())
+                .collect();
+            lost_ids
+                .iter()
+                .filter_map(|worker_id| workers.remove(worker_id))
+                .map(|worker| worker.info)
+                .collect()
+        };
+
+        for worker in lost_workers {
+            error!(
+                "Worker {} with ID {} was last seen at {} and is considered lost.",
+                worker.name, worker.worker_id, worker.last_seen
+            );
+            let active_runs = if worker.active_runs.is_empty() {
+                "It had no active runs.".to_string()
+            } else {
+                format!("Its active runs were: {}", worker.active_runs.join(", "))
+            };
+            self.notification_sender
+                .notify(NotificationEvent::new(
+                    NotificationKind::Crash,
+                    Severity::Error,
+                    format!("Gamayun Worker Lost: {}", worker.name),
+                    format!(
+                        "Worker {} was last seen at {} and is considered lost. {}",
+                        worker.name, worker.last_seen, active_runs
+                    ),
+                ))
+                .await;
+        }
+    }
+}
@@ -6,12 +6,14 @@ use tokio::signal;
 use tokio_util::sync::CancellationToken;
 use tracing::info;
 
+mod artifacts;
 mod config;
 mod grpc;
 mod http;
 mod init;
 mod job_scheduling;
 mod notification;
+mod tls;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -20,6 +22,16 @@ async fn main() -> Result<()> {
     info!("Starting the web server and gRPC server...");
 
     let (shutdown_token, shutdown_future) = create_cancellation_token();
+
+    #[cfg(unix)]
+    if let Some(tls) = app_context.tls.clone() {
+        tokio::spawn(tls::reload_on_sighup(
+            tls,
+            app_context.notification_sender.clone(),
+            shutdown_token.clone(),
+        ));
+    }
+
     let http_server_future = run_actix_server(app_context.clone(), shutdown_token.clone());
     let grpc_server_future = run_grpc_server(app_context.clone(), shutdown_token.clone());
 
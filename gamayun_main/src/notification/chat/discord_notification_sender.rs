@@ -0,0 +1,65 @@
+use crate::notification::chat::{
+    event_facts, severity_color, truncate, ChatWebhook, ChatWebhookConfiguration,
+};
+use crate::notification::event::NotificationEvent;
+use crate::notification::{DeliveryError, NotificationSender};
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tracing::{info, instrument};
+
+const MAX_TITLE_LENGTH: usize = 256;
+const MAX_DESCRIPTION_LENGTH: usize = 4096;
+
+/// Implementation of `NotificationSender` posting embeds to a Discord webhook.
+pub struct DiscordNotificationSender {
+    webhook: ChatWebhook,
+    username: Option<String>,
+}
+
+impl DiscordNotificationSender {
+    /// Creates a new `DiscordNotificationSender` with a configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - A `ChatWebhookConfiguration` with the webhook URL.
+    pub fn new(config: ChatWebhookConfiguration) -> Result<Self> {
+        Ok(DiscordNotificationSender {
+            webhook: ChatWebhook::new("Discord", &config)?,
+            username: config.username,
+        })
+    }
+
+    fn build_payload(&self, event: &NotificationEvent) -> Value {
+        let fields: Vec<Value> = event_facts(event)
+            .into_iter()
+            .map(|(label, value)| json!({ "name": label, "value": value, "inline": true }))
+            .collect();
+
+        let mut payload = json!({
+            "embeds": [{
+                "title": truncate(&event.title, MAX_TITLE_LENGTH),
+                "description": truncate(&event.details, MAX_DESCRIPTION_LENGTH),
+                "color": severity_color(event.severity),
+                "fields": fields,
+                "timestamp": event.timestamp.to_rfc3339(),
+            }],
+        });
+        if let Some(username) = &self.username {
+            payload["username"] = json!(username);
+        }
+        payload
+    }
+}
+
+#[async_trait]
+impl NotificationSender for DiscordNotificationSender {
+    #[instrument(skip(self, event), fields(kind = ?event.kind, job_name = ?event.job_name))]
+    async fn notify(&self, event: NotificationEvent) -> Result<(), DeliveryError> {
+        info!(
+            "Sending notification via Discord with title {}",
+            &event.title
+        );
+        self.webhook.post(self.build_payload(&event)).await
+    }
+}
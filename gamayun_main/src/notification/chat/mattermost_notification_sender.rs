@@ -0,0 +1,63 @@
+use crate::notification::chat::{
+    event_facts, severity_color, ChatWebhook, ChatWebhookConfiguration,
+};
+use crate::notification::event::NotificationEvent;
+use crate::notification::{DeliveryError, NotificationSender};
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tracing::{info, instrument};
+
+/// Implementation of `NotificationSender` posting message attachments to a Mattermost
+/// incoming webhook.
+pub struct MattermostNotificationSender {
+    webhook: ChatWebhook,
+    username: Option<String>,
+}
+
+impl MattermostNotificationSender {
+    /// Creates a new `MattermostNotificationSender` with a configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - A `ChatWebhookConfiguration` with the incoming webhook URL.
+    pub fn new(config: ChatWebhookConfiguration) -> Result<Self> {
+        Ok(MattermostNotificationSender {
+            webhook: ChatWebhook::new("Mattermost", &config)?,
+            username: config.username,
+        })
+    }
+
+    fn build_payload(&self, event: &NotificationEvent) -> Value {
+        let fields: Vec<Value> = event_facts(event)
+            .into_iter()
+            .map(|(label, value)| json!({ "short": true, "title": label, "value": value }))
+            .collect();
+
+        let mut payload = json!({
+            "attachments": [{
+                "fallback": event.title,
+                "color": format!("#{:06X}", severity_color(event.severity)),
+                "title": event.title,
+                "text": event.details,
+                "fields": fields,
+            }],
+        });
+        if let Some(username) = &self.username {
+            payload["username"] = json!(username);
+        }
+        payload
+    }
+}
+
+#[async_trait]
+impl NotificationSender for MattermostNotificationSender {
+    #[instrument(skip(self, event), fields(kind = ?event.kind, job_name = ?event.job_name))]
+    async fn notify(&self, event: NotificationEvent) -> Result<(), DeliveryError> {
+        info!(
+            "Sending notification via Mattermost with title {}",
+            &event.title
+        );
+        self.webhook.post(self.build_payload(&event)).await
+    }
+}
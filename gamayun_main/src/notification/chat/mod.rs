@@ -0,0 +1,134 @@
+pub(crate) mod discord_notification_sender;
+pub(crate) mod mattermost_notification_sender;
+pub(crate) mod slack_notification_sender;
+pub(crate) mod teams_notification_sender;
+
+use crate::notification::event::{NotificationEvent, Severity};
+use crate::notification::{http_client_builder, DeliveryError};
+use anyhow::{Context, Result};
+use reqwest::Client;
+use url::Url;
+
+/// Configuration struct for an incoming webhook of a chat system.
+pub struct ChatWebhookConfiguration {
+    pub webhook_url: String,
+    /// Replaces the scheme, host and port of `webhook_url` when set.
+    pub base_url: Option<String>,
+    /// Name the messages are posted as, if the chat system supports it.
+    pub username: Option<String>,
+}
+
+/// Incoming webhook of a chat system, shared by the chat notification senders.
+pub struct ChatWebhook {
+    url: Url,
+    client: Client,
+}
+
+impl ChatWebhook {
+    /// Creates a new `ChatWebhook`.
+    ///
+    /// # Arguments
+    ///
+    /// * `service` - Name of the chat system, used in errors.
+    /// * `config` - Configuration with the webhook URL and its base URL override.
+    ///
+    /// # Returns
+    ///
+    /// The webhook, or an error if one of the URLs can't be parsed.
+    pub fn new(service: &'static str, config: &ChatWebhookConfiguration) -> Result<Self> {
+        let url = Url::parse(&config.webhook_url)
+            .with_context(|| format!("Invalid {} webhook URL", service))?;
+        let url = match &config.base_url {
+            Some(base_url) => override_base_url(&url, base_url)
+                .with_context(|| format!("Invalid {} base URL {}", service, base_url))?,
+            None => url,
+        };
+        let client = http_client_builder()
+            .build()
+            .with_context(|| format!("Failed to create the {} HTTP client", service))?;
+        Ok(ChatWebhook { url, client })
+    }
+
+    /// Posts a message to the webhook.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload` - The JSON message in the layout of the chat system.
+    pub async fn post(&self, payload: serde_json::Value) -> Result<(), DeliveryError> {
+        post_json(&self.client, self.url.clone(), &payload).await
+    }
+}
+
+/// Posts a JSON message to a chat or push service.
+///
+/// # Arguments
+///
+/// * `client` - HTTP client used for the request.
+/// * `url` - URL the message is posted to.
+/// * `payload` - The JSON message in the layout of the service.
+pub(crate) async fn post_json(
+    client: &Client,
+    url: Url,
+    payload: &serde_json::Value,
+) -> Result<(), DeliveryError> {
+    match client.post(url).json(payload).send().await {
+        Ok(response) if !response.status().is_success() => {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            Err(DeliveryError::from_status(status, body))
+        }
+        Ok(_) => Ok(()),
+        // Webhook URLs and bot tokens are secrets, keep them out of the logs
+        Err(e) => Err(DeliveryError::Transient(format!("{:?}", e.without_url()))),
+    }
+}
+
+/// Moves a webhook URL to another server, keeping its path and query. A path of the base
+/// URL is put in front of the path of the webhook.
+fn override_base_url(url: &Url, base_url: &str) -> Result<Url> {
+    let mut resolved = Url::parse(base_url)?;
+    let path = format!("{}{}", resolved.path().trim_end_matches('/'), url.path());
+    resolved.set_path(&path);
+    resolved.set_query(url.query());
+    Ok(resolved)
+}
+
+/// Shortens a text to the given number of characters, as chat systems reject messages with
+/// fields over their limits.
+pub(crate) fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Color of the message bar of an event, by its severity.
+fn severity_color(severity: Severity) -> u32 {
+    match severity {
+        Severity::Info => 0x2EB67D,
+        Severity::Warning => 0xECB22E,
+        Severity::Error => 0xD24B4E,
+        Severity::Critical => 0x8B1A1A,
+    }
+}
+
+/// Labels and values shown next to the details of an event, such as its severity and the
+/// job and run it's about.
+fn event_facts(event: &NotificationEvent) -> Vec<(&'static str, String)> {
+    let mut facts = vec![
+        ("Severity", event.severity.label().to_string()),
+        ("Kind", event.kind.label().to_string()),
+    ];
+    if let Some(job_name) = &event.job_name {
+        facts.push(("Job", job_name.clone()));
+    }
+    if let Some(run_id) = &event.run_id {
+        facts.push(("Run", run_id.clone()));
+    }
+    if !event.tags.is_empty() {
+        facts.push(("Tags", event.tags.join(", ")));
+    }
+    facts
+}
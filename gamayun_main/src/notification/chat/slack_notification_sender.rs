@@ -0,0 +1,64 @@
+use crate::notification::chat::{event_facts, truncate, ChatWebhook, ChatWebhookConfiguration};
+use crate::notification::event::NotificationEvent;
+use crate::notification::{DeliveryError, NotificationSender};
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tracing::{info, instrument};
+
+const MAX_HEADER_LENGTH: usize = 150;
+const MAX_SECTION_LENGTH: usize = 3000;
+
+/// Implementation of `NotificationSender` posting Block Kit messages to a Slack incoming
+/// webhook.
+pub struct SlackNotificationSender {
+    webhook: ChatWebhook,
+    username: Option<String>,
+}
+
+impl SlackNotificationSender {
+    /// Creates a new `SlackNotificationSender` with a configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - A `ChatWebhookConfiguration` with the incoming webhook URL.
+    pub fn new(config: ChatWebhookConfiguration) -> Result<Self> {
+        Ok(SlackNotificationSender {
+            webhook: ChatWebhook::new("Slack", &config)?,
+            username: config.username,
+        })
+    }
+
+    fn build_payload(&self, event: &NotificationEvent) -> Value {
+        let facts: Vec<Value> = event_facts(event)
+            .into_iter()
+            .map(|(label, value)| json!({ "type": "mrkdwn", "text": format!("*{}:* {}", label, value) }))
+            .collect();
+        let blocks = json!([
+            {
+                "type": "header",
+                "text": { "type": "plain_text", "text": truncate(&event.title, MAX_HEADER_LENGTH) },
+            },
+            {
+                "type": "section",
+                "text": { "type": "plain_text", "text": truncate(&event.details, MAX_SECTION_LENGTH) },
+            },
+            { "type": "context", "elements": facts },
+        ]);
+
+        let mut payload = json!({ "text": event.title, "blocks": blocks });
+        if let Some(username) = &self.username {
+            payload["username"] = json!(username);
+        }
+        payload
+    }
+}
+
+#[async_trait]
+impl NotificationSender for SlackNotificationSender {
+    #[instrument(skip(self, event), fields(kind = ?event.kind, job_name = ?event.job_name))]
+    async fn notify(&self, event: NotificationEvent) -> Result<(), DeliveryError> {
+        info!("Sending notification via Slack with title {}", &event.title);
+        self.webhook.post(self.build_payload(&event)).await
+    }
+}
@@ -0,0 +1,75 @@
+use crate::notification::chat::{event_facts, ChatWebhook, ChatWebhookConfiguration};
+use crate::notification::event::{NotificationEvent, Severity};
+use crate::notification::{DeliveryError, NotificationSender};
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tracing::{info, instrument};
+
+/// Implementation of `NotificationSender` posting Adaptive Cards to a Microsoft Teams
+/// incoming webhook or workflow.
+pub struct TeamsNotificationSender {
+    webhook: ChatWebhook,
+}
+
+impl TeamsNotificationSender {
+    /// Creates a new `TeamsNotificationSender` with a configuration. Teams doesn't support
+    /// posting as another user, so `username` is ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - A `ChatWebhookConfiguration` with the webhook URL.
+    pub fn new(config: ChatWebhookConfiguration) -> Result<Self> {
+        Ok(TeamsNotificationSender {
+            webhook: ChatWebhook::new("Microsoft Teams", &config)?,
+        })
+    }
+
+    fn build_payload(event: &NotificationEvent) -> Value {
+        let title_color = match event.severity {
+            Severity::Info => "Good",
+            Severity::Warning => "Warning",
+            Severity::Error | Severity::Critical => "Attention",
+        };
+        let facts: Vec<Value> = event_facts(event)
+            .into_iter()
+            .map(|(label, value)| json!({ "title": label, "value": value }))
+            .collect();
+
+        json!({
+            "type": "message",
+            "attachments": [{
+                "contentType": "application/vnd.microsoft.card.adaptive",
+                "content": {
+                    "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+                    "type": "AdaptiveCard",
+                    "version": "1.4",
+                    "body": [
+                        {
+                            "type": "TextBlock",
+                            "text": event.title,
+                            "size": "Medium",
+                            "weight": "Bolder",
+                            "color": title_color,
+                            "wrap": true,
+                        },
+                        { "type": "TextBlock", "text": event.details, "wrap": true },
+                        { "type": "FactSet", "facts": facts },
+                    ],
+                },
+            }],
+        })
+    }
+}
+
+#[async_trait]
+impl NotificationSender for TeamsNotificationSender {
+    #[instrument(skip(self, event), fields(kind = ?event.kind, job_name = ?event.job_name))]
+    async fn notify(&self, event: NotificationEvent) -> Result<(), DeliveryError> {
+        info!(
+            "Sending notification via Microsoft Teams with title {}",
+            &event.title
+        );
+        self.webhook.post(Self::build_payload(&event)).await
+    }
+}
@@ -1,12 +1,33 @@
-use crate::notification::NotificationSender;
-use std::sync::Arc; // Keep this for shared ownership of senders.
+use crate::config::job_config::JobConfig;
+use crate::notification::composite_notification_sender::outbox::{NotificationOutbox, OutboxEntry};
+use crate::notification::composite_notification_sender::routing::{NotificationRouter, Target};
+use crate::notification::composite_notification_sender::throttling::NotificationThrottle;
+use crate::notification::composite_notification_sender::NamedNotificationSender;
+use crate::notification::event::NotificationEvent;
+use crate::notification::template::NotificationTemplates;
+use crate::notification::DeliveryError;
+use mongodb::bson::Document;
+use mongodb::Collection;
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, RwLock}; // Keep this for shared ownership of senders.
 use tokio::sync::Mutex;
-use tracing::error;
+use tracing::{error, info, warn};
 // Use tokio's async Mutex for async scenarios.
 
+/// A sender with the ID outbox entries refer to it by.
+struct RegisteredSender {
+    /// Name of the sender and a hash of its destination, such as `slack#3f2a9c1d04b7e865`.
+    id: String,
+    named: NamedNotificationSender,
+}
+
 /// Internal struct that holds and manages the list of notification senders.
 pub struct CompositeNotificationSenderInner {
-    senders: Mutex<Vec<Arc<dyn NotificationSender>>>,
+    senders: Mutex<Vec<RegisteredSender>>,
+    router: NotificationRouter,
+    throttle: Option<NotificationThrottle>,
+    outbox: NotificationOutbox,
+    templates: RwLock<Arc<NotificationTemplates>>,
 }
 
 impl CompositeNotificationSenderInner {
@@ -14,10 +35,29 @@ impl CompositeNotificationSenderInner {
     ///
     /// # Arguments
     ///
-    /// * `initial_senders` - A vector of objects implementing the `NotificationSender` trait.
-    pub fn new(initial_senders: Vec<Arc<dyn NotificationSender>>) -> Self {
+    /// * `initial_senders` - A vector of named objects implementing the `NotificationSender` trait.
+    /// * `router` - Decides which senders get a notification.
+    /// * `throttle` - Suppresses repeated notifications and applies rate limits, if configured.
+    /// * `outbox` - Keeps notifications until they are delivered.
+    /// * `templates` - Templates the subjects and bodies of notifications are rendered from.
+    pub fn new(
+        initial_senders: Vec<NamedNotificationSender>,
+        router: NotificationRouter,
+        throttle: Option<NotificationThrottle>,
+        outbox: NotificationOutbox,
+        templates: NotificationTemplates,
+    ) -> Self {
+        let mut senders = Vec::new();
+        for sender in initial_senders {
+            register(&mut senders, sender);
+        }
+        warn_about_unknown_senders(&senders, &router);
         CompositeNotificationSenderInner {
-            senders: Mutex::new(initial_senders),
+            senders: Mutex::new(senders),
+            router,
+            throttle,
+            outbox,
+            templates: RwLock::new(Arc::new(templates)),
         }
     }
 
@@ -25,35 +65,260 @@ impl CompositeNotificationSenderInner {
     ///
     /// # Arguments
     ///
-    /// * `sender` - An `Arc` pointing to an object that implements the `NotificationSender` trait.
-    pub async fn add_sender(&self, sender: Arc<dyn NotificationSender>) {
+    /// * `sender` - A named object that implements the `NotificationSender` trait.
+    #[allow(dead_code)]
+    pub async fn add_sender(&self, sender: NamedNotificationSender) {
         let mut senders = self.senders.lock().await;
-        senders.push(sender);
+        register(&mut senders, sender);
     }
 
-    /// Notifies all senders with the given title and contents.
+    /// Replaces the `notify` overrides of the jobs.
     ///
     /// # Arguments
     ///
-    /// * `message_title` - A shared reference to the notification title.
-    /// * `message_contents` - A shared reference to the notification message.
-    pub async fn notify(&self, message_title: String, message_contents: String) {
+    /// * `job_configs` - Configurations of all jobs.
+    pub async fn set_job_configs(&self, job_configs: &[JobConfig]) {
+        self.router.set_job_targets(job_configs);
         let senders = self.senders.lock().await;
+        warn_about_unknown_senders(&senders, &self.router);
+    }
+
+    /// Replaces the templates notifications are rendered from.
+    pub fn set_templates(&self, templates: NotificationTemplates) {
+        match self.templates.write() {
+            Ok(mut current) => *current = Arc::new(templates),
+            Err(e) => error!("Failed to update notification templates: {:?}", e),
+        }
+    }
+
+    /// Notifies the senders the event is routed to, unless it repeats a notification that
+    /// was sent recently.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The event each sender is notified about.
+    pub async fn notify(&self, event: NotificationEvent) {
+        if let Some(throttle) = &self.throttle {
+            if !throttle.admit(&event) {
+                info!(
+                    "Suppressed repeated notification with title {}",
+                    event.title
+                );
+                return;
+            }
+        }
+        let event = self.render(event);
+        self.dispatch(event, true).await;
+    }
+
+    /// Records that a run of a job succeeded, and notifies that the job recovered if it failed
+    /// before.
+    ///
+    /// # Arguments
+    ///
+    /// * `job_name` - Name of the job.
+    /// * `run_id` - ID of the successful run.
+    pub async fn report_success(&self, job_name: &str, run_id: &str) {
+        let recovery = self
+            .throttle
+            .as_ref()
+            .and_then(|throttle| throttle.record_success(job_name, run_id));
+        if let Some(event) = recovery {
+            let event = self.render(event);
+            self.dispatch(event, true).await;
+        }
+    }
+
+    /// Sends the digests of suppressed and rate limited notifications, ignoring rate limits.
+    pub async fn send_digests(&self) {
+        let Some(throttle) = &self.throttle else {
+            return;
+        };
+        for event in throttle.take_digests() {
+            self.dispatch(event, false).await;
+        }
+        for (sender_name, event) in throttle.take_rate_limit_digests() {
+            let entries: Vec<OutboxEntry> = {
+                let senders = self.senders.lock().await;
+                senders
+                    .iter()
+                    .filter(|sender| sender.named.name == sender_name)
+                    .map(|sender| OutboxEntry::new(sender.id.clone(), None, event.clone()))
+                    .collect()
+            };
+            self.outbox.enqueue(&entries);
+            self.deliver(entries).await;
+        }
+    }
+
+    /// Attempts the pending notifications whose retry is due.
+    pub async fn retry_pending(&self) {
+        let entries = self.outbox.take_due();
+        if !entries.is_empty() {
+            info!("Retrying {} pending notifications", entries.len());
+            self.deliver(entries).await;
+        }
+    }
+
+    /// Writes the outbox to a collection from now on, after loading what it holds.
+    pub async fn persist_outbox(&self, collection: Collection<Document>) {
+        self.outbox.persist(collection).await;
+    }
+
+    /// Returns the notifications that couldn't be delivered, the oldest first.
+    pub fn dead_letters(&self) -> Vec<OutboxEntry> {
+        self.outbox.dead_letters()
+    }
+
+    /// Renders the subject and bodies of a notification from the templates. Digests keep
+    /// their built-in texts, as they summarize many notifications.
+    fn render(&self, event: NotificationEvent) -> NotificationEvent {
+        let templates = match self.templates.read() {
+            Ok(templates) => Arc::clone(&templates),
+            Err(e) => {
+                error!("Failed to read notification templates: {:?}", e);
+                return event;
+            }
+        };
+        templates.render(event)
+    }
+
+    /// Queues an event for the senders it's routed to in the outbox, and attempts it.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The event each sender is notified about.
+    /// * `rate_limited` - Whether the rate limits of the senders apply.
+    async fn dispatch(&self, event: NotificationEvent, rate_limited: bool) {
+        let targets = self.router.targets(&event);
+        let entries = {
+            let senders = self.senders.lock().await;
+            self.outbox_entries(&senders, &targets, &event, rate_limited)
+        };
+        self.outbox.enqueue(&entries);
+        self.deliver(entries).await;
+    }
+
+    /// Creates an outbox entry for every sender the event is routed to and every distinct
+    /// list of recipients it's routed to the sender with.
+    fn outbox_entries(
+        &self,
+        senders: &[RegisteredSender],
+        targets: &[Target],
+        event: &NotificationEvent,
+        rate_limited: bool,
+    ) -> Vec<OutboxEntry> {
+        // Rate limits apply to all senders sharing a name together
+        let mut allowed_names: Vec<&str> = Vec::new();
+        for sender in senders {
+            let name = sender.named.name.as_str();
+            if allowed_names.contains(&name) || !targets.iter().any(|t| t.includes(name)) {
+                continue;
+            }
+            let within_limit = !rate_limited
+                || self
+                    .throttle
+                    .as_ref()
+                    .is_none_or(|throttle| throttle.allow_sender(name));
+            if within_limit {
+                allowed_names.push(name);
+            } else {
+                info!(
+                    "Rate limit of sender {} reached, notification with title {} left for the digest",
+                    name, event.title
+                );
+            }
+        }
+
+        let mut entries = Vec::new();
+        for sender in senders
+            .iter()
+            .filter(|sender| allowed_names.contains(&sender.named.name.as_str()))
+        {
+            let mut recipient_lists: Vec<Option<Vec<String>>> = Vec::new();
+            for target in targets.iter().filter(|t| t.includes(&sender.named.name)) {
+                if !recipient_lists.contains(&target.to_emails) {
+                    recipient_lists.push(target.to_emails.clone());
+                }
+            }
+            entries.extend(
+                recipient_lists.into_iter().map(|recipients| {
+                    OutboxEntry::new(sender.id.clone(), recipients, event.clone())
+                }),
+            );
+        }
+        entries
+    }
+
+    /// Attempts outbox entries concurrently and records their outcomes in the outbox.
+    async fn deliver(&self, entries: Vec<OutboxEntry>) {
         let mut handles = Vec::new();
-        for sender in senders.iter() {
-            let sender_clone = Arc::clone(sender);
-            let title_clone = message_title.clone();
-            let contents_clone = message_contents.clone();
-            let handle = tokio::spawn(async move {
-                sender_clone.notify(title_clone, contents_clone).await;
-            });
-            handles.push(handle);
+        {
+            let senders = self.senders.lock().await;
+            for entry in entries {
+                let Some(sender) = senders.iter().find(|sender| sender.id == entry.sender_id)
+                else {
+                    self.outbox.record_outcome(
+                        &entry.id,
+                        Err(DeliveryError::Permanent(format!(
+                            "Sender {} is no longer configured",
+                            entry.sender_id
+                        ))),
+                    );
+                    continue;
+                };
+
+                let sender_clone = Arc::clone(&sender.named.sender);
+                let handle = tokio::spawn(async move {
+                    let outcome = match entry.recipients {
+                        Some(recipients) => {
+                            sender_clone
+                                .notify_recipients(entry.event, recipients)
+                                .await
+                        }
+                        None => sender_clone.notify(entry.event).await,
+                    };
+                    (entry.id, outcome)
+                });
+                handles.push(handle);
+            }
         }
 
         for handle in handles {
-            if let Err(e) = handle.await {
-                error!("Notification task failed: {:?}", e);
+            match handle.await {
+                Ok((id, outcome)) => self.outbox.record_outcome(&id, outcome),
+                Err(e) => error!("Notification task failed: {:?}", e),
             }
         }
     }
 }
+
+/// Adds a sender, giving it an ID derived from its name and destination, so the ID stays the
+/// same when other senders are added or removed.
+fn register(senders: &mut Vec<RegisteredSender>, sender: NamedNotificationSender) {
+    let digest = Sha256::digest(sender.destination.as_bytes());
+    let mut id = format!("{}#{}", sender.name, hex::encode(&digest[..8]));
+    // Senders with the same name and destination are told apart by their position
+    let duplicates = senders
+        .iter()
+        .filter(|registered| {
+            registered.named.name == sender.name
+                && registered.named.destination == sender.destination
+        })
+        .count();
+    if duplicates > 0 {
+        id = format!("{}-{}", id, duplicates + 1);
+    }
+    senders.push(RegisteredSender { id, named: sender });
+}
+
+fn warn_about_unknown_senders(senders: &[RegisteredSender], router: &NotificationRouter) {
+    for name in router.sender_names() {
+        if !senders.iter().any(|sender| sender.named.name == name) {
+            warn!(
+                "Notifications are routed to sender {}, but no sender with that name is configured",
+                name
+            );
+        }
+    }
+}
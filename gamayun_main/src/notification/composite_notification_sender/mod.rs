@@ -1,12 +1,60 @@
+use crate::config::app_config::{
+    NotificationDeliveryConfig, NotificationRoutingConfig, NotificationThrottlingConfig,
+};
+use crate::config::job_config::JobConfig;
 use crate::notification::composite_notification_sender::inner::CompositeNotificationSenderInner;
+use crate::notification::composite_notification_sender::outbox::{
+    NotificationOutbox, OutboxEntry, OUTBOX_COLLECTION,
+};
+use crate::notification::composite_notification_sender::routing::NotificationRouter;
+use crate::notification::composite_notification_sender::throttling::NotificationThrottle;
+use crate::notification::event::NotificationEvent;
+use crate::notification::template::NotificationTemplates;
 use crate::notification::NotificationSender;
-use async_trait::async_trait;
+use anyhow::{Context, Result};
+use chrono::Utc;
+use grizzly_scheduler::scheduler::Scheduler;
+use mongodb::Database;
 use std::sync::Arc;
 
 mod inner;
+mod outbox;
+mod routing;
+mod throttling;
+
+/// A notification sender with the name routing rules refer to it by. Several senders can
+/// share a name.
+pub struct NamedNotificationSender {
+    pub name: String,
+    pub sender: Arc<dyn NotificationSender>,
+    /// Where the sender delivers to, such as a webhook URL or a chat ID. Identifies the sender
+    /// among the ones with its name, so outbox entries kept across a restart still reach the
+    /// same destination.
+    pub destination: String,
+}
+
+impl NamedNotificationSender {
+    pub fn new<S>(name: impl Into<String>, sender: S) -> Self
+    where
+        S: NotificationSender + 'static,
+    {
+        NamedNotificationSender {
+            name: name.into(),
+            sender: Arc::new(sender),
+            destination: String::new(),
+        }
+    }
+
+    /// Sets where the sender delivers to, see `destination`.
+    pub fn with_destination(mut self, destination: impl Into<String>) -> Self {
+        self.destination = destination.into();
+        self
+    }
+}
 
 /// A composite notification sender that holds multiple senders and
-/// dispatches notifications to all of them.
+/// dispatches notifications to the ones its routing rules pick.
+/// Notifications are kept in an outbox and retried until every sender delivered them.
 /// It's thread-safe, it can be cloned
 #[derive(Clone)]
 pub struct CompositeNotificationSender {
@@ -17,42 +65,183 @@ impl CompositeNotificationSender {
     ///
     /// # Arguments
     ///
-    /// * `initial_senders` - An optional vector of `Arc` objects implementing the `NotificationSender` trait.
+    /// * `initial_senders` - An optional vector of named objects implementing the `NotificationSender` trait.
+    /// * `routing` - Routing rules, every sender gets every notification when `None`.
+    /// * `throttling` - Deduplication and rate limits, every notification is sent when `None`.
+    /// * `delivery` - Retries of failed notifications and the number of dead letters kept.
+    /// * `templates` - Templates the subjects and bodies of notifications are rendered from.
     ///
     /// # Example
     ///
     /// ```rust
-    /// let composite_sender = composite_notification_sender::new(Some(vec![sender1, sender2]));
+    /// let composite_sender = composite_notification_sender::new(
+    ///     Some(vec![sender1, sender2]),
+    ///     None,
+    ///     None,
+    ///     &NotificationDeliveryConfig::default(),
+    ///     NotificationTemplates::load(&config_root, None),
+    /// );
     /// ```
-    pub fn new(initial_senders: Option<Vec<Arc<dyn NotificationSender>>>) -> Self {
-        let senders = initial_senders.unwrap_or_else(Vec::new);
+    pub fn new(
+        initial_senders: Option<Vec<NamedNotificationSender>>,
+        routing: Option<NotificationRoutingConfig>,
+        throttling: Option<&NotificationThrottlingConfig>,
+        delivery: &NotificationDeliveryConfig,
+        templates: NotificationTemplates,
+    ) -> Self {
+        let senders = initial_senders.unwrap_or_default();
         CompositeNotificationSender {
-            inner: Arc::new(CompositeNotificationSenderInner::new(senders)),
+            inner: Arc::new(CompositeNotificationSenderInner::new(
+                senders,
+                NotificationRouter::new(routing),
+                throttling.map(NotificationThrottle::new),
+                NotificationOutbox::new(delivery),
+                templates,
+            )),
         }
     }
 
+    /// Notifies the senders the event is routed to. The notification is sent in the
+    /// background, so callers such as gRPC handlers don't wait for slow channels. Failed
+    /// deliveries are retried by the schedule of `schedule_redelivery`.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - A `NotificationEvent` describing what happened.
+    pub async fn notify(&self, event: NotificationEvent) {
+        let inner = self.inner.clone();
+        tokio::spawn(async move { inner.notify(event).await });
+    }
+
+    /// Notifies the senders the event is routed to and waits for the first attempt, for
+    /// notifications sent right before Gamayun exits.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - A `NotificationEvent` describing what happened.
+    pub async fn notify_and_wait(&self, event: NotificationEvent) {
+        self.inner.notify(event).await;
+    }
+
     /// Adds a new `NotificationSender` to the composite.
     ///
     /// # Arguments
     ///
+    /// * `name` - Name routing rules refer to the sender by.
     /// * `sender` - An object that implements the `NotificationSender` trait.
     ///
     /// # Example
     ///
     /// ```rust
-    /// composite_sender.add_sender(new_sender).await;
+    /// composite_sender.add_sender("slack", new_sender).await;
     /// ```
-    pub async fn add_sender<S>(&self, sender: S)
+    #[allow(dead_code)]
+    pub async fn add_sender<S>(&self, name: impl Into<String>, sender: S)
     where
         S: NotificationSender + 'static,
     {
-        self.inner.add_sender(Arc::new(sender)).await;
+        self.inner
+            .add_sender(NamedNotificationSender::new(name, sender))
+            .await;
+    }
+
+    /// Applies the `notify` overrides of the jobs, replacing the ones of earlier configurations.
+    ///
+    /// # Arguments
+    ///
+    /// * `job_configs` - Configurations of all jobs.
+    pub async fn set_job_configs(&self, job_configs: &[JobConfig]) {
+        self.inner.set_job_configs(job_configs).await;
+    }
+
+    /// Replaces the templates notifications are rendered from, such as after the
+    /// configuration is reloaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `templates` - The new templates.
+    pub fn set_templates(&self, templates: NotificationTemplates) {
+        self.inner.set_templates(templates);
+    }
+
+    /// Records that a run of a job succeeded, and notifies that the job recovered if it failed
+    /// before, in the background like `notify`. Does nothing unless throttling is configured.
+    ///
+    /// # Arguments
+    ///
+    /// * `job_name` - Name of the job.
+    /// * `run_id` - ID of the successful run.
+    pub async fn report_success(&self, job_name: &str, run_id: &str) {
+        let inner = self.inner.clone();
+        let job_name = job_name.to_string();
+        let run_id = run_id.to_string();
+        tokio::spawn(async move { inner.report_success(&job_name, &run_id).await });
+    }
+
+    /// Schedules the digests of suppressed and rate limited notifications.
+    ///
+    /// # Arguments
+    ///
+    /// * `scheduler` - The scheduler the digests are sent by.
+    /// * `throttling` - Configuration with the cron string of the digests.
+    pub fn schedule_digests(
+        &self,
+        scheduler: Scheduler<Utc>,
+        throttling: &NotificationThrottlingConfig,
+    ) -> Result<()> {
+        let inner = self.inner.clone();
+        scheduler
+            .schedule_sequential_job(
+                &throttling.digest_cron(),
+                Some("Notification Digest".to_string()),
+                None,
+                None,
+                move || {
+                    let inner = inner.clone();
+                    async move { inner.send_digests().await }
+                },
+            )
+            .context("Failed to schedule notification digests")?;
+        Ok(())
+    }
+
+    /// Keeps the outbox in a collection of the Gamayun database, so pending and dead-lettered
+    /// notifications survive restarts. Loads the notifications left by an earlier run.
+    ///
+    /// # Arguments
+    ///
+    /// * `database` - The Gamayun database.
+    pub async fn persist_outbox(&self, database: &Database) {
+        self.inner
+            .persist_outbox(database.collection(OUTBOX_COLLECTION))
+            .await;
+    }
+
+    /// Schedules the retries of the notifications in the outbox, including the ones left
+    /// pending by an earlier run.
+    ///
+    /// # Arguments
+    ///
+    /// * `scheduler` - The scheduler the retries are attempted by.
+    pub fn schedule_redelivery(&self, scheduler: Scheduler<Utc>) -> Result<()> {
+        let inner = self.inner.clone();
+        scheduler
+            .schedule_sequential_job(
+                "*/10 * * * * *", // look for due retries every 10 seconds
+                Some("Notification Redelivery".to_string()),
+                None,
+                None,
+                move || {
+                    let inner = inner.clone();
+                    async move { inner.retry_pending().await }
+                },
+            )
+            .context("Failed to schedule notification redelivery")?;
+        Ok(())
     }
-}
 
-#[async_trait]
-impl NotificationSender for CompositeNotificationSender {
-    async fn notify(&self, message_title: String, message_contents: String) {
-        self.inner.notify(message_title, message_contents).await;
+    /// Returns the notifications that couldn't be delivered, the oldest first.
+    pub fn dead_letters(&self) -> Vec<OutboxEntry> {
+        self.inner.dead_letters()
     }
 }
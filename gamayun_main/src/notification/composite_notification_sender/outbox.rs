@@ -0,0 +1,393 @@
+use crate::config::app_config::NotificationDeliveryConfig;
+use crate::notification::event::NotificationEvent;
+use crate::notification::DeliveryError;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use futures::TryStreamExt;
+use mongodb::bson::{self, doc, Document};
+use mongodb::Collection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+/// Collection in the Gamayun database holding the pending and dead-lettered notifications.
+pub const OUTBOX_COLLECTION: &str = "notification_outbox";
+
+/// A notification waiting to be delivered by one sender, or dead-lettered after it couldn't
+/// be.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    pub id: String,
+    /// Name of the sender and a hash of its destination, such as `slack#3f2a9c1d04b7e865`.
+    pub sender_id: String,
+    /// Recipients of e-mail senders, their configured recipients when not set.
+    pub recipients: Option<Vec<String>>,
+    pub event: NotificationEvent,
+    pub attempts: u32,
+    pub created_at: DateTime<Utc>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dead_lettered_at: Option<DateTime<Utc>>,
+}
+
+impl OutboxEntry {
+    pub fn new(
+        sender_id: String,
+        recipients: Option<Vec<String>>,
+        event: NotificationEvent,
+    ) -> Self {
+        let now = Utc::now();
+        OutboxEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            sender_id,
+            recipients,
+            event,
+            attempts: 0,
+            created_at: now,
+            next_attempt_at: now,
+            last_error: None,
+            dead_lettered_at: None,
+        }
+    }
+}
+
+/// What the outbox holds.
+#[derive(Default)]
+struct OutboxContents {
+    pending: Vec<OutboxEntry>,
+    dead_letters: Vec<OutboxEntry>,
+}
+
+/// A change to the outbox collection. Changes are applied in order by the writer task.
+enum OutboxWrite {
+    Insert(Vec<OutboxEntry>),
+    Replace(Box<OutboxEntry>),
+    Remove(Vec<String>),
+}
+
+#[derive(Default)]
+struct OutboxState {
+    contents: OutboxContents,
+    /// IDs of the pending entries a sender is working on.
+    in_flight: HashSet<String>,
+    /// Sends changes to the writer task, once the outbox is persisted.
+    writer: Option<mpsc::UnboundedSender<OutboxWrite>>,
+}
+
+/// Notifications that are not delivered yet, kept until every sender accepted them or they
+/// are dead-lettered. Once `persist` is called, every change is also written to the outbox
+/// collection, one document per entry, by a background task.
+pub struct NotificationOutbox {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    max_dead_letters: usize,
+    state: Mutex<OutboxState>,
+}
+
+impl NotificationOutbox {
+    /// Creates a new, empty `NotificationOutbox`.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Retry policy and the number of dead letters kept.
+    pub fn new(config: &NotificationDeliveryConfig) -> Self {
+        NotificationOutbox {
+            max_attempts: config.max_attempts(),
+            initial_backoff: Duration::seconds(config.initial_backoff_seconds() as i64),
+            max_backoff: Duration::seconds(config.max_backoff_seconds() as i64),
+            max_dead_letters: config.max_dead_letters(),
+            state: Mutex::new(OutboxState::default()),
+        }
+    }
+
+    /// Loads the notifications left pending by an earlier run from the outbox collection,
+    /// and writes every later change to it. Notifications queued before are written too.
+    ///
+    /// # Arguments
+    ///
+    /// * `collection` - The outbox collection in the Gamayun database.
+    pub async fn persist(&self, collection: Collection<Document>) {
+        let loaded = load_contents(&collection).await;
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        {
+            let Ok(mut state) = self.state.lock() else {
+                error!("Failed to lock the notification outbox");
+                return;
+            };
+            let unsaved: Vec<OutboxEntry> = state
+                .contents
+                .pending
+                .iter()
+                .chain(&state.contents.dead_letters)
+                .cloned()
+                .collect();
+
+            let mut pending = loaded.pending;
+            pending.append(&mut state.contents.pending);
+            let mut dead_letters = loaded.dead_letters;
+            dead_letters.append(&mut state.contents.dead_letters);
+            state.contents = OutboxContents {
+                pending,
+                dead_letters,
+            };
+
+            if !unsaved.is_empty() {
+                let _ = sender.send(OutboxWrite::Insert(unsaved));
+            }
+            let trimmed = self.trim_dead_letters(&mut state.contents);
+            if !trimmed.is_empty() {
+                let _ = sender.send(OutboxWrite::Remove(trimmed));
+            }
+            state.writer = Some(sender);
+        }
+
+        tokio::spawn(write_changes(collection, receiver));
+    }
+
+    /// Adds notifications that are about to be attempted for the first time.
+    pub fn enqueue(&self, entries: &[OutboxEntry]) {
+        self.update(|state| {
+            for entry in entries {
+                state.in_flight.insert(entry.id.clone());
+                state.contents.pending.push(entry.clone());
+            }
+            vec![OutboxWrite::Insert(entries.to_vec())]
+        });
+    }
+
+    /// Returns the pending notifications whose next attempt is due, marking them as in flight.
+    pub fn take_due(&self) -> Vec<OutboxEntry> {
+        let now = Utc::now();
+        let Ok(mut state) = self.state.lock() else {
+            error!("Failed to lock the notification outbox");
+            return Vec::new();
+        };
+        let OutboxState {
+            contents,
+            in_flight,
+            ..
+        } = &mut *state;
+
+        let due: Vec<OutboxEntry> = contents
+            .pending
+            .iter()
+            .filter(|entry| entry.next_attempt_at <= now && !in_flight.contains(&entry.id))
+            .cloned()
+            .collect();
+        in_flight.extend(due.iter().map(|entry| entry.id.clone()));
+        due
+    }
+
+    /// Records the outcome of an attempt. Delivered notifications are removed, failed ones
+    /// are scheduled for a retry with exponential backoff, or dead-lettered when the error is
+    /// permanent or the attempts are used up.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of the attempted entry.
+    /// * `outcome` - What the sender reported.
+    pub fn record_outcome(&self, id: &str, outcome: Result<(), DeliveryError>) {
+        self.update(|state| {
+            state.in_flight.remove(id);
+            let Some(position) = state
+                .contents
+                .pending
+                .iter()
+                .position(|entry| entry.id == id)
+            else {
+                return Vec::new();
+            };
+
+            let Err(e) = outcome else {
+                state.contents.pending.remove(position);
+                return vec![OutboxWrite::Remove(vec![id.to_string()])];
+            };
+
+            let entry = &mut state.contents.pending[position];
+            entry.attempts += 1;
+            entry.last_error = Some(e.to_string());
+            if e.is_transient() && entry.attempts < self.max_attempts {
+                let backoff = self.backoff(entry.attempts);
+                entry.next_attempt_at = Utc::now() + backoff;
+                warn!(
+                    "Sender {} failed to deliver notification {} on attempt {} of {}, retrying in {} seconds: {}",
+                    entry.sender_id,
+                    entry.event.title,
+                    entry.attempts,
+                    self.max_attempts,
+                    backoff.num_seconds(),
+                    e
+                );
+                return vec![OutboxWrite::Replace(Box::new(entry.clone()))];
+            }
+
+            let mut entry = state.contents.pending.remove(position);
+            error!(
+                "Sender {} failed to deliver notification {} after {} attempts, dead-lettering it: {}",
+                entry.sender_id, entry.event.title, entry.attempts, e
+            );
+            entry.dead_lettered_at = Some(Utc::now());
+            state.contents.dead_letters.push(entry.clone());
+            let trimmed = self.trim_dead_letters(&mut state.contents);
+            vec![
+                OutboxWrite::Replace(Box::new(entry)),
+                OutboxWrite::Remove(trimmed),
+            ]
+        });
+    }
+
+    /// Returns the dead-lettered notifications, the oldest first.
+    pub fn dead_letters(&self) -> Vec<OutboxEntry> {
+        match self.state.lock() {
+            Ok(state) => state.contents.dead_letters.clone(),
+            Err(e) => {
+                error!("Failed to lock the notification outbox: {:?}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Delay before the attempt following the given one.
+    fn backoff(&self, attempts: u32) -> Duration {
+        let factor = 2i32.saturating_pow(attempts.saturating_sub(1));
+        self.initial_backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+
+    /// Drops the oldest dead letters over the limit.
+    ///
+    /// # Returns
+    ///
+    /// `Vec<String>` - IDs of the dropped dead letters.
+    fn trim_dead_letters(&self, contents: &mut OutboxContents) -> Vec<String> {
+        let excess = contents
+            .dead_letters
+            .len()
+            .saturating_sub(self.max_dead_letters);
+        contents
+            .dead_letters
+            .drain(..excess)
+            .map(|entry| entry.id)
+            .collect()
+    }
+
+    /// Applies a change to the outbox and hands what changed to the writer task. Nothing is
+    /// written while the lock is held, the writer task does that.
+    fn update<F>(&self, change: F)
+    where
+        F: FnOnce(&mut OutboxState) -> Vec<OutboxWrite>,
+    {
+        let Ok(mut state) = self.state.lock() else {
+            error!("Failed to lock the notification outbox");
+            return;
+        };
+        let writes = change(&mut state);
+
+        if let Some(writer) = &state.writer {
+            for write in writes {
+                if writer.send(write).is_err() {
+                    error!("Failed to write the notification outbox, the writer stopped");
+                }
+            }
+        }
+    }
+}
+
+/// Loads the pending and dead-lettered notifications from the outbox collection, the oldest
+/// first. Entries that can't be read are logged and left out.
+async fn load_contents(collection: &Collection<Document>) -> OutboxContents {
+    let documents: Vec<Document> = match collection.find(doc! {}).await {
+        Ok(cursor) => match cursor.try_collect().await {
+            Ok(documents) => documents,
+            Err(e) => {
+                error!("Failed to load the notification outbox: {:?}", e);
+                return OutboxContents::default();
+            }
+        },
+        Err(e) => {
+            error!("Failed to load the notification outbox: {:?}", e);
+            return OutboxContents::default();
+        }
+    };
+
+    let mut contents = OutboxContents::default();
+    for document in documents {
+        match bson::from_document::<OutboxEntry>(document) {
+            Ok(entry) if entry.dead_lettered_at.is_some() => contents.dead_letters.push(entry),
+            Ok(entry) => contents.pending.push(entry),
+            Err(e) => error!("Ignoring unreadable notification outbox entry: {:?}", e),
+        }
+    }
+    contents.pending.sort_by_key(|entry| entry.created_at);
+    contents
+        .dead_letters
+        .sort_by_key(|entry| entry.dead_lettered_at);
+
+    info!(
+        "Loaded {} pending and {} dead-lettered notifications from the outbox",
+        contents.pending.len(),
+        contents.dead_letters.len()
+    );
+    contents
+}
+
+/// Writes the changes to the outbox collection in the order they were made.
+async fn write_changes(
+    collection: Collection<Document>,
+    mut writes: mpsc::UnboundedReceiver<OutboxWrite>,
+) {
+    while let Some(write) = writes.recv().await {
+        if let Err(e) = write_change(&collection, write).await {
+            error!("Failed to write the notification outbox: {:?}", e);
+        }
+    }
+}
+
+async fn write_change(collection: &Collection<Document>, write: OutboxWrite) -> Result<()> {
+    match write {
+        OutboxWrite::Insert(entries) => {
+            if entries.is_empty() {
+                return Ok(());
+            }
+            let documents = entries
+                .iter()
+                .map(entry_document)
+                .collect::<Result<Vec<_>>>()?;
+            collection
+                .insert_many(documents)
+                .await
+                .context("Failed to insert outbox entries")?;
+        }
+        OutboxWrite::Replace(entry) => {
+            collection
+                .replace_one(doc! { "_id": &entry.id }, entry_document(&entry)?)
+                .upsert(true)
+                .await
+                .context("Failed to update an outbox entry")?;
+        }
+        OutboxWrite::Remove(ids) => {
+            if ids.is_empty() {
+                return Ok(());
+            }
+            collection
+                .delete_many(doc! { "_id": { "$in": ids } })
+                .await
+                .context("Failed to remove outbox entries")?;
+        }
+    }
+    Ok(())
+}
+
+/// The document of an entry, keyed by the ID of the entry.
+fn entry_document(entry: &OutboxEntry) -> Result<Document> {
+    let mut document = bson::to_document(entry).context("Failed to serialize an outbox entry")?;
+    document.insert("_id", entry.id.clone());
+    Ok(document)
+}
@@ -0,0 +1,219 @@
+use crate::config::app_config::{NotificationRoute, NotificationRoutingConfig};
+use crate::config::job_config::{JobConfig, JobNotificationTarget};
+use crate::notification::event::{NotificationEvent, NotificationKind, Severity};
+use glob::Pattern;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use tracing::{error, warn};
+
+/// Senders a notification is sent through and who they send it to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    /// Names of the senders, all senders when `None`.
+    pub senders: Option<Vec<String>>,
+    /// Recipients of e-mail senders, their configured recipients when `None`.
+    pub to_emails: Option<Vec<String>>,
+}
+
+impl Target {
+    fn everyone() -> Self {
+        Target {
+            senders: None,
+            to_emails: None,
+        }
+    }
+
+    fn new(senders: &[String], to_emails: &Option<Vec<String>>) -> Self {
+        Target {
+            senders: (!senders.is_empty()).then(|| senders.to_vec()),
+            to_emails: to_emails.clone(),
+        }
+    }
+
+    /// Whether the sender with the given name is part of the target.
+    pub fn includes(&self, sender_name: &str) -> bool {
+        self.senders
+            .as_ref()
+            .is_none_or(|senders| senders.iter().any(|name| name == sender_name))
+    }
+}
+
+/// A `NotificationRoute` with its job name patterns compiled.
+struct Route {
+    jobs: Vec<Pattern>,
+    tags: Vec<String>,
+    kinds: Vec<NotificationKind>,
+    min_severity: Option<Severity>,
+    target: Target,
+}
+
+impl Route {
+    fn new(route: &NotificationRoute) -> Result<Self, glob::PatternError> {
+        Ok(Route {
+            jobs: route
+                .jobs
+                .iter()
+                .map(|job| Pattern::new(job))
+                .collect::<Result<_, _>>()?,
+            tags: route.tags.clone(),
+            kinds: route.kinds.clone(),
+            min_severity: route.min_severity,
+            target: Target::new(&route.senders, &route.to_emails),
+        })
+    }
+
+    fn matches(&self, event: &NotificationEvent) -> bool {
+        let job_matches = self.jobs.is_empty()
+            || event
+                .job_name
+                .as_ref()
+                .is_some_and(|job_name| self.jobs.iter().any(|job| job.matches(job_name)));
+        let tags_match = self.tags.is_empty()
+            || (event.job_name.is_some() && event.tags.iter().any(|tag| self.tags.contains(tag)));
+        let kind_matches = self.kinds.is_empty() || self.kinds.contains(&event.kind);
+        let severity_matches = self
+            .min_severity
+            .is_none_or(|min_severity| event.severity >= min_severity);
+
+        job_matches && tags_match && kind_matches && severity_matches
+    }
+}
+
+/// Decides which senders get a notification, based on the routing rules of the application
+/// configuration and the `notify` overrides of the jobs.
+pub struct NotificationRouter {
+    /// `None` when routing isn't configured and every sender gets every notification.
+    routes: Option<Vec<Route>>,
+    default_target: Option<Target>,
+    job_targets: RwLock<HashMap<String, JobNotificationTarget>>,
+}
+
+impl NotificationRouter {
+    /// Creates a new `NotificationRouter`. Rules with an invalid job name pattern are logged
+    /// and left out.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The routing rules, every sender gets every notification when `None`.
+    pub fn new(config: Option<NotificationRoutingConfig>) -> Self {
+        let Some(config) = config else {
+            return NotificationRouter {
+                routes: None,
+                default_target: Some(Target::everyone()),
+                job_targets: RwLock::new(HashMap::new()),
+            };
+        };
+
+        let routes = config
+            .routes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, route)| match Route::new(route) {
+                Ok(route) => Some(route),
+                Err(e) => {
+                    error!(
+                        "Ignoring notification route number {} with an invalid job pattern: {}",
+                        index + 1,
+                        e
+                    );
+                    None
+                }
+            })
+            .collect();
+        let default_target = match config.default_senders {
+            None => Some(Target::everyone()),
+            Some(senders) if senders.is_empty() => None,
+            Some(senders) => Some(Target::new(&senders, &None)),
+        };
+
+        NotificationRouter {
+            routes: Some(routes),
+            default_target,
+            job_targets: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Replaces the `notify` overrides of the jobs.
+    ///
+    /// # Arguments
+    ///
+    /// * `job_configs` - Configurations of all jobs.
+    pub fn set_job_targets(&self, job_configs: &[JobConfig]) {
+        let job_targets = job_configs
+            .iter()
+            .filter_map(|job_config| {
+                job_config
+                    .notify
+                    .clone()
+                    .map(|target| (job_config.name.clone(), target))
+            })
+            .collect();
+        match self.job_targets.write() {
+            Ok(mut current) => *current = job_targets,
+            Err(e) => error!("Failed to update job notification targets: {:?}", e),
+        }
+    }
+
+    /// Names of the senders the routing rules and job overrides refer to.
+    pub fn sender_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .routes
+            .iter()
+            .flatten()
+            .map(|route| &route.target)
+            .chain(&self.default_target)
+            .filter_map(|target| target.senders.clone())
+            .flatten()
+            .collect();
+        if let Ok(job_targets) = self.job_targets.read() {
+            names.extend(
+                job_targets
+                    .values()
+                    .flat_map(|target| target.senders.iter().cloned()),
+            );
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Returns where a notification is sent, without duplicates. Empty when it isn't sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The notification.
+    pub fn targets(&self, event: &NotificationEvent) -> Vec<Target> {
+        if let Some(job_target) = self.job_target(event) {
+            let severity_matches = job_target
+                .min_severity
+                .is_none_or(|min_severity| event.severity >= min_severity);
+            return if severity_matches {
+                vec![Target::new(&job_target.senders, &job_target.to_emails)]
+            } else {
+                Vec::new()
+            };
+        }
+
+        let mut targets: Vec<Target> = Vec::new();
+        for route in self.routes.iter().flatten() {
+            if route.matches(event) && !targets.contains(&route.target) {
+                targets.push(route.target.clone());
+            }
+        }
+        if targets.is_empty() {
+            targets.extend(self.default_target.clone());
+        }
+        targets
+    }
+
+    fn job_target(&self, event: &NotificationEvent) -> Option<JobNotificationTarget> {
+        let job_name = event.job_name.as_ref()?;
+        match self.job_targets.read() {
+            Ok(job_targets) => job_targets.get(job_name).cloned(),
+            Err(e) => {
+                warn!("Failed to read job notification targets: {:?}", e);
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use crate::config::app_config::NotificationThrottlingConfig;
+use crate::notification::event::{NotificationEvent, NotificationKind, Severity};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use tracing::error;
+
+/// Identifies repeats of a notification: the job, the kind and the fingerprint.
+type DedupKey = (Option<String>, NotificationKind, String);
+
+/// A notification that was sent, and the repeats of it that were suppressed since.
+struct Suppression {
+    last_sent: DateTime<Utc>,
+    suppressed: u32,
+    /// When the first of the suppressed repeats happened.
+    since: DateTime<Utc>,
+    last_event: NotificationEvent,
+}
+
+/// A job that failed since it last succeeded.
+struct FailingJob {
+    since: DateTime<Utc>,
+    failures: u32,
+    tags: Vec<String>,
+}
+
+struct RateLimit {
+    max_notifications: usize,
+    period: Duration,
+    /// When the notifications of the current period were sent.
+    sent: VecDeque<DateTime<Utc>>,
+    dropped: u32,
+    dropped_since: DateTime<Utc>,
+}
+
+#[derive(Default)]
+struct ThrottleState {
+    suppressions: HashMap<DedupKey, Suppression>,
+    failing_jobs: HashMap<String, FailingJob>,
+    rate_limits: HashMap<String, RateLimit>,
+}
+
+/// Suppresses repeated notifications, applies rate limits of senders and keeps track of what
+/// was left out for the digests.
+pub struct NotificationThrottle {
+    dedup_window: Duration,
+    notify_recovery: bool,
+    state: Mutex<ThrottleState>,
+}
+
+impl NotificationThrottle {
+    /// Creates a new `NotificationThrottle`.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Deduplication window and rate limits.
+    pub fn new(config: &NotificationThrottlingConfig) -> Self {
+        let now = Utc::now();
+        let rate_limits = config
+            .rate_limits
+            .iter()
+            .map(|rate_limit| {
+                (
+                    rate_limit.sender.clone(),
+                    RateLimit {
+                        max_notifications: rate_limit.max_notifications as usize,
+                        period: Duration::seconds(rate_limit.period_seconds() as i64),
+                        sent: VecDeque::new(),
+                        dropped: 0,
+                        dropped_since: now,
+                    },
+                )
+            })
+            .collect();
+
+        NotificationThrottle {
+            dedup_window: Duration::seconds(config.dedup_window_seconds() as i64),
+            notify_recovery: config.notify_recovery(),
+            state: Mutex::new(ThrottleState {
+                rate_limits,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Records a notification and decides whether it's sent, or suppressed as a repeat of a
+    /// notification sent within the deduplication window.
+    ///
+    /// # Returns
+    ///
+    /// `bool` - Whether the notification should be sent.
+    pub fn admit(&self, event: &NotificationEvent) -> bool {
+        let Some(mut state) = self.lock_state() else {
+            return true;
+        };
+        let now = Utc::now();
+
+        if let (Some(job_name), NotificationKind::JobError | NotificationKind::Overdue) =
+            (&event.job_name, event.kind)
+        {
+            let failing_job = state
+                .failing_jobs
+                .entry(job_name.clone())
+                .or_insert_with(|| FailingJob {
+                    since: now,
+                    failures: 0,
+                    tags: Vec::new(),
+                });
+            failing_job.failures += 1;
+            failing_job.tags = event.tags.clone();
+        }
+
+        let key = (
+            event.job_name.clone(),
+            event.kind,
+            event.fingerprint().to_string(),
+        );
+        if let Some(suppression) = state.suppressions.get_mut(&key) {
+            if now - suppression.last_sent < self.dedup_window {
+                if suppression.suppressed == 0 {
+                    suppression.since = now;
+                }
+                suppression.suppressed += 1;
+                suppression.last_event = event.clone();
+                return false;
+            }
+        }
+
+        state.suppressions.insert(
+            key,
+            Suppression {
+                last_sent: now,
+                suppressed: 0,
+                since: now,
+                last_event: event.clone(),
+            },
+        );
+        true
+    }
+
+    /// Counts a notification against the rate limit of a sender.
+    ///
+    /// # Returns
+    ///
+    /// `bool` - Whether the sender is within its rate limit and should send the notification.
+    pub fn allow_sender(&self, sender_name: &str) -> bool {
+        let Some(mut state) = self.lock_state() else {
+            return true;
+        };
+        let Some(rate_limit) = state.rate_limits.get_mut(sender_name) else {
+            return true;
+        };
+
+        let now = Utc::now();
+        while rate_limit
+            .sent
+            .front()
+            .is_some_and(|sent_at| now - *sent_at >= rate_limit.period)
+        {
+            rate_limit.sent.pop_front();
+        }
+        if rate_limit.sent.len() < rate_limit.max_notifications {
+            rate_limit.sent.push_back(now);
+            return true;
+        }
+
+        if rate_limit.dropped == 0 {
+            rate_limit.dropped_since = now;
+        }
+        rate_limit.dropped += 1;
+        false
+    }
+
+    /// Returns a digest of every notification with suppressed repeats, and forgets
+    /// notifications whose deduplication window is over.
+    pub fn take_digests(&self) -> Vec<NotificationEvent> {
+        let Some(mut state) = self.lock_state() else {
+            return Vec::new();
+        };
+        let now = Utc::now();
+        let dedup_window = self.dedup_window;
+
+        let mut digests = Vec::new();
+        state.suppressions.retain(|_, suppression| {
+            if suppression.suppressed == 0 {
+                return now - suppression.last_sent < dedup_window;
+            }
+            digests.push(digest_event(suppression));
+            suppression.suppressed = 0;
+            suppression.last_sent = now;
+            true
+        });
+        digests
+    }
+
+    /// Returns a digest for every sender that left out notifications because of its rate
+    /// limit, together with the name of the sender.
+    pub fn take_rate_limit_digests(&self) -> Vec<(String, NotificationEvent)> {
+        let Some(mut state) = self.lock_state() else {
+            return Vec::new();
+        };
+
+        state
+            .rate_limits
+            .iter_mut()
+            .filter(|(_, rate_limit)| rate_limit.dropped > 0)
+            .map(|(sender_name, rate_limit)| {
+                let event = NotificationEvent::new(
+                    NotificationKind::Digest,
+                    Severity::Warning,
+                    format!("Gamayun Digest: rate limit of {}", sender_name),
+                    format!(
+                        "{} notifications were not sent through {} since {} UTC, as it reached its limit of {} notifications in {} seconds.",
+                        rate_limit.dropped,
+                        sender_name,
+                        rate_limit.dropped_since.format("%Y-%m-%d %H:%M"),
+                        rate_limit.max_notifications,
+                        rate_limit.period.num_seconds()
+                    ),
+                );
+                rate_limit.dropped = 0;
+                (sender_name.clone(), event)
+            })
+            .collect()
+    }
+
+    /// Records that a run of a job succeeded. Suppressed repeats of notifications about the job
+    /// are forgotten, as the job recovered.
+    ///
+    /// # Returns
+    ///
+    /// `Option<NotificationEvent>` - A notification that the job recovered, if it failed
+    /// before and recovery notifications are enabled.
+    pub fn record_success(&self, job_name: &str, run_id: &str) -> Option<NotificationEvent> {
+        let mut state = self.lock_state()?;
+        let failing_job = state.failing_jobs.remove(job_name)?;
+        state
+            .suppressions
+            .retain(|(suppressed_job, _, _), _| suppressed_job.as_deref() != Some(job_name));
+
+        if !self.notify_recovery {
+            return None;
+        }
+        Some(
+            NotificationEvent::new(
+                NotificationKind::Recovered,
+                Severity::Info,
+                format!("Gamayun Recovered: job {}", job_name),
+                format!(
+                    "Job {} succeeded with run ID {} after {} failures since {} UTC.",
+                    job_name,
+                    run_id,
+                    failing_job.failures,
+                    failing_job.since.format("%Y-%m-%d %H:%M")
+                ),
+            )
+            .for_run(job_name, run_id, failing_job.tags),
+        )
+    }
+
+    fn lock_state(&self) -> Option<std::sync::MutexGuard<'_, ThrottleState>> {
+        match self.state.lock() {
+            Ok(state) => Some(state),
+            Err(e) => {
+                error!("Failed to lock notification throttling state: {:?}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Summarizes the suppressed repeats of a notification, such as "job X failed 57 times since
+/// 10:00".
+fn digest_event(suppression: &Suppression) -> NotificationEvent {
+    let last_event = &suppression.last_event;
+    let subject = match &last_event.job_name {
+        Some(job_name) => format!("Job {}", job_name),
+        None => last_event.kind.label().to_string(),
+    };
+    let (what_happened, last_one) = match last_event.kind {
+        NotificationKind::JobError => ("failed", "last error"),
+        NotificationKind::InvalidResults => ("reported invalid results", "last one"),
+        NotificationKind::Overdue => ("was overdue", "last one"),
+        NotificationKind::NoWorker => ("found no worker", "last one"),
+        _ => ("happened", "last one"),
+    };
+
+    NotificationEvent {
+        title: format!("Gamayun Digest: {}", last_event.title),
+        details: format!(
+            "{} {} {} more times since {} UTC, {}:\n\n{}",
+            subject,
+            what_happened,
+            suppression.suppressed,
+            suppression.since.format("%Y-%m-%d %H:%M"),
+            last_one,
+            last_event.details
+        ),
+        timestamp: Utc::now(),
+        ..last_event.clone()
+    }
+}
@@ -0,0 +1,241 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// What happened that a notification is sent about.
+///
+/// Configurations name kinds in PascalCase, JSON events in snake_case, which is read as well
+/// so stored notifications can be loaded again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all(serialize = "snake_case"))]
+pub enum NotificationKind {
+    /// A run reported an error.
+    #[serde(alias = "job_error")]
+    JobError,
+    /// Results of a run didn't match the result schema of the job.
+    #[serde(alias = "invalid_results")]
+    InvalidResults,
+    /// A run didn't report its outcome in time.
+    #[serde(alias = "overdue")]
+    Overdue,
+    /// A run couldn't be started, as no connected worker matches the job.
+    #[serde(alias = "no_worker")]
+    NoWorker,
+    /// A worker stopped sending heartbeats.
+    #[serde(alias = "crash")]
+    Crash,
+    /// Reloading the job configuration or the TLS certificates failed.
+    #[serde(alias = "reload_failure")]
+    ReloadFailure,
+    /// Gamayun failed to start.
+    #[serde(alias = "startup_failure")]
+    StartupFailure,
+    /// A run reported new or changed results.
+    #[serde(alias = "new_results")]
+    NewResults,
+    /// A job succeeded after it failed.
+    #[serde(alias = "recovered")]
+    Recovered,
+    /// Summary of notifications that were not sent because of a rate limit.
+    #[serde(alias = "digest")]
+    Digest,
+}
+
+impl NotificationKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            NotificationKind::JobError => "Job error",
+            NotificationKind::InvalidResults => "Invalid results",
+            NotificationKind::Overdue => "Overdue run",
+            NotificationKind::NoWorker => "No worker",
+            NotificationKind::Crash => "Crash",
+            NotificationKind::ReloadFailure => "Reload failure",
+            NotificationKind::StartupFailure => "Startup failure",
+            NotificationKind::NewResults => "New results",
+            NotificationKind::Recovered => "Recovered",
+            NotificationKind::Digest => "Digest",
+        }
+    }
+}
+
+/// How urgently a notification needs attention, named like `NotificationKind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all(serialize = "snake_case"))]
+pub enum Severity {
+    /// Needs no action, such as new results.
+    #[serde(alias = "info")]
+    Info,
+    /// Expected to resolve by itself, such as rate limiting or an error that is retried.
+    #[serde(alias = "warning")]
+    Warning,
+    #[serde(alias = "error")]
+    Error,
+    /// Needs manual intervention, such as expired credentials.
+    #[serde(alias = "critical")]
+    Critical,
+}
+
+impl Severity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Severity::Info => "Info",
+            Severity::Warning => "Warning",
+            Severity::Error => "Error",
+            Severity::Critical => "Critical",
+        }
+    }
+}
+
+/// An error a run reported, available to notification templates.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventError {
+    pub message: String,
+    /// Category of the error, such as `network`.
+    pub category: String,
+    pub retryable: bool,
+    pub stack_trace: Option<String>,
+    pub context: HashMap<String, String>,
+}
+
+/// A notification, rendered by each `NotificationSender` in the layout of its channel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationEvent {
+    pub kind: NotificationKind,
+    pub severity: Severity,
+    /// The job the notification is about, if any.
+    pub job_name: Option<String>,
+    /// The run the notification is about, if any.
+    pub run_id: Option<String>,
+    /// Tags of the job, from its configuration.
+    pub tags: Vec<String>,
+    pub timestamp: DateTime<Utc>,
+    /// Short summary, such as the subject of an e-mail.
+    pub title: String,
+    /// Description of what happened, in plain text.
+    pub details: String,
+    /// Description of what happened as an HTML document, for e-mail senders.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub html_details: Option<String>,
+    /// The error the run reported, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<EventError>,
+    /// Attempt of the run, 1 for the scheduled run and higher for retries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attempt: Option<u32>,
+    /// When the run was started.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+    /// New and changed results the notification lists, available to notification templates.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub items: Vec<serde_json::Value>,
+    /// Identifies repeats of the same problem, the title is used when not set.
+    #[serde(skip)]
+    pub fingerprint: Option<String>,
+}
+
+impl NotificationEvent {
+    /// Creates a new `NotificationEvent` that is not about a run of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - What happened.
+    /// * `severity` - How urgently the notification needs attention.
+    /// * `title` - Short summary of the notification.
+    /// * `details` - Description of what happened, in plain text.
+    pub fn new(kind: NotificationKind, severity: Severity, title: String, details: String) -> Self {
+        NotificationEvent {
+            kind,
+            severity,
+            job_name: None,
+            run_id: None,
+            tags: Vec::new(),
+            timestamp: Utc::now(),
+            title,
+            details,
+            html_details: None,
+            error: None,
+            attempt: None,
+            started_at: None,
+            items: Vec::new(),
+            fingerprint: None,
+        }
+    }
+
+    /// Sets the run of a job the notification is about.
+    ///
+    /// # Arguments
+    ///
+    /// * `job_name` - Name of the job.
+    /// * `run_id` - ID of the run.
+    /// * `tags` - Tags of the job.
+    pub fn for_run(
+        mut self,
+        job_name: impl Into<String>,
+        run_id: impl Into<String>,
+        tags: Vec<String>,
+    ) -> Self {
+        self.job_name = Some(job_name.into());
+        self.run_id = Some(run_id.into());
+        self.tags = tags;
+        self
+    }
+
+    /// Sets the attempt of the run and when it was started.
+    ///
+    /// # Arguments
+    ///
+    /// * `attempt` - 1 for the scheduled run, higher for retries.
+    /// * `started_at` - When the run was started.
+    pub fn with_run_timing(mut self, attempt: u32, started_at: DateTime<Utc>) -> Self {
+        self.attempt = Some(attempt);
+        self.started_at = Some(started_at);
+        self
+    }
+
+    /// Sets the error the run reported.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The reported error.
+    pub fn with_error(mut self, error: EventError) -> Self {
+        self.error = Some(error);
+        self
+    }
+
+    /// Sets the results the notification lists.
+    ///
+    /// # Arguments
+    ///
+    /// * `items` - The results, each with its `status`, `fields` and `previous` values.
+    pub fn with_items(mut self, items: Vec<serde_json::Value>) -> Self {
+        self.items = items;
+        self
+    }
+
+    /// Sets what identifies repeats of the same problem, such as the category and message of
+    /// an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `fingerprint` - Equal for notifications about the same problem.
+    pub fn with_fingerprint(mut self, fingerprint: impl Into<String>) -> Self {
+        self.fingerprint = Some(fingerprint.into());
+        self
+    }
+
+    /// Returns what identifies repeats of the same problem.
+    pub fn fingerprint(&self) -> &str {
+        self.fingerprint.as_deref().unwrap_or(&self.title)
+    }
+
+    /// Renders the notification as a plain text title and body, for channels without a
+    /// layout of their own, such as e-mail.
+    pub fn render_text(&self) -> (String, String) {
+        (self.title.clone(), self.details.clone())
+    }
+
+    /// Renders the body of the notification as HTML, if a template rendered one.
+    pub fn render_html(&self) -> Option<String> {
+        self.html_details.clone()
+    }
+}
@@ -1 +1,2 @@
 pub(crate) mod sendgrid_notification_sender;
+pub(crate) mod smtp_notification_sender;
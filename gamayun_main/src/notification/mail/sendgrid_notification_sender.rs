@@ -1,7 +1,9 @@
-use crate::notification::NotificationSender;
+use crate::notification::event::NotificationEvent;
+use crate::notification::{http_client_builder, DeliveryError, NotificationSender};
+use anyhow::{Context, Result};
 use async_trait::async_trait;
 use reqwest::Client;
-use tracing::{error, info, instrument};
+use tracing::{info, instrument};
 
 /// Configuration struct for SendGrid.
 pub struct SendGridConfiguration {
@@ -22,34 +24,48 @@ impl SendGridNotificationSender {
     /// # Arguments
     ///
     /// * `config` - A `SendGridConfiguration` containing the "from" email and recipient emails.
-    pub fn new(config: SendGridConfiguration) -> Self {
-        SendGridNotificationSender {
-            config,
-            client: Client::new(),
-        }
+    ///
+    /// # Returns
+    ///
+    /// The sender, or an error if the HTTP client can't be created.
+    pub fn new(config: SendGridConfiguration) -> Result<Self> {
+        let client = http_client_builder()
+            .build()
+            .context("Failed to create the SendGrid HTTP client")?;
+        Ok(SendGridNotificationSender { config, client })
     }
-}
 
-#[async_trait]
-impl NotificationSender for SendGridNotificationSender {
-    #[instrument(skip(self, message_contents))]
-    async fn notify(&self, message_title: String, message_contents: String) {
+    async fn send(
+        &self,
+        event: NotificationEvent,
+        to_emails: &[String],
+    ) -> Result<(), DeliveryError> {
+        let (message_title, message_contents) = event.render_text();
         info!(
             "Sending notification via SendGrid with title {}",
             &message_title
         );
         let url = "https://api.sendgrid.com/v3/mail/send";
 
+        // SendGrid requires the plain text content to come first
+        let mut content = vec![serde_json::json!({
+            "type": "text/plain",
+            "value": message_contents,
+        })];
+        if let Some(html_contents) = event.render_html() {
+            content.push(serde_json::json!({
+                "type": "text/html",
+                "value": html_contents,
+            }));
+        }
+
         let body = serde_json::json!({
             "personalizations": [{
-                "to": self.config.to_emails.iter().map(|email| serde_json::json!({ "email": email })).collect::<Vec<_>>(),
+                "to": to_emails.iter().map(|email| serde_json::json!({ "email": email })).collect::<Vec<_>>(),
                 "subject": message_title,
             }],
             "from": { "email": self.config.from_email },
-            "content": [{
-                "type": "text/plain",
-                "value": message_contents,
-            }],
+            "content": content,
         });
 
         let response = self
@@ -59,10 +75,33 @@ impl NotificationSender for SendGridNotificationSender {
             .header("Content-Type", "application/json")
             .json(&body)
             .send()
-            .await;
+            .await
+            .map_err(|e| DeliveryError::Transient(format!("{:?}", e)))?;
 
-        if let Err(e) = response {
-            error!("Failed to send notification via SendGrid: {:?}", e);
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
         }
+        Err(DeliveryError::from_status(
+            status,
+            response.text().await.unwrap_or_default(),
+        ))
+    }
+}
+
+#[async_trait]
+impl NotificationSender for SendGridNotificationSender {
+    #[instrument(skip(self, event), fields(kind = ?event.kind, job_name = ?event.job_name))]
+    async fn notify(&self, event: NotificationEvent) -> Result<(), DeliveryError> {
+        self.send(event, &self.config.to_emails).await
+    }
+
+    #[instrument(skip(self, event, recipients), fields(kind = ?event.kind, job_name = ?event.job_name))]
+    async fn notify_recipients(
+        &self,
+        event: NotificationEvent,
+        recipients: Vec<String>,
+    ) -> Result<(), DeliveryError> {
+        self.send(event, &recipients).await
     }
 }
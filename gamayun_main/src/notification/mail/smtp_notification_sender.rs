@@ -0,0 +1,151 @@
+use crate::config::app_config::SmtpTlsMode;
+use crate::notification::event::NotificationEvent;
+use crate::notification::{DeliveryError, NotificationSender};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use lettre::message::header::ContentType;
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use tracing::{info, instrument};
+
+/// Configuration struct for SMTP.
+pub struct SmtpConfiguration {
+    pub host: String,
+    /// Port of the server, defaults to 25, 587 or 465 depending on `tls`.
+    pub port: Option<u16>,
+    pub tls: SmtpTlsMode,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from_email: String,
+    pub to_emails: Vec<String>,
+}
+
+/// Implementation of `NotificationSender` sending e-mails through an SMTP server.
+pub struct SmtpNotificationSender {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl SmtpNotificationSender {
+    /// Creates a new `SmtpNotificationSender` with a configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - A `SmtpConfiguration` with the server, credentials and e-mail addresses.
+    ///
+    /// # Returns
+    ///
+    /// The sender, or an error if an address can't be parsed or the TLS setup fails.
+    pub fn new(config: SmtpConfiguration) -> Result<Self> {
+        let mut builder = match config.tls {
+            SmtpTlsMode::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)
+            }
+            SmtpTlsMode::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+                    .context("Failed to set up STARTTLS for the SMTP server")?
+            }
+            SmtpTlsMode::Implicit => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)
+                .context("Failed to set up TLS for the SMTP server")?,
+        };
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+        if let Some(username) = config.username {
+            builder = builder.credentials(Credentials::new(
+                username,
+                config.password.unwrap_or_default(),
+            ));
+        }
+
+        let from = config
+            .from_email
+            .parse()
+            .with_context(|| format!("Invalid SMTP from address {}", config.from_email))?;
+        let to = parse_recipients(&config.to_emails)?;
+
+        Ok(SmtpNotificationSender {
+            transport: builder.build(),
+            from,
+            to,
+        })
+    }
+
+    fn build_message(
+        &self,
+        to: &[Mailbox],
+        message_title: String,
+        message_contents: String,
+        html_contents: Option<String>,
+    ) -> Result<Message> {
+        let mut builder = Message::builder()
+            .from(self.from.clone())
+            .subject(message_title);
+        for recipient in to {
+            builder = builder.to(recipient.clone());
+        }
+        match html_contents {
+            // Mail clients that can't show HTML fall back to the plain text part
+            Some(html_contents) => builder.multipart(MultiPart::alternative_plain_html(
+                message_contents,
+                html_contents,
+            )),
+            None => builder
+                .header(ContentType::TEXT_PLAIN)
+                .body(message_contents),
+        }
+        .context("Failed to build e-mail")
+    }
+
+    async fn send(&self, event: NotificationEvent, to: &[Mailbox]) -> Result<(), DeliveryError> {
+        let (message_title, message_contents) = event.render_text();
+        let html_contents = event.render_html();
+        info!(
+            "Sending notification via SMTP with title {}",
+            &message_title
+        );
+
+        let message = self
+            .build_message(to, message_title, message_contents, html_contents)
+            .map_err(|e| DeliveryError::Permanent(format!("{:?}", e)))?;
+
+        match self.transport.send(message).await {
+            Ok(_) => Ok(()),
+            // 5xx replies won't change on a retry, 4xx replies and connection errors can
+            Err(e) if e.is_permanent() => Err(DeliveryError::Permanent(e.to_string())),
+            Err(e) => Err(DeliveryError::Transient(e.to_string())),
+        }
+    }
+}
+
+fn parse_recipients(emails: &[String]) -> Result<Vec<Mailbox>> {
+    emails
+        .iter()
+        .map(|email| {
+            email
+                .parse()
+                .with_context(|| format!("Invalid SMTP recipient address {}", email))
+        })
+        .collect()
+}
+
+#[async_trait]
+impl NotificationSender for SmtpNotificationSender {
+    #[instrument(skip(self, event), fields(kind = ?event.kind, job_name = ?event.job_name))]
+    async fn notify(&self, event: NotificationEvent) -> Result<(), DeliveryError> {
+        self.send(event, &self.to).await
+    }
+
+    #[instrument(skip(self, event, recipients), fields(kind = ?event.kind, job_name = ?event.job_name))]
+    async fn notify_recipients(
+        &self,
+        event: NotificationEvent,
+        recipients: Vec<String>,
+    ) -> Result<(), DeliveryError> {
+        let to = parse_recipients(&recipients)
+            .map_err(|e| DeliveryError::Permanent(format!("{:?}", e)))?;
+        self.send(event, &to).await
+    }
+}
@@ -1,16 +1,95 @@
+pub(crate) mod chat;
 pub(crate) mod composite_notification_sender;
+pub(crate) mod event;
 pub(crate) mod mail;
+pub(crate) mod push;
+pub(crate) mod template;
+pub(crate) mod webhook;
 
+use crate::notification::event::NotificationEvent;
 use async_trait::async_trait;
+use reqwest::{Client, ClientBuilder, StatusCode};
+use std::fmt;
+use std::time::Duration;
+
+/// Timeout of a request to a notification service, so a service that doesn't answer can't
+/// keep a notification in flight.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Starts building the HTTP client of a sender, with the request timeout.
+pub(crate) fn http_client_builder() -> ClientBuilder {
+    Client::builder().timeout(HTTP_TIMEOUT)
+}
+
+/// Why a notification couldn't be delivered.
+#[derive(Debug, Clone)]
+pub enum DeliveryError {
+    /// Worth retrying, such as a timeout or a 5xx response.
+    Transient(String),
+    /// Won't succeed on a retry, such as a 4xx response.
+    Permanent(String),
+}
+
+impl DeliveryError {
+    /// Classifies an error response of an HTTP API: server errors and rate limiting are
+    /// transient, other errors permanent.
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - Status of the response.
+    /// * `body` - Body of the response, usually describing the error.
+    pub fn from_status(status: StatusCode, body: String) -> Self {
+        let reason = format!("status {}: {}", status, body);
+        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            DeliveryError::Transient(reason)
+        } else {
+            DeliveryError::Permanent(reason)
+        }
+    }
+
+    /// Whether the delivery should be retried.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, DeliveryError::Transient(_))
+    }
+}
+
+impl fmt::Display for DeliveryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeliveryError::Transient(reason) | DeliveryError::Permanent(reason) => {
+                write!(f, "{}", reason)
+            }
+        }
+    }
+}
 
 /// Defines a trait for sending notifications asynchronously.
 #[async_trait]
 pub trait NotificationSender: Send + Sync {
-    /// Sends a notification with the given title and contents.
+    /// Sends a notification, formatted in the layout of the sender's channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - A `NotificationEvent` describing what happened.
+    ///
+    /// # Returns
+    ///
+    /// `Result<(), DeliveryError>` - Whether the notification was accepted by the channel.
+    async fn notify(&self, event: NotificationEvent) -> Result<(), DeliveryError>;
+
+    /// Sends a notification to other recipients than the configured ones. Senders without
+    /// recipients of their own, such as chat webhooks, send it as usual.
     ///
     /// # Arguments
     ///
-    /// * `message_title` - A `String` containing the notification title.
-    /// * `message_contents` - A `String` containing the notification message.
-    async fn notify(&self, message_title: String, message_contents: String);
+    /// * `event` - A `NotificationEvent` describing what happened.
+    /// * `recipients` - E-mail addresses the notification is sent to.
+    async fn notify_recipients(
+        &self,
+        event: NotificationEvent,
+        recipients: Vec<String>,
+    ) -> Result<(), DeliveryError> {
+        let _ = recipients;
+        self.notify(event).await
+    }
 }
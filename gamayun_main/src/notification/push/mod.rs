@@ -0,0 +1,2 @@
+pub(crate) mod ntfy_notification_sender;
+pub(crate) mod telegram_notification_sender;
@@ -0,0 +1,113 @@
+use crate::notification::chat::post_json;
+use crate::notification::event::{NotificationEvent, Severity};
+use crate::notification::{http_client_builder, DeliveryError, NotificationSender};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::Client;
+use serde_json::json;
+use tracing::{info, instrument};
+use url::Url;
+
+const DEFAULT_SERVER_URL: &str = "https://ntfy.sh";
+
+/// Configuration struct for ntfy.
+pub struct NtfyConfiguration {
+    /// Base URL of the ntfy server, defaults to `https://ntfy.sh`.
+    pub server_url: Option<String>,
+    pub topic: String,
+    /// Priority from 1 (min) to 5 (max) of all notifications, picked by the severity of each
+    /// notification when not set.
+    pub priority: Option<u8>,
+    /// Access token for topics that require authentication.
+    pub access_token: Option<String>,
+}
+
+/// Implementation of `NotificationSender` publishing to an ntfy topic. The tags of the job a
+/// notification is about are sent as ntfy tags.
+pub struct NtfyNotificationSender {
+    server_url: Url,
+    topic: String,
+    priority: Option<u8>,
+    client: Client,
+}
+
+impl NtfyNotificationSender {
+    /// Creates a new `NtfyNotificationSender` with a configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - A `NtfyConfiguration` with the server and topic to publish to.
+    ///
+    /// # Returns
+    ///
+    /// The sender, or an error if the server URL or access token is invalid.
+    pub fn new(config: NtfyConfiguration) -> Result<Self> {
+        let server_url = config.server_url.as_deref().unwrap_or(DEFAULT_SERVER_URL);
+        let server_url = Url::parse(server_url)
+            .with_context(|| format!("Invalid ntfy server URL {}", server_url))?;
+
+        let mut headers = HeaderMap::new();
+        if let Some(access_token) = config.access_token {
+            headers.insert(
+                AUTHORIZATION,
+                HeaderValue::try_from(format!("Bearer {}", access_token))
+                    .context("Invalid ntfy access token")?,
+            );
+        }
+        let client = http_client_builder()
+            .default_headers(headers)
+            .build()
+            .context("Failed to create the ntfy HTTP client")?;
+
+        Ok(NtfyNotificationSender {
+            server_url,
+            topic: config.topic,
+            priority: config.priority.map(|priority| priority.clamp(1, 5)),
+            client,
+        })
+    }
+
+    async fn publish(&self, event: &NotificationEvent) -> Result<(), DeliveryError> {
+        let mut message = event.details.clone();
+        if let Some(job_name) = &event.job_name {
+            message.push_str(&format!("\n\nJob: {}", job_name));
+        }
+        if let Some(run_id) = &event.run_id {
+            message.push_str(&format!("\nRun: {}", run_id));
+        }
+        let priority = self
+            .priority
+            .unwrap_or_else(|| severity_priority(event.severity));
+
+        // JSON messages are published to the root of the server, with the topic in the body
+        let payload = json!({
+            "topic": self.topic,
+            "title": event.title,
+            "message": message,
+            "priority": priority,
+            "tags": event.tags,
+        });
+        post_json(&self.client, self.server_url.clone(), &payload).await
+    }
+}
+
+/// ntfy priority of a notification: critical ones get the max priority, which keeps
+/// vibrating, errors the high priority, which pops up the notification.
+fn severity_priority(severity: Severity) -> u8 {
+    match severity {
+        Severity::Info => 2,
+        Severity::Warning => 3,
+        Severity::Error => 4,
+        Severity::Critical => 5,
+    }
+}
+
+#[async_trait]
+impl NotificationSender for NtfyNotificationSender {
+    #[instrument(skip(self, event), fields(kind = ?event.kind, job_name = ?event.job_name))]
+    async fn notify(&self, event: NotificationEvent) -> Result<(), DeliveryError> {
+        info!("Sending notification via ntfy with title {}", &event.title);
+        self.publish(&event).await
+    }
+}
@@ -0,0 +1,132 @@
+use crate::notification::chat::post_json;
+use crate::notification::event::NotificationEvent;
+use crate::notification::{http_client_builder, DeliveryError, NotificationSender};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::json;
+use tracing::{info, instrument};
+use url::Url;
+
+const DEFAULT_API_URL: &str = "https://api.telegram.org";
+/// Telegram rejects messages longer than 4096 characters. The limits apply to the escaped
+/// text and leave room for the job and run lines.
+const MAX_TITLE_LENGTH: usize = 256;
+const MAX_CONTENTS_LENGTH: usize = 3500;
+const MARKDOWN_SPECIAL_CHARACTERS: &str = "_*[]()~`>#+-=|{}.!\\";
+
+/// Configuration struct for Telegram.
+pub struct TelegramConfiguration {
+    pub bot_token: String,
+    /// Chat the bot posts to, a numeric ID or `@channelname`.
+    pub chat_id: String,
+    /// Base URL of the Bot API, defaults to `https://api.telegram.org`.
+    pub api_url: Option<String>,
+}
+
+/// Implementation of `NotificationSender` sending messages to a chat through a Telegram bot.
+pub struct TelegramNotificationSender {
+    send_message_url: Url,
+    chat_id: String,
+    client: Client,
+}
+
+impl TelegramNotificationSender {
+    /// Creates a new `TelegramNotificationSender` with a configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - A `TelegramConfiguration` with the bot token and the chat to post to.
+    ///
+    /// # Returns
+    ///
+    /// The sender, or an error if the API URL can't be parsed.
+    pub fn new(config: TelegramConfiguration) -> Result<Self> {
+        let api_url = config.api_url.as_deref().unwrap_or(DEFAULT_API_URL);
+        let send_message_url = Url::parse(&format!(
+            "{}/bot{}/sendMessage",
+            api_url.trim_end_matches('/'),
+            config.bot_token
+        ))
+        .with_context(|| format!("Invalid Telegram API URL {}", api_url))?;
+
+        let client = http_client_builder()
+            .build()
+            .context("Failed to create the Telegram HTTP client")?;
+
+        Ok(TelegramNotificationSender {
+            send_message_url,
+            chat_id: config.chat_id,
+            client,
+        })
+    }
+
+    fn build_text(event: &NotificationEvent) -> String {
+        let mut text = format!(
+            "*{}*\n\n{}",
+            escape_markdown(&event.title, MAX_TITLE_LENGTH),
+            escape_markdown(&event.details, MAX_CONTENTS_LENGTH)
+        );
+        if let Some(job_name) = &event.job_name {
+            text.push_str(&format!("\n\nJob: `{}`", escape_code(job_name)));
+        }
+        if let Some(run_id) = &event.run_id {
+            text.push_str(&format!("\nRun: `{}`", escape_code(run_id)));
+        }
+        text
+    }
+
+    async fn send(&self, text: String) -> Result<(), DeliveryError> {
+        let payload = json!({
+            "chat_id": self.chat_id,
+            "text": text,
+            "parse_mode": "MarkdownV2",
+            "disable_web_page_preview": true,
+        });
+        post_json(&self.client, self.send_message_url.clone(), &payload).await
+    }
+}
+
+/// Escapes the characters that have a meaning in Telegram's MarkdownV2, and shortens the
+/// escaped text to `max_chars` characters without splitting an escape sequence.
+fn escape_markdown(text: &str, max_chars: usize) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut escaped_chars = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let width = if MARKDOWN_SPECIAL_CHARACTERS.contains(c) {
+            2
+        } else {
+            1
+        };
+        // Keep room for the ellipsis unless this is the last character
+        let reserved = if chars.peek().is_some() { 1 } else { 0 };
+        if escaped_chars + width + reserved > max_chars {
+            escaped.push('…');
+            break;
+        }
+        if width == 2 {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+        escaped_chars += width;
+    }
+    escaped
+}
+
+/// Escapes text placed in a MarkdownV2 code span, where only `` ` `` and `\` are special.
+fn escape_code(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('`', "\\`")
+}
+
+#[async_trait]
+impl NotificationSender for TelegramNotificationSender {
+    #[instrument(skip(self, event), fields(kind = ?event.kind, job_name = ?event.job_name))]
+    async fn notify(&self, event: NotificationEvent) -> Result<(), DeliveryError> {
+        info!(
+            "Sending notification via Telegram with title {}",
+            &event.title
+        );
+        self.send(Self::build_text(&event)).await
+    }
+}
@@ -0,0 +1,245 @@
+use crate::config::app_config::NotificationTemplatesConfig;
+use crate::notification::event::NotificationEvent;
+use handlebars::Handlebars;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+use tracing::{error, info};
+
+const DEFAULT_TEMPLATE_DIRECTORY: &str = "notification_templates";
+const TEMPLATE_EXTENSION: &str = ".hbs";
+
+/// Built-in templates, used when the template directory has no template for a notification.
+const DEFAULT_SUBJECT_TEMPLATE: &str = "{{title}}";
+const DEFAULT_TEXT_TEMPLATE: &str =
+    "{{details}}{{#if links.run}}\n\nRun details: {{links.run}}{{/if}}";
+const DEFAULT_HTML_TEMPLATE: &str = include_str!("templates/default.html.hbs");
+
+/// Parts of a notification rendered from a template.
+#[derive(Clone, Copy)]
+enum Part {
+    Subject,
+    Text,
+    Html,
+}
+
+impl Part {
+    fn name(&self) -> &'static str {
+        match self {
+            Part::Subject => "subject",
+            Part::Text => "text",
+            Part::Html => "html",
+        }
+    }
+}
+
+/// Handlebars templates of the notification subjects and bodies.
+///
+/// Templates are loaded from the template directory, named after the part they render:
+/// `default.subject.hbs`, `default.text.hbs` and `default.html.hbs` apply to all
+/// notifications, `<kind>.<part>.hbs` (such as `job_error.text.hbs`) to notifications of a
+/// kind, and `jobs/<job name>/<kind or default>.<part>.hbs` to notifications about a job. The
+/// most specific template wins.
+pub struct NotificationTemplates {
+    /// Subject and text templates, rendered without HTML escaping.
+    text: Handlebars<'static>,
+    html: Handlebars<'static>,
+    api_base_url: Option<String>,
+}
+
+impl NotificationTemplates {
+    /// Loads the templates from the template directory in the configuration root. Invalid
+    /// templates are logged and left out.
+    ///
+    /// # Arguments
+    ///
+    /// * `config_root` - The configuration root directory.
+    /// * `config` - Where the templates are and the URL of the HTTP API.
+    pub fn load(config_root: &str, config: Option<&NotificationTemplatesConfig>) -> Self {
+        let mut text = Handlebars::new();
+        text.register_escape_fn(handlebars::no_escape);
+        let mut html = Handlebars::new();
+        register(&mut text, "default.subject", DEFAULT_SUBJECT_TEMPLATE);
+        register(&mut text, "default.text", DEFAULT_TEXT_TEMPLATE);
+        register(&mut html, "default.html", DEFAULT_HTML_TEMPLATE);
+
+        let directory = config
+            .and_then(|config| config.directory.as_deref())
+            .unwrap_or(DEFAULT_TEMPLATE_DIRECTORY);
+        let directory = Path::new(config_root).join(directory);
+        if directory.is_dir() {
+            let mut loaded = 0;
+            for (name, template) in read_templates(&directory, "") {
+                let registry = if name.ends_with(".html") {
+                    &mut html
+                } else {
+                    &mut text
+                };
+                if register(registry, &name, &template) {
+                    loaded += 1;
+                }
+            }
+            info!(
+                "Loaded {} notification templates from {}",
+                loaded,
+                directory.display()
+            );
+        }
+
+        NotificationTemplates {
+            text,
+            html,
+            api_base_url: config
+                .and_then(|config| config.api_base_url.clone())
+                .map(|url| url.trim_end_matches('/').to_string()),
+        }
+    }
+
+    /// Renders the subject and bodies of a notification. The texts the notification was
+    /// created with are available to the templates as `title` and `details`, and are kept if
+    /// a template fails to render.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The notification to render.
+    pub fn render(&self, mut event: NotificationEvent) -> NotificationEvent {
+        let context = self.context(&event);
+
+        if let Some(subject) = self.render_part(&self.text, Part::Subject, &event, &context) {
+            // Subjects are a single line in every channel
+            event.title = subject.lines().collect::<Vec<_>>().join(" ");
+        }
+        if let Some(text) = self.render_part(&self.text, Part::Text, &event, &context) {
+            event.details = text;
+        }
+        event.html_details = self.render_part(&self.html, Part::Html, &event, &context);
+        event
+    }
+
+    fn render_part(
+        &self,
+        registry: &Handlebars<'static>,
+        part: Part,
+        event: &NotificationEvent,
+        context: &Value,
+    ) -> Option<String> {
+        let kind = kind_name(event);
+        let mut candidates = Vec::new();
+        if let Some(job_name) = &event.job_name {
+            candidates.push(format!("jobs/{}/{}.{}", job_name, kind, part.name()));
+            candidates.push(format!("jobs/{}/default.{}", job_name, part.name()));
+        }
+        candidates.push(format!("{}.{}", kind, part.name()));
+        candidates.push(format!("default.{}", part.name()));
+
+        let name = candidates.iter().find(|name| registry.has_template(name))?;
+        match registry.render(name, context) {
+            Ok(rendered) => Some(rendered),
+            Err(e) => {
+                error!("Failed to render notification template {}: {:?}", name, e);
+                None
+            }
+        }
+    }
+
+    /// Variables available to the templates.
+    fn context(&self, event: &NotificationEvent) -> Value {
+        let job = event.job_name.as_ref().map(|job_name| {
+            json!({
+                "name": job_name,
+                "tags": event.tags,
+            })
+        });
+        let run = event.run_id.as_ref().map(|run_id| {
+            json!({
+                "id": run_id,
+                "attempt": event.attempt,
+                "started_at": event.started_at,
+                "duration_seconds": event
+                    .started_at
+                    .map(|started_at| (event.timestamp - started_at).num_seconds()),
+            })
+        });
+
+        let mut links = serde_json::Map::new();
+        if let (Some(api_base_url), Some(job_name)) = (&self.api_base_url, &event.job_name) {
+            let job_link = format!("{}/api/v1/jobs/{}", api_base_url, job_name);
+            if let Some(run_id) = &event.run_id {
+                links.insert(
+                    "run".to_string(),
+                    json!(format!("{}/runs/{}", job_link, run_id)),
+                );
+            }
+            links.insert("job".to_string(), json!(job_link));
+        }
+
+        json!({
+            "kind": kind_name(event),
+            "kind_label": event.kind.label(),
+            "severity": event.severity,
+            "severity_label": event.severity.label(),
+            "title": event.title,
+            "details": event.details,
+            "timestamp": event.timestamp,
+            "job": job,
+            "run": run,
+            "error": event.error,
+            "items": event.items,
+            "tags": event.tags,
+            "links": links,
+        })
+    }
+}
+
+/// Name of the kind of a notification in template file names, such as `job_error`.
+fn kind_name(event: &NotificationEvent) -> String {
+    serde_json::to_value(event.kind)
+        .ok()
+        .and_then(|kind| kind.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn register(registry: &mut Handlebars<'static>, name: &str, template: &str) -> bool {
+    match registry.register_template_string(name, template) {
+        Ok(()) => true,
+        Err(e) => {
+            error!("Ignoring invalid notification template {}: {}", name, e);
+            false
+        }
+    }
+}
+
+/// Reads the templates of a directory and its subdirectories, named by their path relative
+/// to the template directory without the extension, such as `jobs/scraper/default.text`.
+fn read_templates(directory: &Path, prefix: &str) -> Vec<(String, String)> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!(
+                "Failed to read notification templates from {}: {:?}",
+                directory.display(),
+                e
+            );
+            return Vec::new();
+        }
+    };
+
+    let mut templates = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if path.is_dir() {
+            templates.extend(read_templates(&path, &format!("{}{}/", prefix, file_name)));
+        } else if let Some(name) = file_name.strip_suffix(TEMPLATE_EXTENSION) {
+            match fs::read_to_string(&path) {
+                Ok(template) => templates.push((format!("{}{}", prefix, name), template)),
+                Err(e) => error!(
+                    "Failed to read notification template {}: {:?}",
+                    path.display(),
+                    e
+                ),
+            }
+        }
+    }
+    templates
+}
@@ -0,0 +1 @@
+pub(crate) mod webhook_notification_sender;
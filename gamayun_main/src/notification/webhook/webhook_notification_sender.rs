@@ -0,0 +1,162 @@
+use crate::notification::event::{NotificationEvent, NotificationKind, Severity};
+use crate::notification::{DeliveryError, NotificationSender};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::Client;
+use serde::Serialize;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::{info, instrument};
+
+const DEFAULT_SIGNATURE_HEADER: &str = "X-Gamayun-Signature";
+
+/// Configuration struct for an outbound webhook.
+pub struct WebhookConfiguration {
+    pub url: String,
+    /// Extra headers sent with every request, such as an API key of the receiving side.
+    pub headers: HashMap<String, String>,
+    /// Secret the payloads are signed with, they are sent unsigned when not set.
+    pub secret: Option<String>,
+    /// Header carrying the signature, defaults to `X-Gamayun-Signature`.
+    pub signature_header: Option<String>,
+    pub timeout: Duration,
+}
+
+/// The JSON event POSTed to the webhook.
+#[derive(Serialize)]
+struct WebhookEvent<'a> {
+    event_type: NotificationKind,
+    job_name: Option<&'a str>,
+    run_id: Option<&'a str>,
+    /// Tags of the job, empty for notifications not about a run.
+    tags: &'a [String],
+    severity: Severity,
+    title: &'a str,
+    body: &'a str,
+    timestamp: DateTime<Utc>,
+}
+
+impl<'a> From<&'a NotificationEvent> for WebhookEvent<'a> {
+    fn from(event: &'a NotificationEvent) -> Self {
+        WebhookEvent {
+            event_type: event.kind,
+            job_name: event.job_name.as_deref(),
+            run_id: event.run_id.as_deref(),
+            tags: &event.tags,
+            severity: event.severity,
+            title: &event.title,
+            body: &event.details,
+            timestamp: event.timestamp,
+        }
+    }
+}
+
+/// Implementation of `NotificationSender` POSTing notifications as JSON events to an HTTP
+/// endpoint, such as incident tooling.
+///
+/// When a secret is configured, the signature header (`X-Gamayun-Signature` by default)
+/// carries `sha256=<hex HMAC-SHA256 of the request body>`, so the receiver can check that the
+/// event comes from Gamayun.
+///
+/// A failed delivery is reported as is, the notification outbox retries it.
+pub struct WebhookNotificationSender {
+    url: String,
+    secret: Option<Vec<u8>>,
+    signature_header: HeaderName,
+    client: Client,
+}
+
+impl WebhookNotificationSender {
+    /// Creates a new `WebhookNotificationSender` with a configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - A `WebhookConfiguration` with the URL, headers, secret and timeout.
+    ///
+    /// # Returns
+    ///
+    /// The sender, or an error if a header is invalid.
+    pub fn new(config: WebhookConfiguration) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &config.headers {
+            headers.insert(
+                HeaderName::try_from(name.as_str())
+                    .with_context(|| format!("Invalid webhook header name {}", name))?,
+                HeaderValue::try_from(value.as_str())
+                    .with_context(|| format!("Invalid value of webhook header {}", name))?,
+            );
+        }
+        let signature_header = config
+            .signature_header
+            .as_deref()
+            .unwrap_or(DEFAULT_SIGNATURE_HEADER);
+        let signature_header = HeaderName::try_from(signature_header)
+            .with_context(|| format!("Invalid signature header name {}", signature_header))?;
+
+        let client = Client::builder()
+            .default_headers(headers)
+            .timeout(config.timeout)
+            .build()
+            .context("Failed to create the webhook HTTP client")?;
+
+        Ok(WebhookNotificationSender {
+            url: config.url,
+            secret: config.secret.map(String::into_bytes),
+            signature_header,
+            client,
+        })
+    }
+
+    fn sign(secret: &[u8], body: &[u8]) -> String {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+        mac.update(body);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    async fn deliver(&self, event: &WebhookEvent<'_>) -> Result<(), DeliveryError> {
+        let body = serde_json::to_vec(event).map_err(|e| {
+            DeliveryError::Permanent(format!("Failed to serialize webhook event: {:?}", e))
+        })?;
+        let signature = self.secret.as_ref().map(|secret| Self::sign(secret, &body));
+
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body);
+        if let Some(signature) = signature {
+            request = request.header(self.signature_header.clone(), signature);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| DeliveryError::Transient(e.to_string()))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+
+        Err(DeliveryError::from_status(
+            status,
+            response.text().await.unwrap_or_default(),
+        ))
+    }
+}
+
+#[async_trait]
+impl NotificationSender for WebhookNotificationSender {
+    #[instrument(skip(self, event), fields(kind = ?event.kind, job_name = ?event.job_name))]
+    async fn notify(&self, event: NotificationEvent) -> Result<(), DeliveryError> {
+        info!(
+            "Sending notification via webhook with title {}",
+            &event.title
+        );
+        self.deliver(&WebhookEvent::from(&event)).await
+    }
+}
@@ -0,0 +1,57 @@
+use anyhow::{Context, Result};
+use rustls::crypto::ring::sign::any_supported_type;
+use rustls::pki_types::CertificateDer;
+use rustls::sign::CertifiedKey;
+use rustls::RootCertStore;
+use std::fs::File;
+use std::io::BufReader;
+
+/// Loads the certificate chain and the private key from PEM files.
+///
+/// # Arguments
+///
+/// * `cert_path` - Path to the PEM file with the certificate chain.
+/// * `key_path` - Path to the PEM file with the private key.
+///
+/// # Returns
+///
+/// The `CertifiedKey` presented to clients or an error if the files can't be read or parsed.
+pub(super) fn load_certified_key(cert_path: &str, key_path: &str) -> Result<CertifiedKey> {
+    let certs = load_certificates(cert_path)?;
+
+    let mut reader = BufReader::new(
+        File::open(key_path).with_context(|| format!("Failed to open key file {}", key_path))?,
+    );
+    let key = rustls_pemfile::private_key(&mut reader)
+        .with_context(|| format!("Failed to parse key file {}", key_path))?
+        .with_context(|| format!("No private key found in {}", key_path))?;
+    let signing_key = any_supported_type(&key)
+        .with_context(|| format!("Unsupported private key type in {}", key_path))?;
+
+    Ok(CertifiedKey::new(certs, signing_key))
+}
+
+/// Loads the CA certificates client certificates are verified against.
+pub(super) fn load_root_store(ca_path: &str) -> Result<RootCertStore> {
+    let mut root_store = RootCertStore::empty();
+    for cert in load_certificates(ca_path)? {
+        root_store
+            .add(cert)
+            .with_context(|| format!("Invalid CA certificate in {}", ca_path))?;
+    }
+    Ok(root_store)
+}
+
+fn load_certificates(path: &str) -> Result<Vec<CertificateDer<'static>>> {
+    let mut reader = BufReader::new(
+        File::open(path).with_context(|| format!("Failed to open certificate file {}", path))?,
+    );
+    let certs = rustls_pemfile::certs(&mut reader)
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Failed to parse certificate file {}", path))?;
+
+    if certs.is_empty() {
+        anyhow::bail!("No certificates found in {}", path);
+    }
+    Ok(certs)
+}
@@ -0,0 +1,210 @@
+use crate::config::app_config::TlsConfig;
+use crate::notification::composite_notification_sender::CompositeNotificationSender;
+use crate::notification::event::{NotificationEvent, NotificationKind, Severity};
+use crate::tls::cert_loading::{load_certified_key, load_root_store};
+use crate::tls::reloadable::{ReloadableCertResolver, ReloadableClientCertVerifier};
+use anyhow::{Context, Result};
+use rustls::crypto::{ring, CryptoProvider};
+use rustls::server::danger::ClientCertVerifier;
+use rustls::server::WebPkiClientVerifier;
+use rustls::ServerConfig;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+mod cert_loading;
+mod reloadable;
+
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long to wait before accepting again after accepting a connection failed, doubled on
+/// every failure in a row up to the maximum. Errors such as running out of file descriptors
+/// would otherwise fail every accept right away.
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(10);
+const MAX_ACCEPT_ERROR_BACKOFF: Duration = Duration::from_secs(1);
+
+/// TLS setup shared by the gRPC and HTTP servers.
+///
+/// The server configurations built from it resolve the certificate and the client CA on every
+/// handshake, so `reload` rotates them for running servers without a restart.
+#[derive(Clone)]
+pub struct ReloadableTls {
+    tls_config: TlsConfig,
+    provider: Arc<CryptoProvider>,
+    cert_resolver: Arc<ReloadableCertResolver>,
+    client_cert_verifier: Option<Arc<ReloadableClientCertVerifier>>,
+}
+
+impl ReloadableTls {
+    /// Loads the certificate, the key and the optional client CA from the configured paths.
+    pub fn new(tls_config: TlsConfig) -> Result<Self> {
+        let provider = Arc::new(ring::default_provider());
+
+        let certified_key = load_certified_key(&tls_config.cert_path, &tls_config.key_path)?;
+        let client_cert_verifier = match &tls_config.client_ca_path {
+            Some(client_ca_path) => Some(Arc::new(ReloadableClientCertVerifier::new(
+                build_client_cert_verifier(client_ca_path, provider.clone())?,
+            ))),
+            None => None,
+        };
+
+        Ok(Self {
+            cert_resolver: Arc::new(ReloadableCertResolver::new(certified_key)),
+            client_cert_verifier,
+            provider,
+            tls_config,
+        })
+    }
+
+    /// Reads the certificate, the key and the client CA again from the configured paths.
+    ///
+    /// Everything is loaded before anything is replaced, so a failed reload keeps serving the
+    /// previous certificates.
+    pub fn reload(&self) -> Result<()> {
+        let certified_key =
+            load_certified_key(&self.tls_config.cert_path, &self.tls_config.key_path)?;
+        let client_cert_verifier =
+            match (&self.tls_config.client_ca_path, &self.client_cert_verifier) {
+                (Some(client_ca_path), Some(reloadable)) => Some((
+                    reloadable,
+                    build_client_cert_verifier(client_ca_path, self.provider.clone())?,
+                )),
+                _ => None,
+            };
+
+        self.cert_resolver.replace(certified_key);
+        if let Some((reloadable, verifier)) = client_cert_verifier {
+            reloadable.replace(verifier);
+        }
+
+        info!("TLS certificates reloaded");
+        Ok(())
+    }
+
+    /// Builds a server configuration backed by the reloadable certificate and client CA.
+    ///
+    /// # Arguments
+    ///
+    /// * `alpn_protocols` - The protocols offered during ALPN negotiation.
+    pub fn server_config(&self, alpn_protocols: Vec<Vec<u8>>) -> Result<ServerConfig> {
+        let builder = ServerConfig::builder_with_provider(self.provider.clone())
+            .with_safe_default_protocol_versions()
+            .context("Failed to select TLS protocol versions")?;
+
+        let builder = match &self.client_cert_verifier {
+            Some(verifier) => builder.with_client_cert_verifier(verifier.clone()),
+            None => builder.with_no_client_auth(),
+        };
+
+        let mut server_config = builder.with_cert_resolver(self.cert_resolver.clone());
+        server_config.alpn_protocols = alpn_protocols;
+        Ok(server_config)
+    }
+}
+
+fn build_client_cert_verifier(
+    client_ca_path: &str,
+    provider: Arc<CryptoProvider>,
+) -> Result<Arc<dyn ClientCertVerifier>> {
+    let root_store = load_root_store(client_ca_path)?;
+    WebPkiClientVerifier::builder_with_provider(Arc::new(root_store), provider)
+        .build()
+        .with_context(|| format!("Failed to build client verifier from {}", client_ca_path))
+}
+
+/// Accepts TCP connections and performs the TLS handshakes in the background, yielding the
+/// established TLS streams. Each handshake runs on its own task, so slow clients don't hold up
+/// the others.
+///
+/// # Arguments
+///
+/// * `listener` - The listener accepting the TCP connections.
+/// * `acceptor` - The acceptor performing the TLS handshakes.
+pub fn accept_tls_connections(
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+) -> ReceiverStream<io::Result<TlsStream<TcpStream>>> {
+    let (sender, receiver) = mpsc::channel(64);
+
+    tokio::spawn(async move {
+        let mut backoff = ACCEPT_ERROR_BACKOFF;
+        while !sender.is_closed() {
+            let (stream, peer_addr) = match listener.accept().await {
+                Ok(connection) => {
+                    backoff = ACCEPT_ERROR_BACKOFF;
+                    connection
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to accept TCP connection, retrying in {:?}: {:?}",
+                        backoff, e
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_ACCEPT_ERROR_BACKOFF);
+                    continue;
+                }
+            };
+
+            let acceptor = acceptor.clone();
+            let sender = sender.clone();
+            tokio::spawn(async move {
+                match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(tls_stream)) => {
+                        let _ = sender.send(Ok(tls_stream)).await;
+                    }
+                    Ok(Err(e)) => warn!("TLS handshake with {} failed: {:?}", peer_addr, e),
+                    Err(_) => warn!("TLS handshake with {} timed out", peer_addr),
+                }
+            });
+        }
+    });
+
+    ReceiverStream::new(receiver)
+}
+
+/// Reloads the TLS certificates whenever the process receives SIGHUP, until shutdown.
+#[cfg(unix)]
+pub async fn reload_on_sighup(
+    tls: ReloadableTls,
+    notification_sender: CompositeNotificationSender,
+    shutdown_token: CancellationToken,
+) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            error!(
+                "Failed to listen for SIGHUP, TLS certificates can't be reloaded: {:?}",
+                e
+            );
+            return;
+        }
+    };
+
+    loop {
+        tokio::select! {
+            _ = hangup.recv() => {
+                info!("Received SIGHUP, reloading TLS certificates");
+                if let Err(e) = tls.reload() {
+                    error!("Failed to reload TLS certificates: {:?}", e);
+                    notification_sender
+                        .notify(NotificationEvent::new(
+                            NotificationKind::ReloadFailure,
+                            Severity::Error,
+                            "Gamayun TLS Certificate Reload Failure".to_string(),
+                            format!("Failed to reload TLS certificates: {:?}", e),
+                        ))
+                        .await;
+                }
+            }
+            _ = shutdown_token.cancelled() => return,
+        }
+    }
+}
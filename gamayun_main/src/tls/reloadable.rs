@@ -0,0 +1,106 @@
+use rustls::client::danger::HandshakeSignatureValid;
+use rustls::pki_types::{CertificateDer, UnixTime};
+use rustls::server::danger::{ClientCertVerified, ClientCertVerifier};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use rustls::{DigitallySignedStruct, DistinguishedName, Error, SignatureScheme};
+use std::sync::{Arc, RwLock};
+
+/// Serves the current server certificate, which is swapped in place on reload so that running
+/// servers pick it up for new connections.
+#[derive(Debug)]
+pub(super) struct ReloadableCertResolver {
+    certified_key: RwLock<Arc<CertifiedKey>>,
+}
+
+impl ReloadableCertResolver {
+    pub(super) fn new(certified_key: CertifiedKey) -> Self {
+        Self {
+            certified_key: RwLock::new(Arc::new(certified_key)),
+        }
+    }
+
+    pub(super) fn replace(&self, certified_key: CertifiedKey) {
+        *self
+            .certified_key
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = Arc::new(certified_key);
+    }
+}
+
+impl ResolvesServerCert for ReloadableCertResolver {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(
+            self.certified_key
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+        )
+    }
+}
+
+/// Verifies client certificates against the current client CA, which is swapped in place on
+/// reload.
+///
+/// No CA names are sent as hints to clients, as they can change while a handshake borrows them.
+#[derive(Debug)]
+pub(super) struct ReloadableClientCertVerifier {
+    verifier: RwLock<Arc<dyn ClientCertVerifier>>,
+}
+
+impl ReloadableClientCertVerifier {
+    pub(super) fn new(verifier: Arc<dyn ClientCertVerifier>) -> Self {
+        Self {
+            verifier: RwLock::new(verifier),
+        }
+    }
+
+    pub(super) fn replace(&self, verifier: Arc<dyn ClientCertVerifier>) {
+        *self.verifier.write().unwrap_or_else(|e| e.into_inner()) = verifier;
+    }
+
+    fn current(&self) -> Arc<dyn ClientCertVerifier> {
+        self.verifier
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+impl ClientCertVerifier for ReloadableClientCertVerifier {
+    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+        &[]
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        now: UnixTime,
+    ) -> Result<ClientCertVerified, Error> {
+        self.current()
+            .verify_client_cert(end_entity, intermediates, now)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        self.current().verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        self.current().verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.current().supported_verify_schemes()
+    }
+}
@@ -0,0 +1,86 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use tonic::transport::{Certificate, ClientTlsConfig, Identity};
+
+/// Configuration of the worker, read from environment variables.
+#[derive(Debug, Clone)]
+pub struct WorkerConfig {
+    /// URL of the Gamayun gRPC server, `https://` enables TLS.
+    pub server_url: String,
+    /// Unique name of the worker.
+    pub name: String,
+    /// Labels matched against the `run_on` selector of jobs.
+    pub labels: HashMap<String, String>,
+    /// Static token sent to Gamayun, which requires one from every worker.
+    pub token: Option<String>,
+    /// PEM file with the CA the server certificate is verified against, in addition to the
+    /// system roots.
+    pub ca_cert_path: Option<String>,
+    /// PEM files with the client certificate and key, for servers requiring mutual TLS.
+    pub client_cert_path: Option<String>,
+    pub client_key_path: Option<String>,
+}
+
+impl WorkerConfig {
+    pub fn from_env() -> Result<Self> {
+        let labels = match env::var("GAMAYUN_WORKER_LABELS") {
+            Ok(labels) => parse_labels(&labels)?,
+            Err(_) => HashMap::new(),
+        };
+
+        Ok(WorkerConfig {
+            server_url: env::var("GAMAYUN_WORKER_SERVER_URL")
+                .unwrap_or_else(|_| "http://[::1]:16656".to_string()),
+            name: env::var("GAMAYUN_WORKER_NAME")
+                .context("GAMAYUN_WORKER_NAME environment variable is not set")?,
+            labels,
+            token: env::var("GAMAYUN_WORKER_TOKEN").ok(),
+            ca_cert_path: env::var("GAMAYUN_WORKER_CA_CERT").ok(),
+            client_cert_path: env::var("GAMAYUN_WORKER_CLIENT_CERT").ok(),
+            client_key_path: env::var("GAMAYUN_WORKER_CLIENT_KEY").ok(),
+        })
+    }
+
+    /// Builds the TLS configuration of the connection to the server.
+    pub fn tls_config(&self) -> Result<ClientTlsConfig> {
+        let mut tls_config = ClientTlsConfig::new().with_native_roots();
+
+        if let Some(ca_cert_path) = &self.ca_cert_path {
+            let ca_cert = fs::read(ca_cert_path)
+                .with_context(|| format!("Failed to read CA certificate {}", ca_cert_path))?;
+            tls_config = tls_config.ca_certificate(Certificate::from_pem(ca_cert));
+        }
+
+        match (&self.client_cert_path, &self.client_key_path) {
+            (Some(client_cert_path), Some(client_key_path)) => {
+                let cert = fs::read(client_cert_path).with_context(|| {
+                    format!("Failed to read client certificate {}", client_cert_path)
+                })?;
+                let key = fs::read(client_key_path)
+                    .with_context(|| format!("Failed to read client key {}", client_key_path))?;
+                tls_config = tls_config.identity(Identity::from_pem(cert, key));
+            }
+            (None, None) => {}
+            _ => bail!(
+                "GAMAYUN_WORKER_CLIENT_CERT and GAMAYUN_WORKER_CLIENT_KEY have to be set together"
+            ),
+        }
+
+        Ok(tls_config)
+    }
+}
+
+/// Parses labels given as `key=value` pairs separated by commas.
+fn parse_labels(labels: &str) -> Result<HashMap<String, String>> {
+    labels
+        .split(',')
+        .map(str::trim)
+        .filter(|label| !label.is_empty())
+        .map(|label| match label.split_once('=') {
+            Some((key, value)) => Ok((key.trim().to_string(), value.trim().to_string())),
+            None => bail!("Label {} is not in the key=value format", label),
+        })
+        .collect()
+}
@@ -0,0 +1,33 @@
+use crate::config::WorkerConfig;
+use crate::worker::Worker;
+use anyhow::Result;
+use tokio::signal;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+
+mod config;
+mod worker;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv::dotenv().ok();
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .init();
+
+    let config = WorkerConfig::from_env()?;
+    info!(
+        "Starting worker {} for Gamayun at {}",
+        config.name, config.server_url
+    );
+    let worker = Worker::new(config)?;
+
+    tokio::select! {
+        _ = worker.run() => {}
+        _ = signal::ctrl_c() => {
+            info!("Received Ctrl+C, shutting down the worker");
+        }
+    }
+
+    Ok(())
+}
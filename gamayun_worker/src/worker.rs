@@ -0,0 +1,206 @@
+use crate::config::WorkerConfig;
+use anyhow::{bail, Context, Result};
+use protos::gamayun::worker_service_client::WorkerServiceClient;
+use protos::gamayun::{JobAssignment, JobExit, WorkerIdentity, WorkerRegistration};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::process::Command;
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::service::interceptor::InterceptedService;
+use tonic::service::Interceptor;
+use tonic::transport::{Channel, Endpoint};
+use tonic::{Request, Status};
+use tracing::{error, info, warn};
+
+/// Metadata key Gamayun expects the static token under.
+const TOKEN_METADATA_KEY: &str = "x-gamayun-token";
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+const MIN_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Adds the static token to every request, if one is configured.
+#[derive(Clone)]
+struct TokenInterceptor {
+    token: Option<MetadataValue<Ascii>>,
+}
+
+impl Interceptor for TokenInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Some(token) = &self.token {
+            request
+                .metadata_mut()
+                .insert(TOKEN_METADATA_KEY, token.clone());
+        }
+        Ok(request)
+    }
+}
+
+type Client = WorkerServiceClient<InterceptedService<Channel, TokenInterceptor>>;
+
+/// Worker that registers with Gamayun, receives the runs assigned to it and executes them.
+pub struct Worker {
+    config: WorkerConfig,
+    client: Client,
+    /// ID of the current registration. Runs started under an earlier registration report their
+    /// exit with it, as Gamayun moves them to the new registration and forgets the old ID.
+    worker_id: Arc<RwLock<String>>,
+}
+
+impl Worker {
+    pub fn new(config: WorkerConfig) -> Result<Self> {
+        let mut endpoint = Endpoint::from_shared(config.server_url.clone())
+            .with_context(|| format!("Invalid server URL {}", config.server_url))?;
+        if config.server_url.starts_with("https://") {
+            endpoint = endpoint
+                .tls_config(config.tls_config()?)
+                .context("Failed to configure TLS")?;
+        }
+
+        let token = config
+            .token
+            .as_deref()
+            .map(MetadataValue::try_from)
+            .transpose()
+            .context("GAMAYUN_WORKER_TOKEN is not a valid metadata value")?;
+
+        let client = WorkerServiceClient::with_interceptor(
+            endpoint.connect_lazy(),
+            TokenInterceptor { token },
+        );
+
+        Ok(Worker {
+            config,
+            client,
+            worker_id: Arc::new(RwLock::new(String::new())),
+        })
+    }
+
+    /// Runs the worker, registering again whenever the connection to Gamayun is lost.
+    pub async fn run(&self) {
+        loop {
+            if let Err(e) = self.run_session().await {
+                warn!(
+                    "Lost the connection to Gamayun, reconnecting in {:?}: {:?}",
+                    RECONNECT_DELAY, e
+                );
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+
+    async fn run_session(&self) -> Result<()> {
+        let mut client = self.client.clone();
+
+        let registration = client
+            .register_worker(WorkerRegistration {
+                name: self.config.name.clone(),
+                labels: self.config.labels.clone(),
+            })
+            .await
+            .context("Failed to register")?
+            .into_inner();
+        let worker_id = registration.worker_id;
+        *self.worker_id.write().unwrap_or_else(|e| e.into_inner()) = worker_id.clone();
+        info!(
+            "Registered as {} with ID {} and labels {:?}",
+            self.config.name, worker_id, self.config.labels
+        );
+
+        let mut assignments = client
+            .poll_jobs(WorkerIdentity {
+                worker_id: worker_id.clone(),
+            })
+            .await
+            .context("Failed to poll for jobs")?
+            .into_inner();
+
+        let heartbeat_interval = Duration::from_millis(registration.heartbeat_interval_millis)
+            .max(MIN_HEARTBEAT_INTERVAL);
+        let mut heartbeat = tokio::time::interval(heartbeat_interval);
+
+        loop {
+            tokio::select! {
+                _ = heartbeat.tick() => {
+                    client
+                        .heartbeat(WorkerIdentity { worker_id: worker_id.clone() })
+                        .await
+                        .context("Failed to send heartbeat")?;
+                }
+                assignment = assignments.message() => {
+                    match assignment.context("Failed to receive job")? {
+                        Some(assignment) => {
+                            tokio::spawn(execute_assignment(
+                                self.client.clone(),
+                                self.config.server_url.clone(),
+                                self.worker_id.clone(),
+                                assignment,
+                            ));
+                        }
+                        None => bail!("Gamayun closed the job stream"),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Runs the assigned job like Gamayun runs local jobs and relays how it ended.
+async fn execute_assignment(
+    mut client: Client,
+    server_url: String,
+    worker_id: Arc<RwLock<String>>,
+    assignment: JobAssignment,
+) {
+    let run_information = assignment.run_information.unwrap_or_default();
+    info!(
+        "Executing job {} with run ID {}",
+        run_information.job_name, run_information.run_id
+    );
+
+    let status = Command::new(&assignment.path_to_executable)
+        .env("GAMAYUN_JOB_NAME", &run_information.job_name)
+        .env("GAMAYUN_JOB_UNIQUE_ID", &run_information.run_id)
+        .env("GAMAYUN_JOB_TOKEN", &assignment.token)
+        .env("GAMAYUN_JOB_ATTEMPT", assignment.attempt.max(1).to_string())
+        .env("GAMAYUN_SERVER_URL", &server_url)
+        .args(&assignment.arguments)
+        .status()
+        .await;
+
+    let (exit_code, error) = match status {
+        Ok(status) if status.success() => (status.code(), String::new()),
+        Ok(status) => (status.code(), status.to_string()),
+        Err(e) => (
+            None,
+            format!("Failed to start {}: {}", assignment.path_to_executable, e),
+        ),
+    };
+
+    if error.is_empty() {
+        info!(
+            "Job {} with run ID {} exited successfully",
+            run_information.job_name, run_information.run_id
+        );
+    } else {
+        error!(
+            "Job {} with run ID {} failed: {}",
+            run_information.job_name, run_information.run_id, error
+        );
+    }
+
+    let worker_id = worker_id.read().unwrap_or_else(|e| e.into_inner()).clone();
+    if let Err(e) = client
+        .report_job_exit(JobExit {
+            worker_id,
+            run_information: Some(run_information.clone()),
+            exit_code,
+            error,
+        })
+        .await
+    {
+        error!(
+            "Failed to report the exit of run ID {}: {:?}",
+            run_information.run_id, e
+        );
+    }
+}
@@ -5,9 +5,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 fn handle_protos() -> Result<(), Box<dyn std::error::Error>> {
     let include_paths: [&str; 1] = ["proto/"];
 
-    let protos = ["proto/result_reporting_service.proto"];
+    let protos = [
+        "proto/result_reporting_service.proto",
+        "proto/worker_service.proto",
+    ];
+
+    let descriptor_path =
+        std::path::PathBuf::from(std::env::var("OUT_DIR")?).join("gamayun_descriptor.bin");
 
     let config = tonic_build::configure()
+        .file_descriptor_set_path(descriptor_path)
         .type_attribute(".", "#[derive(serde::Serialize, serde::Deserialize)]")
         .type_attribute(".", "#[serde(rename_all = \"camelCase\")]");
 
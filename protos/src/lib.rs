@@ -2,4 +2,8 @@
 //by doing it like this, we decide in which module we want to import generated code
 pub mod gamayun {
     tonic::include_proto!("gamayun"); // The string specified here must match the proto package name
+
+    /// Encoded descriptors of the `gamayun` package, served by gRPC server reflection.
+    pub const FILE_DESCRIPTOR_SET: &[u8] =
+        tonic::include_file_descriptor_set!("gamayun_descriptor");
 }